@@ -0,0 +1,61 @@
+use std::hint::black_box;
+use std::path::PathBuf;
+
+use audio_io::*;
+use criterion::{Criterion, criterion_group, criterion_main};
+
+/// Write a 1 minute stereo file.
+fn stereo_file() -> PathBuf {
+    let path = std::env::temp_dir().join("audio_io_bench_stereo.wav");
+    if !path.exists() {
+        let sample_rate = 48000;
+        let num_frames = sample_rate as usize * 60;
+        let samples: Vec<f32> = (0..num_frames * 2)
+            .map(|i| ((i / 2) as f32 * 0.01).sin() * 0.5)
+            .collect();
+        let block = AudioBlockInterleavedView::from_slice(&samples, 2, num_frames);
+        audio_write(&path, block, sample_rate, AudioWriteConfig::default()).unwrap();
+    }
+    path
+}
+
+fn bench_full_read(c: &mut Criterion) {
+    let path = stereo_file();
+
+    let mut group = c.benchmark_group("full_read");
+
+    // whole packets are copied without conversion
+    group.bench_function("f32", |b| {
+        b.iter(|| {
+            let data: AudioData<f32> =
+                audio_read(black_box(&path), AudioReadConfig::default()).unwrap();
+            black_box(data)
+        })
+    });
+    // the same samples, but selected and converted sample by sample
+    group.bench_function("f32_converted", |b| {
+        b.iter(|| {
+            let data: AudioData<f32> = audio_read(
+                black_box(&path),
+                AudioReadConfig {
+                    decode_into: DecodeInto::F64,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            black_box(data)
+        })
+    });
+    group.bench_function("f64", |b| {
+        b.iter(|| {
+            let data: AudioData<f64> =
+                audio_read(black_box(&path), AudioReadConfig::default()).unwrap();
+            black_box(data)
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_full_read);
+criterion_main!(benches);
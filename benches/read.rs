@@ -0,0 +1,65 @@
+use std::hint::black_box;
+use std::path::PathBuf;
+
+use audio_io::*;
+use criterion::{Criterion, criterion_group, criterion_main};
+
+/// Write a 10 minute stereo file, large enough that IO dominates.
+fn large_file() -> PathBuf {
+    let path = std::env::temp_dir().join("audio_io_bench_large.wav");
+    if !path.exists() {
+        let sample_rate = 48000;
+        let num_frames = sample_rate as usize * 600;
+        let samples: Vec<f32> = (0..num_frames * 2)
+            .map(|i| ((i / 2) as f32 * 0.01).sin() * 0.5)
+            .collect();
+        let block = AudioBlockInterleavedView::from_slice(&samples, 2, num_frames);
+        audio_write(&path, block, sample_rate, AudioWriteConfig::default()).unwrap();
+    }
+    path
+}
+
+fn bench_read(c: &mut Criterion) {
+    let path = large_file();
+
+    let mut group = c.benchmark_group("read");
+    group.sample_size(10);
+
+    group.bench_function("file", |b| {
+        b.iter(|| {
+            let data: AudioData<f32> =
+                audio_read(black_box(&path), AudioReadConfig::default()).unwrap();
+            black_box(data)
+        })
+    });
+    group.bench_function("mmap", |b| {
+        b.iter(|| {
+            let data: AudioData<f32> =
+                audio_read_mmap(black_box(&path), AudioReadConfig::default()).unwrap();
+            black_box(data)
+        })
+    });
+
+    // seeking close to the end only touches a small part of the file
+    let late_start = || AudioReadConfig {
+        start: Position::Time(std::time::Duration::from_secs(590)),
+        ..Default::default()
+    };
+    group.bench_function("file_late_start", |b| {
+        b.iter(|| {
+            let data: AudioData<f32> = audio_read(black_box(&path), late_start()).unwrap();
+            black_box(data)
+        })
+    });
+    group.bench_function("mmap_late_start", |b| {
+        b.iter(|| {
+            let data: AudioData<f32> = audio_read_mmap(black_box(&path), late_start()).unwrap();
+            black_box(data)
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_read);
+criterion_main!(benches);
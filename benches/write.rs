@@ -0,0 +1,38 @@
+use std::hint::black_box;
+
+use audio_io::writer::WriteSampleFormat;
+use audio_io::*;
+use criterion::{Criterion, criterion_group, criterion_main};
+
+/// Write 10 seconds of 8 channels as 16-bit, compare with and without the `simd` feature.
+fn bench_write_i16(c: &mut Criterion) {
+    let sample_rate = 48000;
+    let num_channels = 8;
+    let num_frames = sample_rate as usize * 10;
+    let samples: Vec<f32> = (0..num_frames * num_channels)
+        .map(|i| (i as f32 * 0.001).sin() * 1.1)
+        .collect();
+    let path = std::env::temp_dir().join("audio_io_bench_write.wav");
+
+    c.bench_function("write_i16", |b| {
+        b.iter(|| {
+            let block =
+                AudioBlockInterleavedView::from_slice(&samples, num_channels as u16, num_frames);
+            audio_write(
+                black_box(&path),
+                block,
+                sample_rate,
+                AudioWriteConfig {
+                    sample_format: WriteSampleFormat::Int16,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        })
+    });
+
+    let _ = std::fs::remove_file(path);
+}
+
+criterion_group!(benches, bench_write_i16);
+criterion_main!(benches);
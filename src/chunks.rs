@@ -0,0 +1,393 @@
+//! Parsing of container chunks with metadata that symphonia does not read.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::reader::{Cue, InstrumentInfo, LoopRegion};
+
+/// Markers, loops and the position of the audio data found in the chunks of a file
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Markers {
+    pub cues: Vec<Cue>,
+    pub loops: Vec<LoopRegion>,
+    pub instrument: Option<InstrumentInfo>,
+    /// Byte offset and length of the samples in the file
+    pub data_chunk: Option<(u64, u64)>,
+}
+
+/// Read the markers of a file, identified by its magic bytes.
+///
+/// Unknown formats result in empty markers, the reader is left at an arbitrary position.
+pub(crate) fn read_markers<R: Read + Seek>(reader: &mut R) -> std::io::Result<Markers> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    match &magic {
+        b"caff" => read_caf_markers(reader),
+        b"RIFF" => read_wav_markers(reader),
+        b"FORM" => read_aiff_markers(reader),
+        _ => Ok(Markers::default()),
+    }
+}
+
+/// Marker types of regions in CAF files
+const CAF_REGION_START: [u8; 4] = *b"rbeg";
+const CAF_REGION_END: [u8; 4] = *b"rend";
+const CAF_SUSTAIN_LOOP_START: [u8; 4] = *b"slbg";
+const CAF_SUSTAIN_LOOP_END: [u8; 4] = *b"slen";
+/// Region flag for regions that are played as loop
+const CAF_REGION_LOOP_ENABLE: u32 = 1;
+
+struct CafMarker {
+    marker_type: [u8; 4],
+    frame: f64,
+    string_id: u32,
+}
+
+/// Parse the `mark`, `regn` and `strg` chunks of a CAF file, after the 4 magic bytes.
+///
+/// All values in CAF files are big endian.
+fn read_caf_markers<R: Read + Seek>(reader: &mut R) -> std::io::Result<Markers> {
+    // version and flags
+    read_bytes::<4, _>(reader)?;
+
+    let mut markers = Vec::new();
+    let mut regions = Vec::new();
+    let mut strings = Vec::new();
+
+    loop {
+        let chunk_type = match read_bytes::<4, _>(reader) {
+            Ok(chunk_type) => chunk_type,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        };
+        let size = i64::from_be_bytes(read_bytes(reader)?);
+        // only the data chunk can have an unknown size (-1), which extends to the end
+        if size < 0 {
+            break;
+        }
+        let next = reader.stream_position()? + size as u64;
+
+        match &chunk_type {
+            b"mark" => {
+                let _smpte_time_type = read_u32_be(reader)?;
+                for _ in 0..read_u32_be(reader)? {
+                    markers.push(read_caf_marker(reader)?);
+                }
+            }
+            b"regn" => {
+                let _smpte_time_type = read_u32_be(reader)?;
+                for _ in 0..read_u32_be(reader)? {
+                    let _region_id = read_u32_be(reader)?;
+                    let flags = read_u32_be(reader)?;
+                    let region_markers = (0..read_u32_be(reader)?)
+                        .map(|_| read_caf_marker(reader))
+                        .collect::<std::io::Result<Vec<_>>>()?;
+                    regions.push((flags, region_markers));
+                }
+            }
+            b"strg" => {
+                let num_entries = read_u32_be(reader)?;
+                let ids = (0..num_entries)
+                    .map(|_| {
+                        let id = read_u32_be(reader)?;
+                        let offset = i64::from_be_bytes(read_bytes(reader)?);
+                        Ok((id, offset as usize))
+                    })
+                    .collect::<std::io::Result<Vec<_>>>()?;
+                let header_len = 4 + 12 * num_entries as u64;
+                let data = read_vec(reader, (size as u64).saturating_sub(header_len))?;
+                for (id, offset) in ids {
+                    let bytes = data.get(offset..).unwrap_or_default();
+                    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+                    strings.push((id, String::from_utf8_lossy(&bytes[..end]).into_owned()));
+                }
+            }
+            _ => {}
+        }
+
+        reader.seek(SeekFrom::Start(next))?;
+    }
+
+    let label = |string_id| {
+        strings
+            .iter()
+            .find(|(id, _)| *id == string_id)
+            .map(|(_, label)| label.clone())
+            .unwrap_or_default()
+    };
+
+    let mut result = Markers::default();
+    let mut sustain_start = None;
+    for marker in &markers {
+        match marker.marker_type {
+            CAF_SUSTAIN_LOOP_START => sustain_start = Some(marker.frame as usize),
+            CAF_SUSTAIN_LOOP_END => {
+                if let Some(start_frame) = sustain_start.take() {
+                    result.loops.push(LoopRegion {
+                        start_frame,
+                        end_frame: marker.frame as usize,
+                    });
+                }
+            }
+            _ => result.cues.push(Cue {
+                label: label(marker.string_id),
+                frame: marker.frame as usize,
+            }),
+        }
+    }
+
+    for (flags, region_markers) in regions {
+        if flags & CAF_REGION_LOOP_ENABLE == 0 {
+            continue;
+        }
+        let find = |marker_type| {
+            region_markers
+                .iter()
+                .find(|m| m.marker_type == marker_type)
+                .map(|m| m.frame as usize)
+        };
+        if let (Some(start_frame), Some(end_frame)) = (find(CAF_REGION_START), find(CAF_REGION_END))
+        {
+            result.loops.push(LoopRegion {
+                start_frame,
+                end_frame,
+            });
+        }
+    }
+
+    Ok(result)
+}
+
+/// Parse the `cue `, `LIST` (`adtl` labels) and `smpl` chunks of a WAV file, after the 4 magic
+/// bytes.
+///
+/// All values in WAV files are little endian.
+fn read_wav_markers<R: Read + Seek>(reader: &mut R) -> std::io::Result<Markers> {
+    let _riff_size = read_u32_le(reader)?;
+    if &read_bytes::<4, _>(reader)? != b"WAVE" {
+        return Ok(Markers::default());
+    }
+
+    let mut result = Markers::default();
+    // cue point ids with their frames, and the labels of the ids
+    let mut cue_points = Vec::new();
+    let mut labels = Vec::new();
+    loop {
+        let chunk_id = match read_bytes::<4, _>(reader) {
+            Ok(chunk_id) => chunk_id,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        };
+        let size = read_u32_le(reader)?;
+        let start = reader.stream_position()?;
+        // chunks are aligned to 2 bytes
+        let next = start + size as u64 + size as u64 % 2;
+
+        if &chunk_id == b"data" {
+            result.data_chunk = Some((start, size as u64));
+        } else if &chunk_id == b"cue " {
+            for _ in 0..read_u32_le(reader)? {
+                let id = read_u32_le(reader)?;
+                // position, data chunk id, chunk start and block start
+                read_bytes::<16, _>(reader)?;
+                let sample_offset = read_u32_le(reader)?;
+                cue_points.push((id, sample_offset as usize));
+            }
+        } else if &chunk_id == b"LIST" && size >= 4 && &read_bytes::<4, _>(reader)? == b"adtl" {
+            let end = start + size as u64;
+            while reader.stream_position()? + 8 <= end {
+                let sub_id = read_bytes::<4, _>(reader)?;
+                let sub_size = read_u32_le(reader)? as u64;
+                let sub_start = reader.stream_position()?;
+                let sub_next = sub_start + sub_size + sub_size % 2;
+                // labels that don't fit into the list are skipped
+                if &sub_id == b"labl" && sub_size >= 4 && sub_start + sub_size <= end {
+                    let id = read_u32_le(reader)?;
+                    let text = read_vec(reader, sub_size - 4)?;
+                    let end = text.iter().position(|b| *b == 0).unwrap_or(text.len());
+                    labels.push((id, String::from_utf8_lossy(&text[..end]).into_owned()));
+                }
+                reader.seek(SeekFrom::Start(sub_next))?;
+            }
+        } else if &chunk_id == b"smpl" {
+            // manufacturer, product and sample period
+            read_bytes::<12, _>(reader)?;
+            let midi_unity_note = read_u32_le(reader)?;
+            // pitch fraction, SMPTE format and offset
+            read_bytes::<12, _>(reader)?;
+            let num_loops = read_u32_le(reader)?;
+            let _sampler_data = read_u32_le(reader)?;
+
+            let mut loops = Vec::new();
+            for _ in 0..num_loops {
+                let _cue_point_id = read_u32_le(reader)?;
+                let _loop_type = read_u32_le(reader)?;
+                let start = read_u32_le(reader)?;
+                let end = read_u32_le(reader)?;
+                // fraction and play count
+                read_bytes::<8, _>(reader)?;
+                // the end in the file is the last frame that is played
+                loops.push(LoopRegion {
+                    start_frame: start as usize,
+                    end_frame: end as usize + 1,
+                });
+            }
+            result.instrument = Some(InstrumentInfo {
+                midi_unity_note: midi_unity_note.min(127) as u8,
+                loops,
+            });
+        }
+
+        reader.seek(SeekFrom::Start(next))?;
+    }
+
+    result.cues = cue_points
+        .into_iter()
+        .map(|(id, frame)| Cue {
+            label: labels
+                .iter()
+                .find(|(label_id, _)| *label_id == id)
+                .map(|(_, label)| label.clone())
+                .unwrap_or_default(),
+            frame,
+        })
+        .collect();
+
+    Ok(result)
+}
+
+/// Find the `SSND` chunk of an AIFF or AIFF-C file, after the 4 magic bytes.
+///
+/// All values in AIFF files are big endian.
+fn read_aiff_markers<R: Read + Seek>(reader: &mut R) -> std::io::Result<Markers> {
+    let _form_size = read_u32_be(reader)?;
+    if !matches!(&read_bytes::<4, _>(reader)?, b"AIFF" | b"AIFC") {
+        return Ok(Markers::default());
+    }
+
+    let mut result = Markers::default();
+    loop {
+        let chunk_id = match read_bytes::<4, _>(reader) {
+            Ok(chunk_id) => chunk_id,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        };
+        let size = read_u32_be(reader)?;
+        // chunks are aligned to 2 bytes
+        let next = reader.stream_position()? + size as u64 + size as u64 % 2;
+
+        if &chunk_id == b"SSND" {
+            // the samples start after the offset and block size fields and the offset
+            let offset = read_u32_be(reader)? as u64;
+            let _block_size = read_u32_be(reader)?;
+            let start = reader.stream_position()? + offset;
+            let len = (size as u64).saturating_sub(8 + offset);
+            result.data_chunk = Some((start, len));
+        }
+
+        reader.seek(SeekFrom::Start(next))?;
+    }
+
+    Ok(result)
+}
+
+fn read_caf_marker<R: Read>(reader: &mut R) -> std::io::Result<CafMarker> {
+    let marker_type = read_bytes(reader)?;
+    let frame = f64::from_be_bytes(read_bytes(reader)?);
+    let string_id = read_u32_be(reader)?;
+    // SMPTE time and channel
+    read_bytes::<10, _>(reader)?;
+    Ok(CafMarker {
+        marker_type,
+        frame,
+        string_id,
+    })
+}
+
+fn read_bytes<const N: usize, R: Read>(reader: &mut R) -> std::io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Read `len` bytes, where `len` comes from the file. The buffer grows with the bytes that
+/// are actually read, so a corrupt length can't allocate more than the rest of the file.
+fn read_vec<R: Read>(reader: &mut R, len: u64) -> std::io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.by_ref().take(len).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < len {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+fn read_u32_be<R: Read>(reader: &mut R) -> std::io::Result<u32> {
+    Ok(u32::from_be_bytes(read_bytes(reader)?))
+}
+
+fn read_u32_le<R: Read>(reader: &mut R) -> std::io::Result<u32> {
+    Ok(u32::from_le_bytes(read_bytes(reader)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_caf_strg_size() {
+        // a string chunk that claims to be much larger than the file
+        let mut caf = b"caff".to_vec();
+        caf.extend([0, 1, 0, 0]);
+        caf.extend(b"strg");
+        caf.extend((1i64 << 60).to_be_bytes());
+        caf.extend(1u32.to_be_bytes());
+        caf.extend(1u32.to_be_bytes());
+        caf.extend(0i64.to_be_bytes());
+        caf.extend(b"label\0");
+
+        let err = read_markers(&mut Cursor::new(caf)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_wav_label_size() {
+        let wav = |labels: &[(u32, &[u8])]| {
+            let mut adtl = b"adtl".to_vec();
+            for (size, text) in labels {
+                adtl.extend(b"labl");
+                adtl.extend(size.to_le_bytes());
+                adtl.extend(text.iter());
+            }
+            let mut wav = b"RIFF".to_vec();
+            wav.extend(0u32.to_le_bytes());
+            wav.extend(b"WAVE");
+            // one cue point with the id 1 at frame 10
+            wav.extend(b"cue ");
+            wav.extend(28u32.to_le_bytes());
+            wav.extend(1u32.to_le_bytes());
+            wav.extend(1u32.to_le_bytes());
+            wav.extend([0; 16]);
+            wav.extend(10u32.to_le_bytes());
+            wav.extend(b"LIST");
+            wav.extend((adtl.len() as u32).to_le_bytes());
+            wav.extend(adtl);
+            wav
+        };
+
+        // too short for the cue point id, larger than the list, and a valid label
+        let markers = read_markers(&mut Cursor::new(wav(&[
+            (2, b"\0\0"),
+            (u32::MAX, b"\x01\0\0\0"),
+        ])))
+        .unwrap();
+        assert_eq!(markers.cues.len(), 1);
+        assert_eq!(markers.cues[0].label, "");
+        let markers = read_markers(&mut Cursor::new(wav(&[
+            (3, b"\0\0\0\0"),
+            (8, b"\x01\0\0\0cue\0"),
+        ])))
+        .unwrap();
+        assert_eq!(markers.cues[0].frame, 10);
+        assert_eq!(markers.cues[0].label, "cue");
+    }
+}
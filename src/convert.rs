@@ -0,0 +1,99 @@
+//! Conversion of float samples to integers for writing.
+//!
+//! With the `simd` feature, 8 samples are converted at once with SSE2 on x86_64, other targets
+//! use the scalar loop. Both produce the same samples: the input is clamped, scaled with
+//! `i16::MAX` and truncated towards zero, NaN becomes 0.
+
+/// Clamp the samples to `min..=max`, scale them to 16 bits and append them to `out`.
+pub(crate) fn f32_to_i16(samples: &[f32], min: f32, max: f32, out: &mut Vec<i16>) {
+    out.reserve(samples.len());
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    let samples = {
+        let chunks = samples.chunks_exact(8);
+        let rest = chunks.remainder();
+        for chunk in chunks {
+            // SAFETY: SSE2 is part of every x86_64 target
+            out.extend_from_slice(&unsafe { sse2::f32_to_i16(chunk, min, max) });
+        }
+        rest
+    };
+
+    out.extend(
+        samples
+            .iter()
+            .map(|sample| sample_to_i16(*sample, min, max)),
+    );
+}
+
+/// Scalar conversion of a single sample, the reference for the SIMD path.
+fn sample_to_i16(sample: f32, min: f32, max: f32) -> i16 {
+    num::ToPrimitive::to_i16(&(sample.clamp(min, max) * i16::MAX as f32)).unwrap_or(0)
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod sse2 {
+    use std::arch::x86_64::*;
+
+    /// Convert 8 samples, `chunk` must have a length of 8.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn f32_to_i16(chunk: &[f32], min: f32, max: f32) -> [i16; 8] {
+        debug_assert_eq!(chunk.len(), 8);
+        let (min, max, scale) = (
+            _mm_set1_ps(min),
+            _mm_set1_ps(max),
+            _mm_set1_ps(i16::MAX as f32),
+        );
+        let convert = |x: __m128| {
+            // NaN becomes the lower bound in max_ps, set it to 0 after clamping like the scalar
+            // path, also if the range does not contain 0
+            let clamped = _mm_min_ps(_mm_max_ps(x, min), max);
+            let clamped = _mm_and_ps(clamped, _mm_cmpord_ps(x, x));
+            _mm_cvttps_epi32(_mm_mul_ps(clamped, scale))
+        };
+
+        let mut out = [0i16; 8];
+        // SAFETY: the chunk has 8 samples and the output 8 integers, unaligned access is used
+        unsafe {
+            let low = convert(_mm_loadu_ps(chunk.as_ptr()));
+            let high = convert(_mm_loadu_ps(chunk.as_ptr().add(4)));
+            _mm_storeu_si128(out.as_mut_ptr().cast(), _mm_packs_epi32(low, high));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_f32_to_i16() {
+        let mut samples: Vec<f32> = (0..1003).map(|i| (i as f32 * 0.37).sin() * 1.2).collect();
+        samples.extend([f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -0.0, 1.0, -1.0]);
+
+        for (min, max) in [(-1.0, 1.0), (-0.5, 0.25), (0.25, 0.5)] {
+            let mut out = Vec::new();
+            f32_to_i16(&samples, min, max, &mut out);
+            let expected: Vec<i16> = samples
+                .iter()
+                .map(|s| sample_to_i16(*s, min, max))
+                .collect();
+            assert_eq!(out, expected);
+        }
+
+        let mut out = Vec::new();
+        f32_to_i16(&[f32::NAN, 1.0, -1.0, 0.5], -1.0, 1.0, &mut out);
+        assert_eq!(out, [0, i16::MAX, -i16::MAX, 16383]);
+
+        // NaN is 0 even if the range excludes it, in a full chunk of 8 samples
+        let mut out = Vec::new();
+        f32_to_i16(
+            &[f32::NAN, 0.0, 1.0, 0.3, f32::NAN, -1.0, 0.5, 0.25],
+            0.25,
+            0.5,
+            &mut out,
+        );
+        assert_eq!(out, [0, 8191, 16383, 9830, 0, 8191, 16383, 8191]);
+    }
+}
@@ -0,0 +1,193 @@
+//! Dither for quantizing float samples to integers.
+//!
+//! Without dither, the quantization error of quiet signals is correlated with the signal and
+//! audible as harmonic distortion. TPDF dither turns it into white noise that is independent
+//! of the signal. Noise shaping additionally feeds the quantization error back through a
+//! filter, which moves the noise towards high frequencies, where the ear is less sensitive.
+//! The total noise power increases, but the noise in the sensitive mid range decreases.
+//! The error feedback works sample by sample, so it adds no latency.
+
+/// Dither added before quantizing to integer samples
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Dither {
+    /// No dither, samples are truncated
+    #[default]
+    None,
+    /// Rectangular (RPDF) dither with an amplitude of 1 LSB. It removes the harmonic distortion,
+    /// but the noise level still depends on the signal.
+    Rectangular,
+    /// Triangular (TPDF) dither with an amplitude of 1 LSB, which results in white noise
+    Tpdf,
+    /// TPDF dither with the quantization error shaped by the given curve
+    NoiseShaped(ShaperCurve),
+}
+
+/// Filter applied to the quantization error with [`Dither::NoiseShaped`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ShaperCurve {
+    /// First order highpass `1 - z⁻¹`, noise rises with 6 dB per octave
+    FirstOrder,
+    /// Second order highpass `(1 - z⁻¹)²`, noise rises with 12 dB per octave.
+    /// Moves more noise out of the mid range, but has the highest total noise power.
+    #[default]
+    SecondOrder,
+}
+
+impl ShaperCurve {
+    /// Feedback coefficients for the previous errors
+    fn coefficients(&self) -> &'static [f64] {
+        match self {
+            Self::FirstOrder => &[-1.0],
+            Self::SecondOrder => &[-2.0, 1.0],
+        }
+    }
+}
+
+/// Seed of the dither noise if none is given, so the output is reproducible by default
+const DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Quantizes samples with dither, keeping the noise shaping state of each channel.
+/// Not used for [`Dither::None`].
+pub(crate) struct Quantizer {
+    rectangular: bool,
+    coefficients: &'static [f64],
+    /// Previous quantization errors of each channel, most recent first
+    errors: Vec<[f64; 2]>,
+    rng: XorShift,
+}
+
+impl Quantizer {
+    pub(crate) fn new(dither: Dither, num_channels: usize, seed: Option<u64>) -> Self {
+        let coefficients = match dither {
+            Dither::NoiseShaped(curve) => curve.coefficients(),
+            _ => &[],
+        };
+        Self {
+            rectangular: dither == Dither::Rectangular,
+            coefficients,
+            errors: vec![[0.0; 2]; num_channels],
+            rng: XorShift::new(seed.unwrap_or(DEFAULT_SEED)),
+        }
+    }
+
+    /// Quantize a sample that is already scaled to the integer range, rounding to the nearest
+    /// integer after adding dither. The result is not clamped.
+    pub(crate) fn quantize(&mut self, channel: usize, sample: f64) -> f64 {
+        let errors = &mut self.errors[channel];
+        let shaped = sample
+            + self
+                .coefficients
+                .iter()
+                .zip(errors.iter())
+                .map(|(c, e)| c * e)
+                .sum::<f64>();
+
+        let dither = if self.rectangular {
+            self.rng.next_f64() - 0.5
+        } else {
+            self.rng.next_f64() - self.rng.next_f64()
+        };
+        let quantized = (shaped + dither).round();
+
+        errors[1] = errors[0];
+        errors[0] = quantized - shaped;
+        quantized
+    }
+}
+
+/// Small and fast pseudo random number generator, which is good enough for dither.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // the state must never be zero
+        Self(seed.max(1))
+    }
+
+    /// Uniformly distributed in 0.0..1.0
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    /// Quantize a quiet 1 kHz tone and return the output.
+    fn quantize_tone(dither: Dither) -> Vec<f64> {
+        let mut quantizer = Quantizer::new(dither, 1, None);
+        (0..4800)
+            .map(|n| {
+                let x = 4.0 * (2.0 * std::f64::consts::PI * 1000.0 * n as f64 / SAMPLE_RATE).sin();
+                quantizer.quantize(0, x)
+            })
+            .collect()
+    }
+
+    /// Energy in the given frequency band, computed with a DFT.
+    fn band_energy(signal: &[f64], low: f64, high: f64) -> f64 {
+        let bin_width = SAMPLE_RATE / signal.len() as f64;
+        let bins = (low / bin_width) as usize..=(high / bin_width) as usize;
+        bins.map(|k| {
+            let (mut re, mut im) = (0.0, 0.0);
+            for (n, x) in signal.iter().enumerate() {
+                let phase = 2.0 * std::f64::consts::PI * (k * n) as f64 / signal.len() as f64;
+                re += x * phase.cos();
+                im -= x * phase.sin();
+            }
+            re * re + im * im
+        })
+        .sum()
+    }
+
+    #[test]
+    fn test_noise_shaping() {
+        let flat = band_energy(&quantize_tone(Dither::Tpdf), 2000.0, 4000.0);
+        let first = band_energy(
+            &quantize_tone(Dither::NoiseShaped(ShaperCurve::FirstOrder)),
+            2000.0,
+            4000.0,
+        );
+        let second = band_energy(
+            &quantize_tone(Dither::NoiseShaped(ShaperCurve::SecondOrder)),
+            2000.0,
+            4000.0,
+        );
+
+        assert!(first < flat / 2.0, "{first} >= {flat} / 2");
+        assert!(second < first, "{second} >= {first}");
+    }
+
+    #[test]
+    fn test_tpdf_decorrelates() {
+        // rounding alone would always result in 0, with dither the average converges to the input
+        for dither in [Dither::Tpdf, Dither::Rectangular] {
+            let mut quantizer = Quantizer::new(dither, 1, None);
+            let mean = (0..100000).map(|_| quantizer.quantize(0, 0.4)).sum::<f64>() / 100000.0;
+            approx::assert_abs_diff_eq!(mean, 0.4, epsilon = 0.02);
+        }
+
+        // rectangular dither of 1 LSB never moves a sample by more than one step
+        let mut quantizer = Quantizer::new(Dither::Rectangular, 1, None);
+        assert!((0..1000).all(|_| (0.0..=1.0).contains(&quantizer.quantize(0, 0.4))));
+    }
+
+    #[test]
+    fn test_seed() {
+        let quantize = |seed| {
+            let mut quantizer = Quantizer::new(Dither::Tpdf, 1, seed);
+            (0..1000)
+                .map(|_| quantizer.quantize(0, 0.4))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(quantize(None), quantize(None));
+        assert_eq!(quantize(Some(7)), quantize(Some(7)));
+        assert_ne!(quantize(Some(7)), quantize(Some(8)));
+    }
+}
@@ -1,14 +1,76 @@
 #![doc = include_str!("../README.md")]
 
 #[cfg(feature = "read")]
-pub use reader::{AudioData, AudioReadConfig, AudioReadError, Position, audio_read};
+pub use reader::{
+    AudioData, AudioInfo, AudioReadConfig, AudioReadError, AudioReader, AudioStats, BlockReader,
+    ChannelLabel, Container, Cue, DecodeInto, InstrumentInfo, LoopRegion, MixError, MonoSelect,
+    PackedAudio, PacketInfo, PadMode, PartialWindow, PlanarAudio, PlanarLayout, PlanarMode,
+    Position, ReadStats, SampleFormat, SourceFormat, TrackInfo, TrackSelection, Window,
+    audio_energy_envelope, audio_is_dual_mono, audio_probe_info, audio_read, audio_read_channel,
+    audio_read_cover_art, audio_read_file, audio_read_from_bytes, audio_read_from_source,
+    audio_read_packed, audio_read_packets, audio_read_planar, audio_read_region_by_cues,
+    audio_read_segment, audio_read_stdin, audio_read_tags, audio_read_windows,
+    audio_read_with_stats, audio_validate, open_format, supported_read_formats,
+};
 
+#[cfg(feature = "mmap")]
+pub use reader::audio_read_mmap;
+
+#[cfg(feature = "async")]
+pub use reader::audio_read_async;
+
+#[cfg(feature = "read")]
+pub use tags::{CoverArt, Tags};
+
+#[cfg(all(feature = "test-util", feature = "read"))]
+pub use test_util::assert_audio_eq;
+
+#[cfg(feature = "write")]
+pub use dither::{Dither, ShaperCurve};
 #[cfg(feature = "write")]
-pub use writer::{AudioWriteConfig, AudioWriteError, audio_write};
+pub use writer::{
+    AudioWriteConfig, AudioWriteConfigBuilder, AudioWriteError, ByteOrder, FourCC,
+    StreamingWavWriter, WriteReport, audio_write, audio_write_to_writer, audio_write_with_report,
+    supported_write_formats,
+};
+
+#[cfg(feature = "hash")]
+pub use reader::audio_content_hash;
+#[cfg(feature = "resample")]
+pub use resample::{ResampleError, resample};
+
+#[cfg(all(feature = "read", feature = "write"))]
+pub use transcode::{TranscodeError, audio_copy_or_transcode, transcode};
+
+pub use sample_rate::{IntoSampleRate, InvalidSampleRate, SampleRate};
 
 pub use audio_blocks::*;
+/// The symphonia version used for decoding, for [`reader::open_format`]
+#[cfg(feature = "read")]
+pub use symphonia;
 
+#[cfg(feature = "read")]
+mod chunks;
+#[cfg(feature = "write")]
+mod convert;
+#[cfg(feature = "write")]
+pub mod dither;
+#[cfg(all(feature = "limiter", feature = "write"))]
+mod limiter;
 #[cfg(feature = "read")]
 pub mod reader;
+#[cfg(feature = "resample")]
+pub mod resample;
+pub mod sample_rate;
+#[cfg(feature = "read")]
+pub mod tags;
+#[cfg(any(test, feature = "test-util"))]
+mod test_util;
+#[cfg(feature = "timestretch")]
+mod timestretch;
+#[cfg(all(feature = "read", feature = "write"))]
+pub mod transcode;
+#[cfg(feature = "read")]
+mod w64;
 #[cfg(feature = "write")]
 pub mod writer;
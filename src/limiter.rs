@@ -0,0 +1,227 @@
+//! True-peak limiter for writing.
+//!
+//! Peaks between samples are found by 4 times oversampling with a windowed sinc interpolator,
+//! like the true-peak meters of ITU-R BS.1770. Where a peak is above the ceiling, the gain is
+//! lowered for all channels. The gain starts to decrease up to 1.5 ms in front of the peak
+//! (look-ahead) and rises linearly after it, by at most full scale in 50 ms. The whole block is
+//! processed before it is written, so the output is not delayed.
+
+use std::collections::VecDeque;
+
+use num::Float;
+
+/// Factor of the oversampling used for finding peaks between samples
+const OVERSAMPLING: usize = 4;
+/// Samples on each side of the interpolated position
+const HALF_TAPS: usize = 12;
+const LOOKAHEAD_SECONDS: f64 = 0.0015;
+const RELEASE_SECONDS: f64 = 0.05;
+/// The gain is applied sample by sample, so a peak can stay slightly above the ceiling after
+/// the first pass
+const MAX_PASSES: usize = 4;
+/// The gain aims about 0.001 dB below the ceiling, so rounding errors don't leave peaks above it
+const MARGIN: f64 = 0.9999;
+
+/// Lower the gain of the interleaved `samples` where the true peak exceeds `ceiling` (linear).
+pub(crate) fn limit<F: Float>(
+    samples: &mut [F],
+    num_channels: usize,
+    sample_rate: u32,
+    ceiling: f64,
+) {
+    let lookahead = ((LOOKAHEAD_SECONDS * sample_rate as f64) as usize).max(HALF_TAPS);
+    // largest increase of the gain per frame
+    let release = 1.0 / (RELEASE_SECONDS * sample_rate as f64);
+
+    for _ in 0..MAX_PASSES {
+        let peaks = true_peaks(samples, num_channels);
+        if peaks.iter().all(|peak| *peak <= ceiling) {
+            return;
+        }
+        let target: Vec<f64> = peaks
+            .iter()
+            .map(|peak| {
+                if *peak > ceiling {
+                    ceiling * MARGIN / peak
+                } else {
+                    1.0
+                }
+            })
+            .collect();
+        // every value of the average is a minimum over a window that contains the frame, so
+        // the smoothed gain is never above the target. The reduction is averaged instead of
+        // the gain, so frames without reduction keep a gain of exactly 1.
+        let reduction: Vec<f64> = sliding_min(&target, lookahead)
+            .iter()
+            .map(|gain| 1.0 - gain)
+            .collect();
+        let smoothed = moving_average(&reduction, lookahead / 2)
+            .into_iter()
+            .map(|reduction| 1.0 - reduction);
+
+        let mut gain = 1.0;
+        for (frame, target) in samples.chunks_exact_mut(num_channels).zip(smoothed) {
+            gain = target.min(gain + release);
+            let gain = F::from(gain).unwrap_or(F::zero());
+            frame.iter_mut().for_each(|sample| *sample = *sample * gain);
+        }
+    }
+}
+
+/// Highest absolute value of all channels for every frame, including the interpolated values
+/// up to the next frame. Non-finite samples are treated as silence.
+pub(crate) fn true_peaks<F: Float>(samples: &[F], num_channels: usize) -> Vec<f64> {
+    let kernel = kernel();
+    let num_frames = samples.len() / num_channels;
+    let sample = |frame: isize, channel: usize| {
+        if frame < 0 || frame as usize >= num_frames {
+            return 0.0;
+        }
+        let sample = samples[frame as usize * num_channels + channel]
+            .to_f64()
+            .unwrap_or(0.0);
+        if sample.is_finite() { sample } else { 0.0 }
+    };
+
+    (0..num_frames as isize)
+        .map(|frame| {
+            let mut peak = 0.0f64;
+            for channel in 0..num_channels {
+                peak = peak.max(sample(frame, channel).abs());
+                for phase in &kernel {
+                    let interpolated: f64 = phase
+                        .iter()
+                        .enumerate()
+                        .map(|(tap, weight)| {
+                            weight * sample(frame + tap as isize + 1 - HALF_TAPS as isize, channel)
+                        })
+                        .sum();
+                    peak = peak.max(interpolated.abs());
+                }
+            }
+            peak
+        })
+        .collect()
+}
+
+/// Weights of the samples `frame - HALF_TAPS + 1..=frame + HALF_TAPS` for the positions
+/// `frame + k / OVERSAMPLING` between two frames.
+fn kernel() -> Vec<[f64; 2 * HALF_TAPS]> {
+    (1..OVERSAMPLING)
+        .map(|k| {
+            let offset = k as f64 / OVERSAMPLING as f64;
+            std::array::from_fn(|tap| {
+                let t = tap as f64 + 1.0 - HALF_TAPS as f64 - offset;
+                // Hann window over the length of the kernel
+                let window = 0.5 + 0.5 * (std::f64::consts::PI * t / HALF_TAPS as f64).cos();
+                sinc(t) * window
+            })
+        })
+        .collect()
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        let x = std::f64::consts::PI * x;
+        x.sin() / x
+    }
+}
+
+/// Minimum of `values[n - radius..=n + radius]` for every `n`.
+fn sliding_min(values: &[f64], radius: usize) -> Vec<f64> {
+    // indices of increasing values, the front is the minimum of the window
+    let mut window: VecDeque<usize> = VecDeque::new();
+    let mut result = Vec::with_capacity(values.len());
+    for n in 0..values.len() + radius {
+        if n < values.len() {
+            while window.back().is_some_and(|i| values[*i] >= values[n]) {
+                window.pop_back();
+            }
+            window.push_back(n);
+        }
+        let Some(center) = n.checked_sub(radius) else {
+            continue;
+        };
+        while window.front().is_some_and(|i| *i + radius < center) {
+            window.pop_front();
+        }
+        result.push(values[window[0]]);
+    }
+    result
+}
+
+/// Mean of `values[n - radius..=n + radius]` for every `n`, limited to the available values.
+fn moving_average(values: &[f64], radius: usize) -> Vec<f64> {
+    let mut sums = Vec::with_capacity(values.len() + 1);
+    sums.push(0.0);
+    for value in values {
+        sums.push(sums.last().unwrap_or(&0.0) + value);
+    }
+    (0..values.len())
+        .map(|n| {
+            let start = n.saturating_sub(radius);
+            let end = (n + radius + 1).min(values.len());
+            (sums[end] - sums[start]) / (end - start) as f64
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sine at a quarter of the sample rate, sampled 45 degrees off its peaks, so the true peak
+    /// is `sqrt(2)` times the sample peak.
+    fn quarter_rate_sine(amplitude: f64, num_frames: usize) -> Vec<f64> {
+        (0..num_frames)
+            .map(|n| {
+                let phase = std::f64::consts::FRAC_PI_2 * n as f64 + std::f64::consts::FRAC_PI_4;
+                amplitude * phase.sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_true_peaks() {
+        let samples = quarter_rate_sine(1.0, 2000);
+        let sample_peak = samples.iter().fold(0.0f64, |max, s| max.max(s.abs()));
+        let peaks = true_peaks(&samples, 1);
+        assert!((sample_peak - 0.5f64.sqrt()).abs() < 1e-9);
+        // away from the edges, every other frame has a peak before the next one
+        for peaks in peaks[100..1900].chunks_exact(2) {
+            let peak = peaks[0].max(peaks[1]);
+            assert!((peak - 1.0).abs() < 0.01, "{peak}");
+        }
+    }
+
+    #[test]
+    fn test_limit() {
+        let mut samples = quarter_rate_sine(1.2, 48000);
+        // the second half is quiet and must not be changed
+        samples[24000..].iter_mut().for_each(|s| *s *= 0.25);
+        let quiet = samples[30000..].to_vec();
+
+        let ceiling = 0.9;
+        limit(&mut samples, 1, 48000, ceiling);
+        let peak = true_peaks(&samples, 1).into_iter().fold(0.0f64, f64::max);
+        assert!(peak <= ceiling, "{peak}");
+        assert!(peak > 0.85, "{peak}");
+        assert_eq!(samples[30000..], quiet);
+    }
+
+    #[test]
+    fn test_sliding_min_and_average() {
+        let values = [3.0, 1.0, 4.0, 1.0, 5.0, 9.0, 2.0, 6.0];
+        assert_eq!(
+            sliding_min(&values, 1),
+            [1.0, 1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0]
+        );
+        assert_eq!(sliding_min(&values, 0), values);
+        assert_eq!(
+            moving_average(&[1.0, 2.0, 3.0, 6.0], 1),
+            [1.5, 2.0, 11.0 / 3.0, 4.5]
+        );
+    }
+}
@@ -1,17 +1,29 @@
 use std::fs::File;
-use std::path::Path;
+use std::io::{Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread::JoinHandle;
+use std::time::Instant;
 
-use audio_blocks::AudioBlockInterleavedView;
+use audio_blocks::{AudioBlockInterleavedView, AudioBlockSequential};
 use num::Float;
-use symphonia::core::audio::SampleBuffer;
-use symphonia::core::codecs::{CODEC_TYPE_NULL, DecoderOptions};
+use symphonia::core::audio::{AudioBufferRef, Channels, SampleBuffer, SignalSpec};
+use symphonia::core::codecs::{self, CodecParameters, CodecType, Decoder};
 use symphonia::core::errors::Error;
 use symphonia::core::formats::{FormatOptions, SeekMode, SeekTo};
-use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::MetadataOptions;
-use symphonia::core::probe::Hint;
+use symphonia::core::formats::{FormatReader, Track};
+use symphonia::core::io::{MediaSource, MediaSourceStream, ReadBytes, SeekBuffered};
+use symphonia::core::meta::{MetadataLog, MetadataOptions, MetadataRevision};
+use symphonia::core::probe::{Instantiate, Probe};
+use symphonia::core::units::TimeBase;
 use thiserror::Error;
 
+use crate::chunks::{self, Markers};
+use crate::sample_rate::{InvalidSampleRate, SampleRate};
+use crate::tags::{CoverArt, Tags};
+use crate::w64::{self, W64Reader};
+
 #[derive(Debug, Error)]
 pub enum AudioReadError {
     #[error("could not read file")]
@@ -24,10 +36,108 @@ pub enum AudioReadError {
     NoSampleRate,
     #[error("end frame {0} is larger than start frame {1}")]
     EndFrameLargerThanStartFrame(usize, usize),
+    #[error("start frame {start} is beyond the end of the file with {length} frames")]
+    StartBeyondEnd { start: usize, length: usize },
+    #[error("could not find cue {0:?}")]
+    CueNotFound(String),
+    #[error("cue {1:?} is before cue {0:?}")]
+    CuesOutOfOrder(String, String),
+    #[error("invalid segment {0} of {1}")]
+    InvalidSegment(usize, usize),
+    #[error("the file does not report its number of frames")]
+    UnknownNumFrames,
+    #[error("file has {found} channels, but at most {max} are allowed")]
+    TooManyChannels { found: usize, max: usize },
+    #[error("expected a stereo file, but it has {0} channels")]
+    NotStereo(usize),
+    #[error("invalid headroom {0} dB")]
+    InvalidHeadroom(f64),
+    #[cfg(feature = "timestretch")]
+    #[error("invalid time stretch ratio {0}")]
+    InvalidTimeStretch(f64),
+    #[cfg(feature = "resample")]
+    #[error("could not resample audio")]
+    ResampleError(#[from] crate::resample::ResampleError),
+    #[error("could not convert a sample into the output type")]
+    SampleConversion,
+    #[error("invalid stride of {0} frames")]
+    InvalidStride(usize),
+    #[error("mono channel {0} invalid, audio has only {1} channels")]
+    InvalidMonoChannel(usize, usize),
+    #[error("{0} mono weights for {1} channels")]
+    InvalidMonoWeights(usize, usize),
+    #[error("gain envelope breakpoint at frame {1} follows frame {0}")]
+    UnsortedGainEnvelope(usize, usize),
     #[error("start channel {0} invalid, audio file has only {1} channels")]
     InvalidStartChannel(usize, usize),
+    #[error("channel {0} invalid, audio file has only {1} channels")]
+    InvalidChannel(usize, usize),
+    #[error("channel {0:?} not found in the file")]
+    ChannelLabelNotFound(ChannelLabel),
     #[error("invalid number of channels to extract: {0}")]
     InvalidNumChannels(usize),
+    #[error("stream ended after {0} frames, but {1} frames were expected")]
+    TruncatedStream(usize, usize),
+    #[error("number of channels changed from {0} to {1} within the file")]
+    ChannelCountChanged(usize, usize),
+    #[error("could not find track with id {0}")]
+    TrackNotFound(u32),
+    #[error("tracks have different sample rates: {0} and {1}")]
+    SampleRateMismatch(u32, u32),
+    #[error("no decoder for codec {0}, it might need to be enabled with a feature flag")]
+    UnsupportedCodec(String),
+    #[error("file does not contain any audio")]
+    EmptyStream,
+    #[error("a number of packets can only be used as stop position")]
+    PacketsAsStart,
+    #[error("invalid window of {0} frames with hop size {1}")]
+    InvalidWindow(usize, usize),
+    #[error("invalid block size of {0} frames")]
+    InvalidBlockSize(usize),
+    #[error("container is not supported: {0}")]
+    UnsupportedContainer(String),
+    #[error("input is larger than the limit of {0} bytes")]
+    InputTooLarge(usize),
+    #[error("decoding did not finish before the deadline")]
+    TimedOut,
+    #[error("{}: {source}", path.display())]
+    WithPath {
+        path: PathBuf,
+        source: Box<AudioReadError>,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum MixError {
+    #[error("sample rates {0} and {1} do not match")]
+    SampleRateMismatch(u32, u32),
+    #[error("number of channels {0} and {1} do not match")]
+    ChannelMismatch(usize, usize),
+}
+
+impl AudioReadError {
+    /// Path of the file that caused the error, if known
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::WithPath { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// The error without the path, for matching on the cause
+    pub fn without_path(self) -> Self {
+        match self {
+            Self::WithPath { source, .. } => *source,
+            err => err,
+        }
+    }
+
+    fn with_path(self, path: &Path) -> Self {
+        Self::WithPath {
+            path: path.to_path_buf(),
+            source: Box::new(self.without_path()),
+        }
+    }
 }
 
 /// Position in the audio stream (for start or stop points)
@@ -40,9 +150,187 @@ pub enum Position {
     Time(std::time::Duration),
     /// Specific frame number (sample position across all channels)
     Frame(usize),
+    /// Number of packets containing selected frames, only valid as stop position.
+    /// How many frames a packet contains depends on the codec and can vary between packets.
+    Packets(usize),
 }
 
-#[derive(Default)]
+impl Position {
+    /// Time position from minutes and seconds, e.g. `Position::from_mmss(1, 30)` for 1:30.
+    pub fn from_mmss(minutes: u64, seconds: u64) -> Self {
+        Self::Time(std::time::Duration::from_secs(minutes * 60 + seconds))
+    }
+}
+
+/// Start and stop position of a time window
+#[derive(Debug, Clone, Copy)]
+pub struct Window {
+    pub start: Position,
+    pub stop: Position,
+}
+
+impl Window {
+    /// Window of `length` beginning at `start`, e.g. "from 1:30 for 10 seconds".
+    pub fn starting_at(start: std::time::Duration, length: std::time::Duration) -> Self {
+        Self {
+            start: Position::Time(start),
+            stop: Position::Time(start + length),
+        }
+    }
+
+    /// Window of `width` centered around `center`.
+    ///
+    /// If the window would begin before the start of the file, the start is clamped to zero,
+    /// so the window gets shorter than `width`.
+    pub fn centered(center: std::time::Duration, width: std::time::Duration) -> Self {
+        let half = width / 2;
+        Self {
+            start: Position::Time(center.saturating_sub(half)),
+            stop: Position::Time(center + (width - half)),
+        }
+    }
+}
+
+impl From<Window> for (Position, Position) {
+    fn from(window: Window) -> Self {
+        (window.start, window.stop)
+    }
+}
+
+/// Sample type the decoder output is converted to, before it is converted to the output type.
+///
+/// `F32` is exact for sources with up to 24 bits, but loses the lowest bits of 32-bit integer
+/// and 64-bit float sources. `I32` or `F64` keep their full precision when reading into `f64`.
+/// Integer samples are scaled to the range -1.0..1.0.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeInto {
+    /// `F64` when reading into `f64`, otherwise `F32`
+    #[default]
+    Auto,
+    I16,
+    I32,
+    F32,
+    F64,
+}
+
+impl DecodeInto {
+    /// Resolve [`Self::Auto`] for the output type `F`.
+    fn for_output<F: Float>(self) -> Self {
+        match self {
+            Self::Auto if size_of::<F>() > size_of::<f32>() => Self::F64,
+            Self::Auto => Self::F32,
+            decode_into => decode_into,
+        }
+    }
+
+    /// Sample type that keeps the samples as they are, with the number of significant bits.
+    ///
+    /// 8-bit and unsigned samples are widened to signed types.
+    fn native(decoded: &AudioBufferRef) -> (Self, u32) {
+        match decoded {
+            AudioBufferRef::U8(_) | AudioBufferRef::S8(_) => (Self::I16, 16),
+            AudioBufferRef::U16(_) | AudioBufferRef::S16(_) => (Self::I16, 16),
+            AudioBufferRef::U24(_) | AudioBufferRef::S24(_) => (Self::I32, 24),
+            AudioBufferRef::U32(_) | AudioBufferRef::S32(_) => (Self::I32, 32),
+            AudioBufferRef::F32(_) => (Self::F32, 32),
+            AudioBufferRef::F64(_) => (Self::F64, 64),
+        }
+    }
+}
+
+/// What happens with the last frames that do not fill a whole window in [`audio_read_windows`]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartialWindow {
+    /// Frames that are not part of a whole window are dropped
+    #[default]
+    Drop,
+    /// Windows are padded with zeros, until every frame was part of a window
+    Pad,
+}
+
+/// How the audio is reduced to a single channel, see [`AudioReadConfig::mono`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum MonoSelect {
+    /// Average of all channels
+    Downmix,
+    /// Sum of the channels multiplied with one weight per selected channel, e.g. to fold 5.1
+    /// into mono with a lower level for the surround channels. The sum is clamped to full scale.
+    Weighted(Vec<f32>),
+    /// The channel with the given index, after the channels were selected
+    Channel(usize),
+    /// The channel with the highest energy, e.g. the speaker in an interview with a silent
+    /// channel. Needs an additional pass over the decoded samples to measure the energy.
+    HighestEnergy,
+}
+
+/// Speaker position of a channel, see [`AudioReadConfig::channels_by_label`].
+///
+/// The positions of the WAV channel mask, in the same order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelLabel {
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    Lfe,
+    BackLeft,
+    BackRight,
+    FrontLeftOfCenter,
+    FrontRightOfCenter,
+    BackCenter,
+    SideLeft,
+    SideRight,
+    TopCenter,
+    TopFrontLeft,
+    TopFrontCenter,
+    TopFrontRight,
+    TopBackLeft,
+    TopBackCenter,
+    TopBackRight,
+}
+
+impl ChannelLabel {
+    /// Bit of the position in the WAV channel mask, which symphonia uses as well
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// Samples added by [`AudioData::pad_to_frames`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PadMode {
+    /// Silence
+    #[default]
+    Zero,
+    /// Repeat the last frame
+    Edge,
+    /// Mirror the audio at the last frame, without repeating it (`a b c` becomes `a b c b a`)
+    Reflect,
+}
+
+/// Which tracks of a file are decoded
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackSelection {
+    /// The first track that contains audio
+    #[default]
+    First,
+    /// The track with the given id
+    Id(u32),
+    /// The first track with a lossless codec (PCM, FLAC, ALAC, ...), or the first track that
+    /// contains audio if there is none
+    Lossless,
+    /// The audio track with the most channels, the first one if several have the same number
+    MostChannels,
+    /// The audio track with the highest sample rate, the first one if several have the same rate
+    HighestSampleRate,
+    /// All audio tracks, which must have the same sample rate.
+    ///
+    /// With `mix` the tracks are summed into the channel layout of the track with the most
+    /// channels, where mono tracks are added to all channels. Otherwise the channels of all
+    /// tracks are concatenated. Shorter tracks are padded with zeros.
+    All { mix: bool },
+}
+
+#[derive(Default, Clone)]
 pub struct AudioReadConfig {
     /// Where to start reading audio (time or frame-based)
     pub start: Position,
@@ -52,14 +340,292 @@ pub struct AudioReadConfig {
     pub start_channel: Option<usize>,
     /// Number of channels to extract. None means extract all remaining channels.
     pub num_channels: Option<usize>,
+    /// Extract these channels (0-indexed), in this order, instead of a range of channels.
+    /// Channels can be repeated. Overrides `start_channel` and `num_channels`.
+    pub channels: Option<Vec<usize>>,
+    /// Extract the channels at these speaker positions, in this order, instead of a range of
+    /// channels. The positions are taken from the channel layout of the file, files without a
+    /// layout get the default layout for their number of channels (e.g. 5.1 for 6 channels).
+    /// Overrides `start_channel`, `num_channels` and `channels`, with multiple tracks the labels
+    /// are resolved in every track.
+    pub channels_by_label: Option<Vec<ChannelLabel>>,
+    /// Tracks to decode. With multiple tracks, the channels are selected after combining them.
+    pub track: TrackSelection,
+    /// If the file ends before the requested stop, fill the output with zeros up to the stop,
+    /// so the output always has the requested length.
+    pub pad_to_stop: bool,
+    /// Sample type used for decoding, by default the one that keeps the precision of the output
+    /// type
+    pub decode_into: DecodeInto,
+    /// Extension used as hint for the probe if the path has no extension and no hint is passed
+    /// (e.g. `"wav"`)
+    pub default_hint: Option<String>,
+    /// Handling of the last frames in [`audio_read_windows`]
+    pub partial_window: PartialWindow,
+    /// Decode from the beginning of the file instead of seeking close to the start position
+    pub disable_seek: bool,
+    /// Abort with [`AudioReadError::TimedOut`] if decoding is not finished at this time.
+    /// Checked before every packet, so it is exceeded by at most the time to decode a packet.
+    pub deadline: Option<Instant>,
+    /// Attenuate the samples by this many dB, to leave room for gain applied after reading.
+    /// It is applied to each track after the frames and channels are selected, before the tracks
+    /// are mixed and the output is padded. Not applied by [`audio_read_packed`].
+    pub headroom_db: Option<f64>,
+    /// Keep only every Nth frame, starting with the first selected frame.
+    /// This is a rough preview (e.g. for waveform overviews), the frames are not low-pass filtered
+    /// and the output keeps the sample rate of the file.
+    pub stride_frames: Option<usize>,
+    /// Reduce the output to a single channel, after all other transforms.
+    /// Only applied by the reads that return [`AudioData`].
+    pub mono: Option<MonoSelect>,
+    /// Gain automation as `(frame, gain)` breakpoints, which are linearly interpolated.
+    /// Frames are relative to the start of the output and must be in ascending order. The gain
+    /// before the first and after the last breakpoint stays constant.
+    /// Only applied by the reads that return [`AudioData`].
+    pub gain_envelope: Option<Vec<(usize, f64)>>,
+    /// Fail with [`AudioReadError::TooManyChannels`] if the file has more channels, checked
+    /// before any samples are decoded. Independent of the channel selection.
+    pub max_channels: Option<usize>,
+    /// Replace NaN and infinite samples with zero, e.g. from corrupt float files. The number of
+    /// replaced samples is reported in [`ReadStats::sanitized_samples`].
+    /// Not applied by [`audio_read_packed`].
+    pub sanitize_input: bool,
+    /// Compute the peak and RMS of each channel while decoding, see [`AudioData::stats`]
+    pub collect_stats: bool,
+    /// Change the tempo without changing the pitch, where 0.5 plays at half the speed. The speed
+    /// must be between 0.1 and 10.
+    /// The output has `num_frames / time_stretch` frames at the same sample rate. It is applied
+    /// after the tracks are mixed and the output is padded, the gain envelope refers to the
+    /// stretched frames. Cues and loops are not rescaled and keep the frames of the file.
+    /// Stretching is much slower than decoding.
+    /// Only applied by the reads that return [`AudioData`].
+    #[cfg(feature = "timestretch")]
+    pub time_stretch: Option<f64>,
+    /// Resample the output to this sample rate, if the file has a different one.
+    /// Start and stop positions in frames refer to the sample rate of the file, the gain
+    /// envelope to the resampled output. Cues and loops are converted to the new sample rate.
+    /// It is applied after the tracks are mixed and the output is padded.
+    /// Only applied by the reads that return [`AudioData`].
+    #[cfg(feature = "resample")]
+    pub target_sample_rate: Option<u32>,
 }
 
-#[derive(Default)]
+/// Container format of an audio file
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Container {
+    /// The container could not be identified
+    #[default]
+    Unknown,
+    Wav,
+    Aiff,
+    Caf,
+    Flac,
+    Ogg,
+    /// Matroska or WebM
+    Mkv,
+    /// MP4, M4A, MOV, ...
+    IsoMp4,
+    /// Raw AAC in ADTS frames
+    Adts,
+    /// Raw MPEG audio frames (MP1, MP2, MP3)
+    MpegAudio,
+    /// Sony Wave64
+    W64,
+}
+
+impl Container {
+    /// Identify the container from the marker found by the probe.
+    fn from_marker(marker: &[u8; 16]) -> Self {
+        match marker {
+            [
+                b'R',
+                b'I',
+                b'F',
+                b'F',
+                _,
+                _,
+                _,
+                _,
+                b'W',
+                b'A',
+                b'V',
+                b'E',
+                ..,
+            ] => Self::Wav,
+            &w64::W64_RIFF_GUID => Self::W64,
+            [b'F', b'O', b'R', b'M', ..] => Self::Aiff,
+            [b'c', b'a', b'f', b'f', ..] => Self::Caf,
+            [b'f', b'L', b'a', b'C', ..] => Self::Flac,
+            [b'O', b'g', b'g', b'S', ..] => Self::Ogg,
+            [0x1a, 0x45, 0xdf, 0xa3, ..] => Self::Mkv,
+            [b'f', b't', b'y', b'p', ..] => Self::IsoMp4,
+            // 12 bit sync word with layer 0 is ADTS, 11 bit sync word is MPEG audio
+            [0xff, b, ..] if b & 0xf6 == 0xf0 => Self::Adts,
+            [0xff, b, ..] if b & 0xe0 == 0xe0 => Self::MpegAudio,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Identify the container from a file extension, which is used as hint for the probe.
+    fn from_extension(ext: &str) -> Self {
+        match ext.to_ascii_lowercase().as_str() {
+            "wav" | "wave" => Self::Wav,
+            "w64" => Self::W64,
+            "aif" | "aiff" | "aifc" => Self::Aiff,
+            "caf" => Self::Caf,
+            "flac" => Self::Flac,
+            "ogg" | "oga" | "opus" => Self::Ogg,
+            "mkv" | "mka" | "webm" => Self::Mkv,
+            "mp4" | "m4a" | "m4b" | "mov" => Self::IsoMp4,
+            "aac" => Self::Adts,
+            "mp1" | "mp2" | "mp3" => Self::MpegAudio,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl std::fmt::Display for Container {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Unknown => "unknown",
+            Self::Wav => "WAV",
+            Self::Aiff => "AIFF",
+            Self::Caf => "CAF",
+            Self::Flac => "FLAC",
+            Self::Ogg => "Ogg",
+            Self::Mkv => "Matroska",
+            Self::IsoMp4 => "MP4",
+            Self::Adts => "ADTS",
+            Self::MpegAudio => "MPEG audio",
+            Self::W64 => "Wave64",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Marker at a position in the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cue {
+    /// Name of the marker, empty if it has none
+    pub label: String,
+    /// Position in frames from the start of the file
+    pub frame: usize,
+}
+
+/// Sampler settings of a WAV file, read from the `smpl` chunk
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InstrumentInfo {
+    /// MIDI note that plays the sample at its original pitch
+    pub midi_unity_note: u8,
+    /// Sustain loops, the positions are relative to the start of the file
+    pub loops: Vec<LoopRegion>,
+}
+
+/// Region that is looped, e.g. by a sampler
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LoopRegion {
+    /// First frame of the loop, from the start of the file
+    pub start_frame: usize,
+    /// End of the loop, from the start of the file
+    pub end_frame: usize,
+}
+
+#[derive(Default, Clone)]
 pub struct AudioData<F: Float + 'static> {
     pub interleaved_samples: Vec<F>,
     pub sample_rate: u32,
     pub num_channels: usize,
     pub num_frames: usize,
+    /// Container format the audio was read from
+    pub container: Container,
+    /// Number of frames in the whole file, independent of the selected range.
+    /// `None` if the container does not report it.
+    pub source_num_frames: Option<usize>,
+    /// Markers in the file (currently read from CAF and WAV files).
+    /// The positions are relative to the start of the file, not to the selected range.
+    pub cues: Vec<Cue>,
+    /// Loop regions in the file (currently read from CAF files)
+    pub loops: Vec<LoopRegion>,
+    /// Sample format of the source, `None` if the codec does not report it (e.g. lossy codecs)
+    pub source_format: Option<SourceFormat>,
+    /// Metadata tags of the file (e.g. Vorbis comments or ID3v2 frames)
+    pub tags: Tags,
+    /// Sampler settings of the file (currently read from WAV files)
+    pub instrument: Option<InstrumentInfo>,
+    /// Byte offset of the first sample in the file, for uncompressed WAV and AIFF files.
+    /// Independent of the selected range, it is the start of the data chunk.
+    pub data_offset: Option<u64>,
+    /// Length of the samples in the file in bytes, see [`Self::data_offset`]
+    pub data_len: Option<u64>,
+    /// Levels of the channels, if [`AudioReadConfig::collect_stats`] is set
+    pub stats: Option<AudioStats>,
+}
+
+/// Levels of each channel of the read audio, see [`AudioReadConfig::collect_stats`]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AudioStats {
+    /// Highest absolute sample value of each channel
+    pub peak: Vec<f64>,
+    /// Root mean square of each channel
+    pub rms: Vec<f64>,
+}
+
+impl AudioStats {
+    fn from_samples<F: Float>(samples: &[F], num_channels: usize) -> Self {
+        let mut meter = LevelMeter::default();
+        meter.add(samples, num_channels);
+        meter.finish(num_channels, samples.len() / num_channels.max(1))
+    }
+}
+
+/// Peak and sum of squares of each channel, accumulated while decoding
+#[derive(Default)]
+struct LevelMeter {
+    peak: Vec<f64>,
+    sum_squares: Vec<f64>,
+}
+
+impl LevelMeter {
+    fn add<S: num::ToPrimitive>(&mut self, samples: &[S], num_channels: usize) {
+        if num_channels == 0 {
+            return;
+        }
+        self.peak.resize(num_channels, 0.0);
+        self.sum_squares.resize(num_channels, 0.0);
+        for frame in samples.chunks_exact(num_channels) {
+            for (channel, sample) in frame.iter().enumerate() {
+                let sample = sample.to_f64().unwrap_or(0.0);
+                self.peak[channel] = self.peak[channel].max(sample.abs());
+                self.sum_squares[channel] += sample * sample;
+            }
+        }
+    }
+
+    /// Levels over `num_frames` frames, frames that were not added count as silence.
+    fn finish(mut self, num_channels: usize, num_frames: usize) -> AudioStats {
+        self.peak.resize(num_channels, 0.0);
+        self.sum_squares.resize(num_channels, 0.0);
+        let rms = self
+            .sum_squares
+            .iter()
+            .map(|sum| {
+                if num_frames == 0 {
+                    0.0
+                } else {
+                    (sum / num_frames as f64).sqrt()
+                }
+            })
+            .collect();
+        AudioStats {
+            peak: self.peak,
+            rms,
+        }
+    }
 }
 
 impl<F: Float> AudioData<F> {
@@ -75,303 +641,5301 @@ impl<F: Float> AudioData<F> {
             self.num_frames,
         )
     }
+
+    /// Sample rate as [`SampleRate`], which fails for rates above [`SampleRate::MAX`].
+    pub fn sample_rate_typed(&self) -> Result<SampleRate, InvalidSampleRate> {
+        SampleRate::try_from(self.sample_rate)
+    }
+
+    /// Deinterleave into an owned sequential block, where the samples of each channel are
+    /// contiguous. Useful for DSP code that works on whole channels.
+    pub fn into_sequential_block(self) -> AudioBlockSequential<F> {
+        AudioBlockSequential::from_block(&self.audio_block())
+    }
+
+    /// Deinterleave into planar buffers with the given layout, see [`PlanarLayout`].
+    /// With [`PlanarMode::Contiguous`] all channels are stored in one allocation, which is easier
+    /// to hand over through FFI.
+    pub fn into_planar(self, mode: PlanarMode) -> PlanarLayout<F> {
+        let channel_len = self.num_frames;
+        if mode == PlanarMode::Contiguous {
+            let mut data = vec![F::zero(); self.num_channels * channel_len];
+            for (frame, samples) in self.frames().enumerate() {
+                for (channel, sample) in samples.iter().enumerate() {
+                    data[channel * channel_len + frame] = *sample;
+                }
+            }
+            PlanarLayout::Contiguous { data, channel_len }
+        } else {
+            let channels = (0..self.num_channels)
+                .map(|channel| self.channel(channel).copied().collect())
+                .collect();
+            PlanarLayout::Separate(channels)
+        }
+    }
+
+    /// Add `other` multiplied with `gain` to a copy of this audio.
+    ///
+    /// Both need the same sample rate and number of channels. The result has the length of the
+    /// longer one, the shorter one is padded with zeros. The metadata is taken from `self`.
+    pub fn mix(&self, other: &AudioData<F>, gain: F) -> Result<AudioData<F>, MixError> {
+        if self.sample_rate != other.sample_rate {
+            return Err(MixError::SampleRateMismatch(
+                self.sample_rate,
+                other.sample_rate,
+            ));
+        }
+        if self.num_channels != other.num_channels {
+            return Err(MixError::ChannelMismatch(
+                self.num_channels,
+                other.num_channels,
+            ));
+        }
+
+        let mut mixed = self.clone();
+        mixed.num_frames = self.num_frames.max(other.num_frames);
+        mixed
+            .interleaved_samples
+            .resize(mixed.num_frames * self.num_channels, F::zero());
+        for (sample, other) in mixed
+            .interleaved_samples
+            .iter_mut()
+            .zip(&other.interleaved_samples)
+        {
+            *sample = *sample + *other * gain;
+        }
+        Ok(mixed)
+    }
+
+    /// Crop or pad the audio to exactly `frames` frames around its center.
+    ///
+    /// A longer source loses `(num_frames - frames) / 2` frames at the start and the rest at the
+    /// end, a shorter one gets `(frames - num_frames) / 2` zero frames in front and the rest
+    /// after it. With an odd difference, the end gets one frame more.
+    /// Cues and loops keep their positions in the file.
+    pub fn center_crop(mut self, frames: usize) -> AudioData<F> {
+        let num_channels = self.num_channels;
+        if self.num_frames >= frames {
+            let start = (self.num_frames - frames) / 2;
+            self.interleaved_samples.drain(..start * num_channels);
+            self.interleaved_samples.truncate(frames * num_channels);
+        } else {
+            let front = (frames - self.num_frames) / 2;
+            let mut samples = vec![F::zero(); frames * num_channels];
+            samples[front * num_channels..][..self.interleaved_samples.len()]
+                .copy_from_slice(&self.interleaved_samples);
+            self.interleaved_samples = samples;
+        }
+        self.num_frames = frames;
+        self
+    }
+
+    /// Pad the audio at the end to `frames` frames, audio that is already longer is unchanged.
+    ///
+    /// [`PadMode::Edge`] and [`PadMode::Reflect`] pad empty audio with zeros. Reflections
+    /// longer than the audio continue back and forth, audio with a single frame is repeated.
+    pub fn pad_to_frames(mut self, frames: usize, mode: PadMode) -> AudioData<F> {
+        if frames <= self.num_frames {
+            return self;
+        }
+        let num_channels = self.num_channels;
+        let num_frames = self.num_frames;
+        let source_frame = |frame: usize| match mode {
+            _ if num_frames == 0 => None,
+            PadMode::Zero => None,
+            PadMode::Edge => Some(num_frames - 1),
+            PadMode::Reflect if num_frames == 1 => Some(0),
+            PadMode::Reflect => {
+                let period = 2 * (num_frames - 1);
+                let pos = frame % period;
+                Some(if pos < num_frames { pos } else { period - pos })
+            }
+        };
+
+        self.interleaved_samples
+            .reserve((frames - num_frames) * num_channels);
+        for frame in num_frames..frames {
+            match source_frame(frame) {
+                Some(source) => self
+                    .interleaved_samples
+                    .extend_from_within(source * num_channels..(source + 1) * num_channels),
+                None => self
+                    .interleaved_samples
+                    .extend(std::iter::repeat_n(F::zero(), num_channels)),
+            }
+        }
+        self.num_frames = frames;
+        self
+    }
+
+    /// Iterate over the frames, each containing one sample per channel.
+    pub fn frames(&self) -> impl Iterator<Item = &[F]> {
+        self.interleaved_samples
+            .chunks_exact(self.num_channels.max(1))
+    }
+
+    /// Iterate over the samples of one channel.
+    ///
+    /// Panics if `idx` is not smaller than the number of channels.
+    pub fn channel(&self, idx: usize) -> impl Iterator<Item = &F> {
+        assert!(
+            idx < self.num_channels,
+            "channel {idx} out of range for {} channels",
+            self.num_channels
+        );
+        self.interleaved_samples
+            .iter()
+            .skip(idx)
+            .step_by(self.num_channels)
+    }
 }
 
-pub fn audio_read<P: AsRef<Path>, F: Float>(
-    path: P,
-    config: AudioReadConfig,
-) -> Result<AudioData<F>, AudioReadError> {
-    let src = File::open(path.as_ref())?;
-    let mss = MediaSourceStream::new(Box::new(src), Default::default());
+impl AudioData<f32> {
+    /// The interleaved samples as bytes, e.g. to create a `Float32Array` in JavaScript
+    /// without copying.
+    ///
+    /// The bytes are in the native byte order (little-endian on WASM and most other targets).
+    /// The slice starts at the 4-byte alignment of the samples, as `Float32Array` requires
+    /// for its offset into the WASM memory.
+    pub fn as_f32_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.interleaved_samples)
+    }
+}
+
+/// Layout requested from [`AudioData::into_planar`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanarMode {
+    /// One buffer per channel, see [`PlanarLayout::Separate`]
+    Separate,
+    /// All channels in a single buffer, see [`PlanarLayout::Contiguous`]
+    Contiguous,
+}
+
+/// Deinterleaved samples, see [`AudioData::into_planar`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlanarLayout<F> {
+    /// One buffer per channel, the sample of frame `n` in channel `c` is at `channels[c][n]`
+    Separate(Vec<Vec<F>>),
+    /// All channels one after another in a single buffer. The sample of frame `n` in channel `c`
+    /// is at `data[c * channel_len + n]`, so channel `c` is `data[c * channel_len..][..channel_len]`.
+    Contiguous { data: Vec<F>, channel_len: usize },
+}
+
+pub fn audio_read<P: AsRef<Path>, F: Float>(
+    path: P,
+    config: AudioReadConfig,
+) -> Result<AudioData<F>, AudioReadError> {
+    let (data, _) = audio_read_with_stats(path, config)?;
+    Ok(data)
+}
+
+/// Reads files with a shared configuration.
+///
+/// Cloning only copies a reference to the configuration, so a reader can be handed to every
+/// worker of a pool.
+#[derive(Default, Clone)]
+pub struct AudioReader {
+    config: Arc<AudioReadConfig>,
+}
+
+impl AudioReader {
+    pub fn new(config: AudioReadConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+        }
+    }
+
+    pub fn config(&self) -> &AudioReadConfig {
+        &self.config
+    }
+
+    /// Same as [`audio_read`] with the configuration of the reader.
+    pub fn read<P: AsRef<Path>, F: Float>(&self, path: P) -> Result<AudioData<F>, AudioReadError> {
+        audio_read(path, AudioReadConfig::clone(&self.config))
+    }
+
+    /// Same as [`audio_read_with_stats`] with the configuration of the reader.
+    pub fn read_with_stats<P: AsRef<Path>, F: Float>(
+        &self,
+        path: P,
+    ) -> Result<(AudioData<F>, ReadStats), AudioReadError> {
+        audio_read_with_stats(path, AudioReadConfig::clone(&self.config))
+    }
+}
+
+/// Same as [`audio_read`], but decodes on the blocking thread pool of tokio, so the executor
+/// threads are not blocked.
+///
+/// Must be called from within a tokio runtime. A panic while decoding is resumed in the caller,
+/// if the runtime shuts down before the read finishes [`AudioReadError::FileError`] is returned.
+#[cfg(feature = "async")]
+pub async fn audio_read_async<P: AsRef<Path>, F: Float + Send + 'static>(
+    path: P,
+    config: AudioReadConfig,
+) -> Result<AudioData<F>, AudioReadError> {
+    let path = path.as_ref().to_path_buf();
+    match tokio::task::spawn_blocking(move || audio_read(path, config)).await {
+        Ok(result) => result,
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(err) => Err(AudioReadError::FileError(err.into())),
+    }
+}
+
+/// Read audio from a file that is already opened.
+///
+/// The file is read from its beginning. See [`audio_read_from_source`] for the hint.
+pub fn audio_read_file<F: Float>(
+    file: File,
+    hint_ext: Option<&str>,
+    config: AudioReadConfig,
+) -> Result<AudioData<F>, AudioReadError> {
+    let (data, _) = read_tracks(config, |config| {
+        // each track is decoded after the other, so they can share the file position
+        let mut file = file.try_clone()?;
+        file.seek(SeekFrom::Start(0))?;
+        Decoding::from_source(Box::new(file), hint_ext, config)
+    })?;
+    Ok(data)
+}
+
+/// Read audio from any source, e.g. an upload in memory, without writing it to a file.
+///
+/// Seekable sources are read from their beginning. `hint_ext` is the extension of the expected
+/// container (e.g. `"wav"`), like the extension of the path in [`audio_read`]. If the first
+/// marker in the source belongs to another container, e.g. bytes in front of the actual
+/// container, the probe searches further for a container of the hinted type. Without a hint,
+/// `default_hint` of the config is used.
+/// Sources that can't seek are decoded from the start, like [`audio_read_stdin`]. With
+/// [`TrackSelection::All`] the source is buffered in memory, as every track is decoded from
+/// the start.
+pub fn audio_read_from_source<F: Float>(
+    mut source: Box<dyn MediaSource>,
+    hint_ext: Option<&str>,
+    config: AudioReadConfig,
+) -> Result<AudioData<F>, AudioReadError> {
+    use std::io::Read;
+
+    let config = AudioReadConfig {
+        disable_seek: config.disable_seek || !source.is_seekable(),
+        ..config
+    };
+    if source.is_seekable() {
+        source.seek(SeekFrom::Start(0))?;
+    }
+
+    if !matches!(config.track, TrackSelection::All { .. }) {
+        // a single track is opened exactly once
+        let source = std::cell::Cell::new(Some(source));
+        let (data, _) = read_tracks(config, |config| {
+            let source = source.take().ok_or(AudioReadError::EmptyStream)?;
+            Decoding::from_source(source, hint_ext, config)
+        })?;
+        return Ok(data);
+    }
+
+    let mut bytes = Vec::new();
+    source.read_to_end(&mut bytes)?;
+    let bytes: Arc<[u8]> = bytes.into();
+    let (data, _) = read_tracks(config, |config| {
+        let source = std::io::Cursor::new(bytes.clone());
+        Decoding::from_source(Box::new(source), hint_ext, config)
+    })?;
+    Ok(data)
+}
+
+/// Read audio from memory, e.g. sound effects embedded with `include_bytes!`.
+///
+/// The bytes can seek like a file, so a late start position is reached by seeking.
+/// See [`audio_read_from_source`] for the hint.
+pub fn audio_read_from_bytes<F: Float>(
+    bytes: Vec<u8>,
+    hint_ext: Option<&str>,
+    config: AudioReadConfig,
+) -> Result<AudioData<F>, AudioReadError> {
+    audio_read_from_source(Box::new(std::io::Cursor::new(bytes)), hint_ext, config)
+}
+
+/// Read audio from stdin, e.g. at the end of a shell pipeline.
+///
+/// Stdin is not seekable, so the whole input is buffered in memory before it is decoded. If it is
+/// larger than `max_bytes`, reading fails with [`AudioReadError::InputTooLarge`]. A late start
+/// position is reached by decoding and discarding all frames in front of it.
+/// See [`audio_read_from_source`] for the hint.
+pub fn audio_read_stdin<F: Float>(
+    hint_ext: Option<&str>,
+    max_bytes: usize,
+    config: AudioReadConfig,
+) -> Result<AudioData<F>, AudioReadError> {
+    read_unseekable(std::io::stdin().lock(), hint_ext, max_bytes, config)
+}
+
+/// Buffer a stream that can not seek and decode it from memory.
+fn read_unseekable<F: Float>(
+    reader: impl std::io::Read,
+    hint_ext: Option<&str>,
+    max_bytes: usize,
+    config: AudioReadConfig,
+) -> Result<AudioData<F>, AudioReadError> {
+    use std::io::Read;
+
+    let mut bytes = Vec::new();
+    reader
+        .take((max_bytes as u64).saturating_add(1))
+        .read_to_end(&mut bytes)?;
+    if bytes.len() > max_bytes {
+        return Err(AudioReadError::InputTooLarge(max_bytes));
+    }
+    let bytes: std::sync::Arc<[u8]> = bytes.into();
+
+    let config = AudioReadConfig {
+        disable_seek: true,
+        ..config
+    };
+    let (data, _) = read_tracks(config, |config| {
+        let source = std::io::Cursor::new(bytes.clone());
+        Decoding::from_source(Box::new(source), hint_ext, config)
+    })?;
+    Ok(data)
+}
+
+/// Decode the file on a background thread and send the interleaved samples packet by packet.
+///
+/// The channel holds at most `capacity` chunks, so decoding pauses until the receiver catches up.
+/// A chunk contains whole frames. Errors are sent as the last item and end the thread, which
+/// also stops once the receiver is dropped. The thread does not panic, joining the handle is only
+/// needed to wait until the file is closed. Only a single track is read, [`TrackSelection::All`]
+/// reads the first track.
+pub fn audio_read_channel<P: AsRef<Path>, F: Float + Send + 'static>(
+    path: P,
+    capacity: usize,
+    config: AudioReadConfig,
+) -> (Receiver<Result<Vec<F>, AudioReadError>>, JoinHandle<()>) {
+    let path = path.as_ref().to_path_buf();
+    let (sender, receiver) = std::sync::mpsc::sync_channel(capacity);
+    let handle = std::thread::spawn(move || {
+        if let Err(err) = send_packets(&path, &config, &sender) {
+            // fails if the receiver was dropped, then nobody is interested in the error
+            let _ = sender.send(Err(err.with_path(&path)));
+        }
+    });
+    (receiver, handle)
+}
+
+fn send_packets<F: Float>(
+    path: &Path,
+    config: &AudioReadConfig,
+    sender: &SyncSender<Result<Vec<F>, AudioReadError>>,
+) -> Result<(), AudioReadError> {
+    let mut decoding = Decoding::open(path, config)?.for_output::<F>();
+
+    let mut num_samples = 0;
+    while let Some(packet_samples) = decoding.next_packet()? {
+        if packet_samples.is_empty() {
+            continue;
+        }
+        let mut chunk = Vec::new();
+        extend_converted(&mut chunk, packet_samples)?;
+        num_samples += chunk.len();
+        if sender.send(Ok(chunk)).is_err() {
+            // the receiver was dropped
+            return Ok(());
+        }
+    }
+
+    let num_channels = decoding.num_channels()?;
+    if decoding.pad_to_stop
+        && let Some(end_frame) = decoding.end_frame
+    {
+        let num_frames = (end_frame - decoding.start_frame).div_ceil(decoding.stride);
+        let missing = (num_frames * num_channels).saturating_sub(num_samples);
+        if missing > 0 {
+            let _ = sender.send(Ok(vec![F::zero(); missing]));
+        }
+    }
+    Ok(())
+}
+
+/// A decoded packet with its position, see [`audio_read_packets`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacketInfo<F> {
+    /// Timestamp of the packet in the container, in the time base of the track
+    pub ts: u64,
+    /// Position of the first frame in `samples`, from the start of the file
+    pub frame_start: usize,
+    pub num_frames: usize,
+    /// Interleaved samples of the selected frames and channels of the packet
+    pub samples: Vec<F>,
+}
+
+/// Decode the file packet by packet, together with the timestamp of each packet in the container.
+///
+/// Useful to debug the synchronization with other streams (e.g. video). Packets without selected
+/// frames are skipped. The iterator ends after the first error. Only a single track is read,
+/// [`TrackSelection::All`] reads the first track.
+pub fn audio_read_packets<P: AsRef<Path>, F: Float>(
+    path: P,
+    config: AudioReadConfig,
+) -> impl Iterator<Item = Result<PacketInfo<F>, AudioReadError>> {
+    let path = path.as_ref().to_path_buf();
+    let mut state = Some(Decoding::open(&path, &config).map(Decoding::for_output::<F>));
+    std::iter::from_fn(move || {
+        let result = match state.take()? {
+            Ok(mut decoding) => match next_packet_info(&mut decoding) {
+                Ok(Some(info)) => {
+                    state = Some(Ok(decoding));
+                    Ok(info)
+                }
+                Ok(None) => return None,
+                Err(err) => Err(err),
+            },
+            Err(err) => Err(err),
+        };
+        Some(result.map_err(|e| e.with_path(&path)))
+    })
+}
+
+fn next_packet_info<F: Float>(
+    decoding: &mut Decoding,
+) -> Result<Option<PacketInfo<F>>, AudioReadError> {
+    while let Some(frames) = decoding.decode_next()? {
+        let frame_start = decoding.packet_pos as usize + frames.start + decoding.stride_skip();
+        let packet_samples = decoding.select(frames)?;
+        if packet_samples.is_empty() {
+            continue;
+        }
+        let mut samples = Vec::new();
+        extend_converted(&mut samples, packet_samples)?;
+        return Ok(Some(PacketInfo {
+            ts: decoding.packet_ts,
+            frame_start,
+            num_frames: samples.len() / decoding.num_channels()?,
+            samples,
+        }));
+    }
+    Ok(None)
+}
+
+/// Decode the file once and call `f` with overlapping windows of `window_frames` frames,
+/// which start every `hop_frames` frames.
+///
+/// `f` receives the interleaved samples of a window and its start frame, relative to the start
+/// of the selected range. Only the current window is kept in memory. If the hop size is larger
+/// than the window, the frames in between are skipped. Only a single track is read,
+/// [`TrackSelection::All`] reads the first track.
+pub fn audio_read_windows<P: AsRef<Path>, F: Float>(
+    path: P,
+    window_frames: usize,
+    hop_frames: usize,
+    config: AudioReadConfig,
+    mut f: impl FnMut(&[F], usize),
+) -> Result<(), AudioReadError> {
+    if window_frames == 0 || hop_frames == 0 {
+        return Err(AudioReadError::InvalidWindow(window_frames, hop_frames));
+    }
+
+    read_windows(path.as_ref(), window_frames, hop_frames, config, &mut f)
+        .map_err(|e| e.with_path(path.as_ref()))
+}
+
+fn read_windows<F: Float>(
+    path: &Path,
+    window_frames: usize,
+    hop_frames: usize,
+    config: AudioReadConfig,
+    f: &mut impl FnMut(&[F], usize),
+) -> Result<(), AudioReadError> {
+    let mut decoding = Decoding::open(path, &config)?.for_output::<F>();
+
+    // samples from the start of the next window on
+    let mut buffer: Vec<F> = Vec::new();
+    let mut window_start = 0;
+    // frames that still have to be skipped before the next window starts
+    let mut skip = 0;
+    // end of the last window
+    let mut covered = 0;
+    let mut num_channels = 0;
+
+    // drop the frames in front of the next window, returns the frames that are still missing
+    let advance = |buffer: &mut Vec<F>, num_channels: usize| {
+        let buffered = buffer.len() / num_channels;
+        buffer.drain(..hop_frames.min(buffered) * num_channels);
+        hop_frames.saturating_sub(buffered)
+    };
+
+    while let Some(frames) = decoding.decode_next()? {
+        num_channels = decoding.num_channels()?;
+        let packet_samples = decoding.select(frames)?;
+
+        let skipped = skip.min(packet_samples.len() / num_channels);
+        skip -= skipped;
+        extend_converted(&mut buffer, &packet_samples[skipped * num_channels..])?;
+
+        while buffer.len() >= window_frames * num_channels {
+            f(&buffer[..window_frames * num_channels], window_start);
+            covered = window_start + window_frames;
+            skip = advance(&mut buffer, num_channels);
+            window_start += hop_frames;
+        }
+    }
+
+    if config.partial_window == PartialWindow::Pad && num_channels > 0 {
+        let mut window = Vec::with_capacity(window_frames * num_channels);
+        while !buffer.is_empty() && window_start + buffer.len() / num_channels > covered {
+            window.clear();
+            window.extend_from_slice(&buffer);
+            window.resize(window_frames * num_channels, F::zero());
+            f(&window, window_start);
+            covered = window_start + window_frames;
+            advance(&mut buffer, num_channels);
+            window_start += hop_frames;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a file block by block, so files larger than the memory can be processed.
+///
+/// The frame and channel selection, stride, headroom, sanitizing and padding to the stop
+/// position are applied as in [`audio_read`], the blocks are consecutive parts of the same
+/// output. The transforms that need the whole output (gain envelope, mono selection and time
+/// stretch) are not applied. Only a single track is read, [`TrackSelection::All`] reads the
+/// first track.
+///
+/// The blocks contain the format of the file, but no tags, markers or other metadata.
+pub struct BlockReader<F: Float + 'static> {
+    path: PathBuf,
+    decoding: Decoding,
+    /// Selected samples that were decoded, but not returned yet
+    pending: Vec<F>,
+    /// Frames returned so far
+    position: usize,
+    /// No more packets to decode
+    finished: bool,
+}
+
+impl<F: Float + 'static> BlockReader<F> {
+    pub fn open<P: AsRef<Path>>(path: P, config: AudioReadConfig) -> Result<Self, AudioReadError> {
+        let decoding = Decoding::open(path.as_ref(), &config)
+            .map_err(|e| e.with_path(path.as_ref()))?
+            .for_output::<F>();
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            decoding,
+            pending: Vec::new(),
+            position: 0,
+            finished: false,
+        })
+    }
+
+    /// Next block with up to `max_frames` frames, only the last block can be shorter.
+    /// Returns `None` once all frames were returned.
+    pub fn next_block(
+        &mut self,
+        max_frames: usize,
+    ) -> Result<Option<AudioData<F>>, AudioReadError> {
+        if max_frames == 0 {
+            return Err(AudioReadError::InvalidBlockSize(max_frames));
+        }
+        self.fill(max_frames).map_err(|e| e.with_path(&self.path))?;
+
+        let num_channels = self.decoding.num_channels().unwrap_or(0);
+        if num_channels == 0 || self.pending.is_empty() {
+            return Ok(None);
+        }
+        let num_frames = max_frames.min(self.pending.len() / num_channels);
+        let interleaved_samples: Vec<F> = self.pending.drain(..num_frames * num_channels).collect();
+        let stats = self
+            .decoding
+            .levels
+            .is_some()
+            .then(|| AudioStats::from_samples(&interleaved_samples, num_channels));
+        self.position += num_frames;
+
+        let decoding = &self.decoding;
+        let mut source_format = decoding.source_format;
+        if let Some(format) = &mut source_format
+            && (num_channels != decoding.source_channels || decoding.selected_channels.is_some())
+        {
+            format.channel_mask = None;
+        }
+        Ok(Some(AudioData {
+            interleaved_samples,
+            sample_rate: decoding.sample_rate,
+            num_channels,
+            num_frames,
+            container: decoding.container,
+            source_num_frames: decoding.n_frames.map(|n| n as usize),
+            cues: Vec::new(),
+            loops: Vec::new(),
+            source_format,
+            tags: Tags::default(),
+            instrument: None,
+            data_offset: None,
+            data_len: None,
+            stats,
+        }))
+    }
+
+    /// Number of frames returned so far, which is the position of the next block in the output
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Decode packets until `max_frames` frames are pending or the end is reached.
+    fn fill(&mut self, max_frames: usize) -> Result<(), AudioReadError> {
+        while !self.finished {
+            if let Ok(num_channels) = self.decoding.num_channels()
+                && self.pending.len() >= max_frames * num_channels
+            {
+                return Ok(());
+            }
+            let Some(frames) = self.decoding.decode_next()? else {
+                self.finished = true;
+                self.pad_to_stop();
+                break;
+            };
+            let packet_samples = self.decoding.select(frames)?;
+            extend_converted(&mut self.pending, packet_samples)?;
+        }
+        Ok(())
+    }
+
+    /// Fill the output with zeros up to the stop position, see [`AudioReadConfig::pad_to_stop`]
+    fn pad_to_stop(&mut self) {
+        let decoding = &self.decoding;
+        let (true, Some(end_frame), Ok(num_channels)) = (
+            decoding.pad_to_stop,
+            decoding.end_frame,
+            decoding.num_channels(),
+        ) else {
+            return;
+        };
+        let total = (end_frame - decoding.start_frame).div_ceil(decoding.stride);
+        let available = self.position + self.pending.len() / num_channels;
+        let missing = total.saturating_sub(available);
+        self.pending
+            .resize(self.pending.len() + missing * num_channels, F::zero());
+    }
+}
+
+/// Statistics about a read, see [`audio_read_with_stats`].
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReadStats {
+    /// Number of decoded packets
+    pub num_packets: usize,
+    /// Approximate peak number of bytes allocated for the decoded samples
+    pub peak_bytes: usize,
+    /// Number of decoded frames before the start position, which were dropped after seeking.
+    /// A large value means that the seek landed far in front of the start position.
+    pub discarded_lead_frames: usize,
+    /// Number of non-finite samples that were replaced, see [`AudioReadConfig::sanitize_input`]
+    pub sanitized_samples: usize,
+}
+
+/// Same as [`audio_read`], but additionally returns statistics about the read.
+pub fn audio_read_with_stats<P: AsRef<Path>, F: Float>(
+    path: P,
+    config: AudioReadConfig,
+) -> Result<(AudioData<F>, ReadStats), AudioReadError> {
+    read_tracks(config, |config| Decoding::open(path.as_ref(), config))
+        .map_err(|e| e.with_path(path.as_ref()))
+}
+
+/// Decode the selected tracks, where `open` opens the file for decoding a single track.
+fn read_tracks<F: Float>(
+    config: AudioReadConfig,
+    open: impl Fn(&AudioReadConfig) -> Result<Decoding, AudioReadError>,
+) -> Result<(AudioData<F>, ReadStats), AudioReadError> {
+    let mono = config.mono.clone();
+    let envelope = config.gain_envelope.clone();
+    if let Some(pair) = envelope
+        .iter()
+        .flat_map(|envelope| envelope.windows(2))
+        .find(|pair| pair[1].0 < pair[0].0)
+    {
+        return Err(AudioReadError::UnsortedGainEnvelope(pair[0].0, pair[1].0));
+    }
+    #[cfg(feature = "timestretch")]
+    let time_stretch = config.time_stretch;
+    #[cfg(feature = "timestretch")]
+    if let Some(speed) =
+        time_stretch.filter(|speed| !crate::timestretch::SPEED_RANGE.contains(speed))
+    {
+        return Err(AudioReadError::InvalidTimeStretch(speed));
+    }
+
+    #[cfg(feature = "resample")]
+    let target_sample_rate = config.target_sample_rate;
+    let collect_stats = config.collect_stats;
+
+    let (mut data, mut stats) = combine_tracks(config, open)?;
+    #[cfg(feature = "resample")]
+    if let Some(target_sample_rate) = target_sample_rate
+        && target_sample_rate != data.sample_rate
+        && data.num_channels > 0
+    {
+        data.interleaved_samples = crate::resample::resample(
+            &data.interleaved_samples,
+            data.num_channels,
+            data.sample_rate,
+            target_sample_rate,
+        )?;
+        rescale_markers(&mut data, target_sample_rate);
+        data.sample_rate = target_sample_rate;
+        data.num_frames = data.interleaved_samples.len() / data.num_channels;
+        stats.peak_bytes += data.interleaved_samples.capacity() * size_of::<F>();
+        data.stats = None;
+    }
+    #[cfg(feature = "timestretch")]
+    if let Some(speed) = time_stretch {
+        data.interleaved_samples = crate::timestretch::time_stretch(
+            &data.interleaved_samples,
+            data.num_channels,
+            data.sample_rate,
+            speed,
+        );
+        data.num_frames = data.interleaved_samples.len() / data.num_channels.max(1);
+        stats.peak_bytes += data.interleaved_samples.capacity() * size_of::<F>();
+        data.stats = None;
+    }
+    if let Some(envelope) = envelope {
+        apply_gain_envelope(&mut data, &envelope)?;
+        data.stats = None;
+    }
+    if let Some(mono) = mono {
+        data = select_mono(data, mono)?;
+        stats.peak_bytes += data.interleaved_samples.capacity() * size_of::<F>();
+        data.stats = None;
+    }
+    // the levels are measured while decoding, unless the output was changed afterwards
+    if collect_stats && data.stats.is_none() {
+        data.stats = Some(AudioStats::from_samples(
+            &data.interleaved_samples,
+            data.num_channels,
+        ));
+    }
+    Ok((data, stats))
+}
+
+/// Convert the positions of the cues and loops from the sample rate of `data` to `sample_rate`.
+#[cfg(feature = "resample")]
+fn rescale_markers<F: Float>(data: &mut AudioData<F>, sample_rate: u32) {
+    let ratio = sample_rate as f64 / data.sample_rate as f64;
+    let rescale = |frame: &mut usize| *frame = (*frame as f64 * ratio).round() as usize;
+    for cue in &mut data.cues {
+        rescale(&mut cue.frame);
+    }
+    let instrument_loops = data.instrument.iter_mut().flat_map(|i| i.loops.iter_mut());
+    for region in data.loops.iter_mut().chain(instrument_loops) {
+        rescale(&mut region.start_frame);
+        rescale(&mut region.end_frame);
+    }
+}
+
+/// Multiply the frames with the linearly interpolated gain of the sorted `envelope`.
+fn apply_gain_envelope<F: Float>(
+    data: &mut AudioData<F>,
+    envelope: &[(usize, f64)],
+) -> Result<(), AudioReadError> {
+    let (Some(first), Some(last)) = (envelope.first(), envelope.last()) else {
+        return Ok(());
+    };
+    let num_channels = data.num_channels.max(1);
+    let mut segment = 0;
+    for (frame, samples) in data
+        .interleaved_samples
+        .chunks_exact_mut(num_channels)
+        .enumerate()
+    {
+        while segment + 1 < envelope.len() && envelope[segment + 1].0 <= frame {
+            segment += 1;
+        }
+        let gain = if frame < first.0 {
+            first.1
+        } else if segment + 1 == envelope.len() {
+            last.1
+        } else {
+            let (start, start_gain) = envelope[segment];
+            let (end, end_gain) = envelope[segment + 1];
+            let t = (frame - start) as f64 / (end - start) as f64;
+            start_gain + (end_gain - start_gain) * t
+        };
+        let gain: F = convert(gain)?;
+        samples.iter_mut().for_each(|s| *s = *s * gain);
+    }
+    Ok(())
+}
+
+/// Reduce the audio to a single channel.
+fn select_mono<F: Float>(
+    data: AudioData<F>,
+    mono: MonoSelect,
+) -> Result<AudioData<F>, AudioReadError> {
+    let num_channels = data.num_channels;
+    if num_channels == 0 {
+        return Ok(data);
+    }
+
+    let channel = match &mono {
+        MonoSelect::Downmix => None,
+        MonoSelect::Weighted(weights) if weights.len() != num_channels => {
+            return Err(AudioReadError::InvalidMonoWeights(
+                weights.len(),
+                num_channels,
+            ));
+        }
+        MonoSelect::Weighted(_) => None,
+        MonoSelect::Channel(channel) if *channel >= num_channels => {
+            return Err(AudioReadError::InvalidMonoChannel(*channel, num_channels));
+        }
+        MonoSelect::Channel(channel) => Some(*channel),
+        MonoSelect::HighestEnergy => {
+            let mut energy = vec![0.0; num_channels];
+            for frame in data.frames() {
+                for (energy, sample) in energy.iter_mut().zip(frame) {
+                    let sample = sample.to_f64().unwrap_or(0.0);
+                    *energy += sample * sample;
+                }
+            }
+            // the first channel wins if the energy is equal
+            let loudest = energy.iter().enumerate().fold(0, |loudest, (channel, e)| {
+                if *e > energy[loudest] {
+                    channel
+                } else {
+                    loudest
+                }
+            });
+            Some(loudest)
+        }
+    };
+
+    let samples: Vec<F> = match (channel, &mono) {
+        (Some(channel), _) => data.channel(channel).copied().collect(),
+        (None, MonoSelect::Weighted(weights)) => {
+            let weights = weights
+                .iter()
+                .map(|weight| convert::<F, _>(*weight))
+                .collect::<Result<Vec<_>, _>>()?;
+            data.frames()
+                .map(|frame| {
+                    let sum = frame
+                        .iter()
+                        .zip(&weights)
+                        .fold(F::zero(), |sum, (s, weight)| sum + *s * *weight);
+                    sum.max(-F::one()).min(F::one())
+                })
+                .collect()
+        }
+        (None, _) => {
+            let scale = convert::<F, _>(num_channels)?.recip();
+            data.frames()
+                .map(|frame| frame.iter().fold(F::zero(), |sum, s| sum + *s) * scale)
+                .collect()
+        }
+    };
+
+    // the channel mask does not describe a single selected channel
+    let source_format = data.source_format.map(|format| SourceFormat {
+        channel_mask: None,
+        ..format
+    });
+    Ok(AudioData {
+        interleaved_samples: samples,
+        num_channels: 1,
+        source_format,
+        ..data
+    })
+}
+
+/// Decode the selected tracks and combine them into one [`AudioData`].
+fn combine_tracks<F: Float>(
+    config: AudioReadConfig,
+    open: impl Fn(&AudioReadConfig) -> Result<Decoding, AudioReadError>,
+) -> Result<(AudioData<F>, ReadStats), AudioReadError> {
+    let TrackSelection::All { mix } = config.track else {
+        return collect(open(&config)?);
+    };
+
+    // the channels are selected after combining the tracks
+    let track_config = |track| AudioReadConfig {
+        track,
+        start_channel: None,
+        num_channels: None,
+        channels: None,
+        collect_stats: false,
+        ..config.clone()
+    };
+
+    let first = open(&track_config(TrackSelection::First))?;
+    let other_ids: Vec<u32> = first
+        .format
+        .tracks()
+        .iter()
+        .filter(|t| t.codec_params.codec != codecs::CODEC_TYPE_NULL && t.id != first.track_id)
+        .map(|t| t.id)
+        .collect();
+
+    let mut stats = ReadStats::default();
+    let mut add_stats = |(data, track_stats): (AudioData<F>, ReadStats)| {
+        stats.num_packets += track_stats.num_packets;
+        // all tracks are kept in memory until they are combined
+        stats.peak_bytes += track_stats.peak_bytes;
+        stats.discarded_lead_frames = stats
+            .discarded_lead_frames
+            .max(track_stats.discarded_lead_frames);
+        stats.sanitized_samples += track_stats.sanitized_samples;
+        data
+    };
+
+    let mut tracks = vec![add_stats(collect(first)?)];
+    for id in other_ids {
+        let decoding = open(&track_config(TrackSelection::Id(id)))?;
+        if decoding.sample_rate != tracks[0].sample_rate {
+            return Err(AudioReadError::SampleRateMismatch(
+                tracks[0].sample_rate,
+                decoding.sample_rate,
+            ));
+        }
+        tracks.push(add_stats(collect(decoding)?));
+    }
+
+    let num_frames = tracks.iter().map(|t| t.num_frames).max().unwrap_or(0);
+    let num_channels = if mix {
+        tracks.iter().map(|t| t.num_channels).max().unwrap_or(0)
+    } else {
+        tracks.iter().map(|t| t.num_channels).sum()
+    };
+
+    let mut samples = vec![F::zero(); num_frames * num_channels];
+    let mut channel_offset = 0;
+    for track in &tracks {
+        for (frame, track_frame) in samples
+            .chunks_exact_mut(num_channels)
+            .zip(track.interleaved_samples.chunks_exact(track.num_channels))
+        {
+            if !mix {
+                frame[channel_offset..channel_offset + track.num_channels]
+                    .copy_from_slice(track_frame);
+            } else if track.num_channels == 1 {
+                frame.iter_mut().for_each(|s| *s = *s + track_frame[0]);
+            } else {
+                for (s, t) in frame.iter_mut().zip(track_frame) {
+                    *s = *s + *t;
+                }
+            }
+        }
+        channel_offset += track.num_channels;
+    }
+
+    stats.peak_bytes += samples.capacity() * size_of::<F>();
+
+    let channels: Vec<usize> = match (&config.channels_by_label, config.channels) {
+        (Some(_), _) => (0..num_channels).collect(),
+        (None, Some(channels)) => {
+            check_channels(&channels, num_channels)?;
+            channels
+        }
+        (None, None) => {
+            channel_range(config.start_channel, config.num_channels, num_channels)?.collect()
+        }
+    };
+    if !channels.iter().copied().eq(0..num_channels) {
+        let selected: Vec<F> = samples
+            .chunks_exact(num_channels)
+            .flat_map(|frame| channels.iter().map(|channel| frame[*channel]))
+            .collect();
+        stats.peak_bytes += selected.capacity() * size_of::<F>();
+        samples = selected;
+    }
+
+    let data = AudioData {
+        interleaved_samples: samples,
+        sample_rate: tracks[0].sample_rate,
+        num_channels: channels.len(),
+        num_frames,
+        container: tracks[0].container,
+        source_num_frames: tracks.iter().filter_map(|t| t.source_num_frames).max(),
+        cues: tracks[0].cues.clone(),
+        loops: tracks[0].loops.clone(),
+        source_format: None,
+        tags: tracks[0].tags.clone(),
+        instrument: tracks[0].instrument.clone(),
+        // the samples of several tracks are not stored in one place
+        data_offset: None,
+        data_len: None,
+        stats: None,
+    };
+    Ok((data, stats))
+}
+
+/// Decode all remaining packets into [`AudioData`].
+fn collect<F: Float + 'static>(
+    decoding: Decoding,
+) -> Result<(AudioData<F>, ReadStats), AudioReadError> {
+    let mut decoding = decoding.for_output::<F>();
+    let mut samples = Vec::new();
+    let mut num_packets = 0;
+    while let Some(frames) = decoding.decode_next()? {
+        num_packets += 1;
+
+        // fast path for reading whole packets, which copies f32 samples without conversion
+        if let Some(packet_samples) = decoding.whole_packet_f32(&frames) {
+            match (&mut samples as &mut dyn std::any::Any).downcast_mut::<Vec<f32>>() {
+                Some(samples) => samples.extend_from_slice(packet_samples),
+                None => extend_converted(&mut samples, packet_samples)?,
+            }
+            continue;
+        }
+
+        let packet_samples = decoding.select(frames)?;
+        extend_converted(&mut samples, packet_samples)?;
+    }
+
+    let num_channels = decoding.num_channels()?;
+    if decoding.pad_to_stop
+        && let Some(end_frame) = decoding.end_frame
+    {
+        let num_frames = (end_frame - decoding.start_frame).div_ceil(decoding.stride);
+        samples.resize(num_frames * num_channels, F::zero());
+    }
+    let num_frames = samples.len() / num_channels;
+
+    // the channel mask only describes the output if all channels were read
+    let mut source_format = decoding.source_format;
+    if let Some(format) = &mut source_format
+        && (num_channels != decoding.source_channels || decoding.selected_channels.is_some())
+    {
+        format.channel_mask = None;
+    }
+
+    let stats = ReadStats {
+        num_packets,
+        peak_bytes: samples.capacity() * size_of::<F>(),
+        discarded_lead_frames: decoding.discarded_lead_frames,
+        sanitized_samples: decoding.sanitized_samples,
+    };
+    let data = AudioData {
+        sample_rate: decoding.sample_rate,
+        num_channels,
+        num_frames,
+        interleaved_samples: samples,
+        container: decoding.container,
+        source_num_frames: decoding.n_frames.map(|n| n as usize),
+        cues: decoding.markers.cues,
+        loops: decoding.markers.loops,
+        source_format,
+        tags: decoding.tags,
+        instrument: decoding.markers.instrument,
+        data_offset: decoding.markers.data_chunk.map(|(offset, _)| offset),
+        data_len: decoding.markers.data_chunk.map(|(_, len)| len),
+        stats: decoding
+            .levels
+            .map(|levels| levels.finish(num_channels, num_frames)),
+    };
+    Ok((data, stats))
+}
+
+/// Sample format of the file that was read, see [`AudioData::source_format`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceFormat {
+    pub sample_format: SampleFormat,
+    pub bits_per_sample: u32,
+    /// Speaker positions of the channels in the order of the WAV channel mask.
+    /// Only set if all channels of the file were read.
+    pub channel_mask: Option<u32>,
+}
+
+impl SourceFormat {
+    fn from_params(params: &CodecParameters) -> Option<Self> {
+        let (sample_format, bits_per_sample) = match params.codec {
+            codecs::CODEC_TYPE_PCM_F32LE | codecs::CODEC_TYPE_PCM_F32BE => {
+                (SampleFormat::Float, 32)
+            }
+            codecs::CODEC_TYPE_PCM_F64LE | codecs::CODEC_TYPE_PCM_F64BE => {
+                (SampleFormat::Float, 64)
+            }
+            _ => (SampleFormat::Int, params.bits_per_sample?),
+        };
+        Some(Self {
+            sample_format,
+            bits_per_sample,
+            channel_mask: params.channels.map(|channels| channels.bits()),
+        })
+    }
+}
+
+/// Type of the samples in [`PackedAudio`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SampleFormat {
+    /// Signed integer
+    Int,
+    /// IEEE float
+    Float,
+}
+
+/// Decoded samples in the native format of the source, see [`audio_read_packed`]
+#[derive(Debug)]
+pub struct PackedAudio {
+    /// Interleaved samples as little endian bytes
+    pub bytes: Vec<u8>,
+    pub sample_format: SampleFormat,
+    /// Bits per sample, each sample takes `bits_per_sample / 8` bytes
+    pub bits_per_sample: u32,
+    pub num_channels: usize,
+    pub num_frames: usize,
+    pub sample_rate: u32,
+}
+
+/// Read the decoded samples as bytes, without converting them to float.
+///
+/// The samples are interleaved and always little endian, independent of the platform and the
+/// file. 8-bit samples are widened to 16 bits, unsigned samples are converted to signed.
+/// Only a single track is read, [`TrackSelection::All`] reads the first track.
+pub fn audio_read_packed<P: AsRef<Path>>(
+    path: P,
+    config: AudioReadConfig,
+) -> Result<PackedAudio, AudioReadError> {
+    read_packed(path.as_ref(), config).map_err(|e| e.with_path(path.as_ref()))
+}
+
+fn read_packed(path: &Path, config: AudioReadConfig) -> Result<PackedAudio, AudioReadError> {
+    let mut decoding = Decoding::open(path, &config)?;
+    decoding.packed = true;
+
+    let mut bytes = Vec::new();
+    while decoding.next_packet_bytes(&mut bytes)? {}
+
+    let sample_format = match decoding.decode_into {
+        DecodeInto::I16 | DecodeInto::I32 => SampleFormat::Int,
+        DecodeInto::Auto | DecodeInto::F32 | DecodeInto::F64 => SampleFormat::Float,
+    };
+    let num_channels = decoding.num_channels()?;
+    let bytes_per_frame = num_channels * decoding.bits_per_sample as usize / 8;
+
+    Ok(PackedAudio {
+        num_frames: bytes.len() / bytes_per_frame,
+        bytes,
+        sample_format,
+        bits_per_sample: decoding.bits_per_sample,
+        num_channels,
+        sample_rate: decoding.sample_rate,
+    })
+}
+
+/// Decoded samples with one buffer per channel, see [`audio_read_planar`]
+#[derive(Debug, Clone)]
+pub struct PlanarAudio<F> {
+    /// The sample of frame `n` in channel `c` is at `channels[c][n]`
+    pub channels: Vec<Vec<F>>,
+    pub num_channels: usize,
+    pub num_frames: usize,
+    pub sample_rate: u32,
+}
+
+/// Read the selected audio directly into one buffer per channel.
+///
+/// Same as [`AudioData::into_planar`] after [`audio_read`], but without the interleaved buffer
+/// in between. The frame and channel selection, stride, headroom, sanitizing and padding to
+/// the stop position are applied as in [`audio_read`]. The transforms that need the whole
+/// output (resampling, time stretch, gain envelope and mono selection) are not applied. Only a
+/// single track is read, [`TrackSelection::All`] reads the first track.
+pub fn audio_read_planar<P: AsRef<Path>, F: Float>(
+    path: P,
+    config: AudioReadConfig,
+) -> Result<PlanarAudio<F>, AudioReadError> {
+    read_planar(path.as_ref(), config).map_err(|e| e.with_path(path.as_ref()))
+}
+
+fn read_planar<F: Float>(
+    path: &Path,
+    config: AudioReadConfig,
+) -> Result<PlanarAudio<F>, AudioReadError> {
+    let mut decoding = Decoding::open(path, &config)?.for_output::<F>();
+
+    let mut channels: Vec<Vec<F>> = Vec::new();
+    while let Some(frames) = decoding.decode_next()? {
+        let num_channels = decoding.num_channels()?;
+        if channels.is_empty() {
+            // reserve the expected length once, so the buffers don't grow while decoding. The
+            // stop can be past the end of the file, so only the length of the file is trusted.
+            let n_frames = decoding.n_frames.map_or(0, |n| n as usize);
+            let end_frame = decoding.end_frame.map_or(n_frames, |end| end.min(n_frames));
+            let capacity = end_frame.saturating_sub(decoding.start_frame) / decoding.stride;
+            channels = (0..num_channels)
+                .map(|_| Vec::with_capacity(capacity))
+                .collect();
+        }
+        let samples = decoding.select(frames)?;
+        for frame in samples.chunks_exact(num_channels) {
+            for (channel, sample) in channels.iter_mut().zip(frame) {
+                channel.push(convert(*sample)?);
+            }
+        }
+    }
+
+    let num_channels = decoding.num_channels()?;
+    channels.resize_with(num_channels, Vec::new);
+    let mut num_frames = channels.first().map_or(0, Vec::len);
+    if decoding.pad_to_stop
+        && let Some(end_frame) = decoding.end_frame
+    {
+        num_frames = (end_frame - decoding.start_frame).div_ceil(decoding.stride);
+        for channel in &mut channels {
+            channel.resize(num_frames, F::zero());
+        }
+    }
+
+    Ok(PlanarAudio {
+        channels,
+        num_channels,
+        num_frames,
+        sample_rate: decoding.sample_rate,
+    })
+}
+
+/// Read audio from a memory-mapped file.
+///
+/// Instead of copying the file through buffered IO, the operating system pages in only the
+/// regions that are decoded. This is faster for very large files, especially when seeking to a
+/// late start position.
+///
+/// The file must not be modified or truncated by another process while it is read, which would
+/// invalidate the mapping and lead to undefined behavior.
+#[cfg(feature = "mmap")]
+pub fn audio_read_mmap<P: AsRef<Path>, F: Float>(
+    path: P,
+    config: AudioReadConfig,
+) -> Result<AudioData<F>, AudioReadError> {
+    read_mmap(path.as_ref(), config).map_err(|e| e.with_path(path.as_ref()))
+}
+
+#[cfg(feature = "mmap")]
+fn read_mmap<F: Float>(
+    path: &Path,
+    config: AudioReadConfig,
+) -> Result<AudioData<F>, AudioReadError> {
+    let file = File::open(path)?;
+    // SAFETY: the mapping is only read, the caller guarantees that the file is not truncated
+    // while it is mapped.
+    let mmap = unsafe { memmap2::Mmap::map(&file)? };
+    let data = std::sync::Arc::new(mmap);
+    let (data, _) = read_tracks(config, |config| {
+        let source = std::io::Cursor::new(MmapSlice(data.clone()));
+        Decoding::from_source(Box::new(source), path_extension(path), config)
+    })?;
+    Ok(data)
+}
+
+/// Shared memory mapping, so multiple tracks can be decoded from a single mapping.
+#[cfg(feature = "mmap")]
+struct MmapSlice(std::sync::Arc<memmap2::Mmap>);
+
+#[cfg(feature = "mmap")]
+impl AsRef<[u8]> for MmapSlice {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Check that a file decodes from start to end, without keeping the decoded audio.
+///
+/// Returns the first error that occurs while decoding, or [`AudioReadError::TruncatedStream`]
+/// if the file contains fewer frames than its header announces.
+pub fn audio_validate<P: AsRef<Path>>(path: P) -> Result<(), AudioReadError> {
+    validate(path.as_ref()).map_err(|e| e.with_path(path.as_ref()))
+}
+
+fn validate(path: &Path) -> Result<(), AudioReadError> {
+    let mut decoding = Decoding::open(path, &AudioReadConfig::default())?;
+
+    let mut num_samples = 0;
+    while let Some(packet_samples) = decoding.next_packet()? {
+        num_samples += packet_samples.len();
+    }
+
+    if let Some(expected) = decoding.n_frames {
+        let decoded = num_samples / decoding.num_channels().unwrap_or(1);
+        if (decoded as u64) < expected {
+            return Err(AudioReadError::TruncatedStream(decoded, expected as usize));
+        }
+    }
+
+    Ok(())
+}
+
+/// Hash of the decoded samples of the selected frames and channels, e.g. to find duplicates
+/// with the same audio but different tags or containers.
+///
+/// The file is decoded in one pass and the samples are hashed with XXH3, together with the
+/// number of channels and the sample rate. Lossless copies hash the same, lossy encodings
+/// don't. Start, stop and channel selection scope the hash, padding to the stop is not applied.
+/// Only a single track is read, [`TrackSelection::All`] reads the first track.
+#[cfg(feature = "hash")]
+pub fn audio_content_hash<P: AsRef<Path>>(
+    path: P,
+    config: AudioReadConfig,
+) -> Result<u64, AudioReadError> {
+    content_hash(path.as_ref(), &config).map_err(|e| e.with_path(path.as_ref()))
+}
+
+#[cfg(feature = "hash")]
+fn content_hash(path: &Path, config: &AudioReadConfig) -> Result<u64, AudioReadError> {
+    let mut decoding = Decoding::open(path, config)?;
+    let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+    let mut bytes = Vec::new();
+    while let Some(samples) = decoding.next_packet()? {
+        bytes.clear();
+        bytes.extend(samples.iter().flat_map(|s| s.to_le_bytes()));
+        hasher.update(&bytes);
+    }
+    let num_channels = decoding.num_channels().unwrap_or(0) as u64;
+    hasher.update(&num_channels.to_le_bytes());
+    hasher.update(&decoding.sample_rate.to_le_bytes());
+    Ok(hasher.digest())
+}
+
+/// Track chosen by [`open_format`]
+#[derive(Debug, Clone)]
+pub struct TrackInfo {
+    /// Id of the track, packets of other tracks can be skipped
+    pub id: u32,
+    pub codec_params: CodecParameters,
+    pub container: Container,
+}
+
+/// Probe a file and return the symphonia format reader together with its first audio track.
+///
+/// This is an escape hatch for features this crate does not wrap (e.g. chapters, metadata
+/// revisions or custom seeking): the caller decodes the packets. The types are those of the
+/// re-exported [`symphonia`] crate, so this API changes with symphonia.
+pub fn open_format<P: AsRef<Path>>(
+    path: P,
+) -> Result<(Box<dyn FormatReader>, TrackInfo), AudioReadError> {
+    open_format_inner(path.as_ref()).map_err(|e| e.with_path(path.as_ref()))
+}
+
+fn open_format_inner(path: &Path) -> Result<(Box<dyn FormatReader>, TrackInfo), AudioReadError> {
+    let mss = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+    let (format, container, _) = probe_format(
+        mss,
+        path_extension(path),
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    let track = first_audio_track(format.as_ref())?;
+    let info = TrackInfo {
+        id: track.id,
+        codec_params: track.codec_params.clone(),
+        container,
+    };
+    Ok((format, info))
+}
+
+/// Format of a file as reported by its header, see [`audio_probe_info`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AudioInfo {
+    pub container: Container,
+    /// Name of the codec (e.g. `"FLAC"`)
+    pub codec: String,
+    pub sample_rate: Option<u32>,
+    pub num_channels: Option<usize>,
+    /// Number of frames, if the container stores it
+    pub num_frames: Option<usize>,
+    /// Duration computed from the number of frames, if the container stores it
+    pub duration: Option<std::time::Duration>,
+}
+
+/// Read the format of the first audio track from the header, without decoding.
+///
+/// This is much cheaper than reading the file, e.g. to decide whether to load or stream it.
+/// Values that the container does not store are `None`.
+pub fn audio_probe_info<P: AsRef<Path>>(path: P) -> Result<AudioInfo, AudioReadError> {
+    probe_info(path.as_ref()).map_err(|e| e.with_path(path.as_ref()))
+}
+
+fn probe_info(path: &Path) -> Result<AudioInfo, AudioReadError> {
+    let (_, track) = open_format_inner(path)?;
+    let params = &track.codec_params;
+    let duration = params.n_frames.and_then(|n_frames| {
+        let time_base = params
+            .time_base
+            .or(params.sample_rate.map(|rate| TimeBase::new(1, rate)))?;
+        let time = time_base.calc_time(n_frames);
+        Some(
+            std::time::Duration::from_secs(time.seconds)
+                + std::time::Duration::from_secs_f64(time.frac),
+        )
+    });
+    Ok(AudioInfo {
+        container: track.container,
+        codec: codec_name(params.codec),
+        sample_rate: params.sample_rate,
+        num_channels: params.channels.map(|channels| channels.count()),
+        num_frames: params.n_frames.map(|n| n as usize),
+        duration,
+    })
+}
+
+/// Read the metadata tags of a file without decoding the audio.
+///
+/// Returns the same tags as [`AudioData::tags`], e.g. to catalogue a library.
+pub fn audio_read_tags<P: AsRef<Path>>(path: P) -> Result<Tags, AudioReadError> {
+    read_tags_only(path.as_ref()).map_err(|e| e.with_path(path.as_ref()))
+}
+
+fn read_tags_only(path: &Path) -> Result<Tags, AudioReadError> {
+    let mss = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+    let (mut format, _, mut metadata) = probe_format(
+        mss,
+        path_extension(path),
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    Ok(read_tags(&mut metadata, format.as_mut()))
+}
+
+/// Read the pictures embedded in the metadata of a file (e.g. the album cover), without
+/// decoding the audio.
+///
+/// Returns an empty list if the file has no pictures.
+pub fn audio_read_cover_art<P: AsRef<Path>>(path: P) -> Result<Vec<CoverArt>, AudioReadError> {
+    read_cover_art(path.as_ref()).map_err(|e| e.with_path(path.as_ref()))
+}
+
+fn read_cover_art(path: &Path) -> Result<Vec<CoverArt>, AudioReadError> {
+    let mss = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+    let (mut format, _, mut metadata) = probe_format(
+        mss,
+        path_extension(path),
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    let mut cover_art = Vec::new();
+    let mut push = |revision: Option<&MetadataRevision>| {
+        for visual in revision.map(MetadataRevision::visuals).unwrap_or_default() {
+            cover_art.push(CoverArt {
+                media_type: visual.media_type.clone(),
+                data: visual.data.to_vec(),
+                usage: visual.usage.map(|usage| format!("{usage:?}")),
+            });
+        }
+    };
+    push(metadata.metadata().current());
+    push(format.metadata().current());
+    Ok(cover_art)
+}
+
+/// Read segment `segment_index` of `num_segments` contiguous segments of equal length, e.g. to
+/// process a file in parallel.
+///
+/// Segment `i` covers the frames `i * len..(i + 1) * len` with `len = num_frames / num_segments`,
+/// the last segment additionally contains the remaining frames. The start and stop of the
+/// config are replaced by the segment, all other options apply. The file needs to report its
+/// number of frames, otherwise [`AudioReadError::UnknownNumFrames`] is returned.
+pub fn audio_read_segment<P: AsRef<Path>, F: Float>(
+    path: P,
+    segment_index: usize,
+    num_segments: usize,
+    config: AudioReadConfig,
+) -> Result<AudioData<F>, AudioReadError> {
+    read_segment(path.as_ref(), segment_index, num_segments, config)
+        .map_err(|e| e.with_path(path.as_ref()))
+}
+
+fn read_segment<F: Float>(
+    path: &Path,
+    segment_index: usize,
+    num_segments: usize,
+    config: AudioReadConfig,
+) -> Result<AudioData<F>, AudioReadError> {
+    if segment_index >= num_segments {
+        return Err(AudioReadError::InvalidSegment(segment_index, num_segments));
+    }
+    let num_frames = Decoding::open(path, &config)?
+        .n_frames
+        .ok_or(AudioReadError::UnknownNumFrames)? as usize;
+
+    let len = num_frames / num_segments;
+    let start = segment_index * len;
+    let stop = if segment_index + 1 == num_segments {
+        num_frames
+    } else {
+        start + len
+    };
+    let config = AudioReadConfig {
+        start: Position::Frame(start),
+        stop: Position::Frame(stop),
+        ..config
+    };
+    let (data, _) = read_tracks(config, |config| Decoding::open(path, config))?;
+    Ok(data)
+}
+
+/// Read the frames from the cue labeled `start_label` up to the cue labeled `stop_label`, e.g. a
+/// region between two markers of an editor.
+///
+/// The first cue with each label is used, see [`AudioData::cues`]. The start and stop of the
+/// config are replaced by the cues, all other options apply.
+pub fn audio_read_region_by_cues<P: AsRef<Path>, F: Float>(
+    path: P,
+    start_label: &str,
+    stop_label: &str,
+    config: AudioReadConfig,
+) -> Result<AudioData<F>, AudioReadError> {
+    read_region_by_cues(path.as_ref(), start_label, stop_label, config)
+        .map_err(|e| e.with_path(path.as_ref()))
+}
+
+fn read_region_by_cues<F: Float>(
+    path: &Path,
+    start_label: &str,
+    stop_label: &str,
+    config: AudioReadConfig,
+) -> Result<AudioData<F>, AudioReadError> {
+    let cues = Decoding::open(path, &config)?.markers.cues;
+    let find = |label: &str| {
+        cues.iter()
+            .find(|cue| cue.label == label)
+            .map(|cue| cue.frame)
+            .ok_or_else(|| AudioReadError::CueNotFound(label.to_string()))
+    };
+    let (start, stop) = (find(start_label)?, find(stop_label)?);
+    if stop < start {
+        return Err(AudioReadError::CuesOutOfOrder(
+            start_label.to_string(),
+            stop_label.to_string(),
+        ));
+    }
+
+    let config = AudioReadConfig {
+        start: Position::Frame(start),
+        stop: Position::Frame(stop),
+        ..config
+    };
+    let (data, _) = read_tracks(config, |config| Decoding::open(path, config))?;
+    Ok(data)
+}
+
+/// RMS of the downmixed selected channels in consecutive windows of length `window`, e.g. as
+/// input for voice activity detection.
+///
+/// The file is decoded in one pass without keeping the audio in memory. The last window can be
+/// shorter. Only a single track is read, [`TrackSelection::All`] reads the first track.
+pub fn audio_energy_envelope<P: AsRef<Path>, F: Float>(
+    path: P,
+    window: std::time::Duration,
+    config: AudioReadConfig,
+) -> Result<Vec<F>, AudioReadError> {
+    energy_envelope(path.as_ref(), window, &config).map_err(|e| e.with_path(path.as_ref()))
+}
+
+fn energy_envelope<F: Float>(
+    path: &Path,
+    window: std::time::Duration,
+    config: &AudioReadConfig,
+) -> Result<Vec<F>, AudioReadError> {
+    let mut decoding = Decoding::open(path, config)?.for_output::<F>();
+    let window_frames = (window.as_secs_f64() * decoding.sample_rate as f64).round() as usize;
+    if window_frames == 0 {
+        return Err(AudioReadError::InvalidWindow(window_frames, window_frames));
+    }
+
+    let mut envelope = Vec::new();
+    let mut sum = 0.0;
+    let mut frames_in_window = 0;
+    while let Some(frames) = decoding.decode_next()? {
+        let num_channels = decoding.num_channels()?;
+        for frame in decoding.select(frames)?.chunks_exact(num_channels) {
+            let downmix = frame.iter().sum::<f64>() / num_channels as f64;
+            sum += downmix * downmix;
+            frames_in_window += 1;
+            if frames_in_window == window_frames {
+                envelope.push(convert((sum / window_frames as f64).sqrt())?);
+                sum = 0.0;
+                frames_in_window = 0;
+            }
+        }
+    }
+    if frames_in_window > 0 {
+        envelope.push(convert((sum / frames_in_window as f64).sqrt())?);
+    }
+    Ok(envelope)
+}
+
+/// Check if the left and right channel of a stereo file differ by at most `epsilon`.
+///
+/// The file is decoded packet by packet and reading stops at the first frame that differs.
+pub fn audio_is_dual_mono<P: AsRef<Path>, F: Float>(
+    path: P,
+    epsilon: F,
+) -> Result<bool, AudioReadError> {
+    is_dual_mono(path.as_ref(), epsilon).map_err(|e| e.with_path(path.as_ref()))
+}
+
+fn is_dual_mono<F: Float>(path: &Path, epsilon: F) -> Result<bool, AudioReadError> {
+    let mut decoding = Decoding::open(path, &AudioReadConfig::default())?;
+
+    while let Some(frames) = decoding.decode_next()? {
+        let num_channels = decoding.num_channels()?;
+        if num_channels != 2 {
+            return Err(AudioReadError::NotStereo(num_channels));
+        }
+        for frame in decoding.select(frames)?.chunks_exact(2) {
+            let difference: F = convert(frame[0] - frame[1])?;
+            if difference.abs() > epsilon {
+                return Ok(false);
+            }
+        }
+    }
+
+    // the number of channels is only known after a packet was decoded
+    decoding.num_channels()?;
+    Ok(true)
+}
+
+/// State of an opened audio file, which is decoded packet by packet.
+struct Decoding {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    /// Selection of the track, which is repeated for every stream of a chained file
+    track_selection: TrackSelection,
+    /// Position of the track among the audio tracks of the stream
+    track_index: usize,
+    time_base: Option<TimeBase>,
+    sample_rate: u32,
+    container: Container,
+    markers: Markers,
+    /// Number of frames in the file, if the container reports it
+    n_frames: Option<u64>,
+    source_format: Option<SourceFormat>,
+    tags: Tags,
+    start_frame: usize,
+    end_frame: Option<usize>,
+    /// Stop after this many packets with selected frames
+    max_packets: Option<usize>,
+    /// Number of packets with selected frames so far
+    num_packets: usize,
+    start_channel: Option<usize>,
+    requested_channels: Option<usize>,
+    channel_list: Option<Vec<usize>>,
+    channel_labels: Option<Vec<ChannelLabel>>,
+    /// Selected channels of the file from `channel_list` or at the positions of
+    /// `channel_labels`, known after the first packet
+    selected_channels: Option<Vec<usize>>,
+    pad_to_stop: bool,
+    max_channels: Option<usize>,
+    /// Replace non-finite samples, see [`AudioReadConfig::sanitize_input`]
+    sanitize: bool,
+    sanitized_samples: usize,
+    /// Levels of the selected samples, see [`AudioReadConfig::collect_stats`]
+    levels: Option<LevelMeter>,
+    decode_into: DecodeInto,
+    /// Linear gain of the headroom
+    gain: f64,
+    /// Timestamp of the current packet in the time base of the track
+    packet_ts: u64,
+    /// Position of the first frame of the current packet in the file
+    packet_pos: u64,
+    /// Keep every `stride` frame
+    stride: usize,
+    /// Frames within the selected range so far, before the stride is applied
+    frames_in_range: usize,
+    /// Decode into the native sample type of the source, see [`audio_read_packed`]
+    packed: bool,
+    /// Bits per sample of the native sample type
+    bits_per_sample: u32,
+    sample_buf: Option<PacketBuffer>,
+    /// Number of channels in the file, known after the first decoded packet
+    source_channels: usize,
+    /// We'll track exact position by counting samples as we decode
+    current_sample: Option<u64>,
+    /// Position at which the current stream of a chained file (e.g. Ogg) started
+    stream_offset: u64,
+    /// Decoded frames in front of the start frame
+    discarded_lead_frames: usize,
+    deadline: Option<Instant>,
+    /// Samples of the current packet, reduced to the selected frames and channels
+    selected: Vec<f64>,
+    finished: bool,
+}
+
+impl Decoding {
+    /// Decode into the type that keeps the precision of the output type `F`, if the sample type
+    /// was not chosen in the configuration.
+    fn for_output<F: Float>(mut self) -> Self {
+        self.decode_into = self.decode_into.for_output::<F>();
+        self
+    }
+
+    fn open(path: &Path, config: &AudioReadConfig) -> Result<Self, AudioReadError> {
+        let src = File::open(path)?;
+        Self::from_source(Box::new(src), path_extension(path), config)
+    }
+
+    fn from_source(
+        mut source: Box<dyn MediaSource>,
+        hint: Option<&str>,
+        config: &AudioReadConfig,
+    ) -> Result<Self, AudioReadError> {
+        let hint = hint.or(config.default_hint.as_deref());
+        if source.byte_len() == Some(0) {
+            return Err(AudioReadError::EmptyStream);
+        }
+
+        // metadata that symphonia does not read, missing or broken markers are ignored
+        let mut markers = Markers::default();
+        if source.is_seekable() {
+            markers = chunks::read_markers(&mut source).unwrap_or_default();
+            source.seek(SeekFrom::Start(0))?;
+        }
+        let mss = MediaSourceStream::new(source, Default::default());
+
+        let meta_opts: MetadataOptions = Default::default();
+        let fmt_opts: FormatOptions = Default::default();
+
+        let (mut format, container, mut metadata) = probe_format(mss, hint, &fmt_opts, &meta_opts)?;
+        let tags = read_tags(&mut metadata, format.as_mut());
+
+        let track = select_track(format.as_ref(), config.track, None)?;
+        let track_index = audio_track_index(format.as_ref(), track.id);
+
+        let sample_rate = track
+            .codec_params
+            .sample_rate
+            .ok_or(AudioReadError::NoSampleRate)?;
+
+        let track_id = track.id;
+
+        // Clone codec params before the mutable borrow
+        let codec_params = track.codec_params.clone();
+        let time_base = track.codec_params.time_base;
+
+        // the position of the data is only useful if the bytes are the samples
+        if !is_pcm(codec_params.codec) {
+            markers.data_chunk = None;
+        }
+
+        // Convert start/stop positions to frame numbers
+        let start_frame = match config.start {
+            Position::Default => 0,
+            Position::Time(duration) => {
+                let secs = duration.as_secs_f64();
+                (secs * sample_rate as f64) as usize
+            }
+            Position::Frame(frame) => frame,
+            Position::Packets(_) => return Err(AudioReadError::PacketsAsStart),
+        };
+
+        let end_frame: Option<usize> = match config.stop {
+            Position::Default => None,
+            Position::Time(duration) => {
+                let secs = duration.as_secs_f64();
+                Some((secs * sample_rate as f64) as usize)
+            }
+            Position::Frame(frame) => Some(frame),
+            Position::Packets(_) => None,
+        };
+        let max_packets = match config.stop {
+            Position::Packets(packets) => Some(packets),
+            _ => None,
+        };
+
+        if let Some(end_frame) = end_frame
+            && start_frame > end_frame
+        {
+            return Err(AudioReadError::EndFrameLargerThanStartFrame(
+                end_frame,
+                start_frame,
+            ));
+        }
+        let gain = match config.headroom_db {
+            Some(db) if !db.is_finite() || db < 0.0 => {
+                return Err(AudioReadError::InvalidHeadroom(db));
+            }
+            Some(db) => 10f64.powf(-db / 20.0),
+            None => 1.0,
+        };
+
+        let stride = match config.stride_frames {
+            Some(0) => return Err(AudioReadError::InvalidStride(0)),
+            Some(stride) => stride,
+            None => 1,
+        };
+
+        if let Some(length) = codec_params.n_frames
+            && start_frame as u64 > length
+        {
+            return Err(AudioReadError::StartBeyondEnd {
+                start: start_frame,
+                length: length as usize,
+            });
+        }
+
+        if let Some(channels) = codec_params.channels {
+            check_max_channels(channels.count(), config.max_channels)?;
+        }
+
+        // If start_frame is large (more than 1 second), use seeking to avoid decoding everything
+        if start_frame > sample_rate as usize
+            && !config.disable_seek
+            && let Some(tb) = time_base
+        {
+            // Seek to 90% of the target to account for keyframe positioning
+            let seek_sample = (start_frame as f64 * 0.9) as u64;
+            let seek_ts = (seek_sample * tb.denom as u64) / (sample_rate as u64);
+
+            // Try to seek, but don't fail if seeking doesn't work
+            let _ = format.seek(
+                SeekMode::Accurate,
+                SeekTo::TimeStamp {
+                    ts: seek_ts,
+                    track_id,
+                },
+            );
+        }
+
+        let decoder = make_decoder(&codec_params)?;
+
+        Ok(Self {
+            format,
+            decoder,
+            track_id,
+            track_selection: config.track,
+            track_index,
+            time_base,
+            sample_rate,
+            container,
+            markers,
+            n_frames: codec_params.n_frames,
+            source_format: SourceFormat::from_params(&codec_params),
+            tags,
+            start_frame,
+            end_frame,
+            max_packets,
+            num_packets: 0,
+            start_channel: config.start_channel,
+            requested_channels: config.num_channels,
+            channel_list: config.channels.clone(),
+            channel_labels: config.channels_by_label.clone(),
+            selected_channels: None,
+            pad_to_stop: config.pad_to_stop,
+            max_channels: config.max_channels,
+            sanitize: config.sanitize_input,
+            sanitized_samples: 0,
+            levels: config.collect_stats.then(LevelMeter::default),
+            decode_into: config.decode_into,
+            gain,
+            stride,
+            frames_in_range: 0,
+            packet_ts: 0,
+            packet_pos: 0,
+            packed: false,
+            bits_per_sample: 0,
+            sample_buf: None,
+            source_channels: 0,
+            current_sample: None,
+            stream_offset: 0,
+            discarded_lead_frames: 0,
+            deadline: config.deadline,
+            selected: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// Continue with the next stream after the format reader requested a reset.
+    ///
+    /// A new stream in a chained file (e.g. Ogg) comes with new tracks, so the track and decoder
+    /// have to be set up again. Timestamps of the new stream start from zero, so positions are
+    /// counted on from where the previous stream ended.
+    fn restart_stream(&mut self) -> Result<(), AudioReadError> {
+        let format = self.format.as_ref();
+        let track = select_track(format, self.track_selection, Some(self.track_index))?;
+        self.track_index = audio_track_index(format, track.id);
+        self.track_id = track.id;
+        self.time_base = track.codec_params.time_base;
+        self.decoder = make_decoder(&track.codec_params)?;
+
+        self.stream_offset = self.current_sample.unwrap_or(self.stream_offset);
+        self.current_sample = None;
+        // the packet size of the new stream can be different
+        self.sample_buf = None;
+        Ok(())
+    }
+
+    /// Number of channels in the output.
+    ///
+    /// Fails with [`AudioReadError::EmptyStream`] if no packet was decoded, so the number of
+    /// channels is unknown.
+    fn num_channels(&self) -> Result<usize, AudioReadError> {
+        if self.source_channels == 0 {
+            return Err(AudioReadError::EmptyStream);
+        }
+        Ok(self
+            .channel_ranges()?
+            .iter()
+            .map(|channels| channels.len())
+            .sum())
+    }
+
+    /// Decode the next packet of the track and return its samples within the selected range.
+    ///
+    /// Returns `None` once the end of the stream or the end frame is reached.
+    fn next_packet(&mut self) -> Result<Option<&[f64]>, AudioReadError> {
+        let Some(frames) = self.decode_next()? else {
+            return Ok(None);
+        };
+        self.select(frames).map(Some)
+    }
+
+    /// Samples of the selected `frames` and channels of the current packet.
+    fn select(&mut self, frames: std::ops::Range<usize>) -> Result<&[f64], AudioReadError> {
+        self.selected.clear();
+        let frames = self.strided(frames);
+        if let Some(buf) = &self.sample_buf {
+            let channels = self.channel_ranges()?;
+            for frame in frames {
+                let frame_start = frame * self.source_channels;
+                for channels in &channels {
+                    buf.extend_scaled(
+                        frame_start + channels.start..frame_start + channels.end,
+                        &mut self.selected,
+                    );
+                }
+            }
+        }
+        if self.sanitize {
+            for sample in self.selected.iter_mut().filter(|s| !s.is_finite()) {
+                *sample = 0.0;
+                self.sanitized_samples += 1;
+            }
+        }
+        if self.gain != 1.0 {
+            self.selected.iter_mut().for_each(|s| *s *= self.gain);
+        }
+        if self.levels.is_some() {
+            let num_channels = self.num_channels()?;
+            if let Some(levels) = &mut self.levels {
+                levels.add(&self.selected, num_channels);
+            }
+        }
+
+        Ok(&self.selected)
+    }
+
+    /// All samples of the current packet, if `frames` and the selected channels cover the whole
+    /// packet and it is decoded as `f32` without gain, stride, sanitizing and levels, so no
+    /// samples need to be selected or converted.
+    fn whole_packet_f32(&self, frames: &std::ops::Range<usize>) -> Option<&[f32]> {
+        let Some(PacketBuffer::F32(buf)) = &self.sample_buf else {
+            return None;
+        };
+        if self.gain != 1.0 || self.stride != 1 || self.sanitize || self.levels.is_some() {
+            return None;
+        }
+        let whole_packet = frames.start == 0 && frames.end * self.source_channels == buf.len();
+        let all_channels = matches!(
+            self.channel_ranges().ok()?.as_slice(),
+            [channels] if *channels == (0..self.source_channels)
+        );
+        (whole_packet && all_channels).then(|| buf.samples())
+    }
+
+    /// Same as [`Self::next_packet`], but appends the samples in their native format as little
+    /// endian bytes to `out`. Returns `false` once the end is reached.
+    fn next_packet_bytes(&mut self, out: &mut Vec<u8>) -> Result<bool, AudioReadError> {
+        let Some(frames) = self.decode_next()? else {
+            return Ok(false);
+        };
+
+        let frames = self.strided(frames);
+        if let Some(buf) = &self.sample_buf {
+            let channels = self.channel_ranges()?;
+            for frame in frames {
+                let frame_start = frame * self.source_channels;
+                for channels in &channels {
+                    buf.extend_bytes(
+                        frame_start + channels.start..frame_start + channels.end,
+                        self.bits_per_sample,
+                        out,
+                    );
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Frames of the current packet that are kept with the stride.
+    fn strided(
+        &mut self,
+        frames: std::ops::Range<usize>,
+    ) -> std::iter::StepBy<std::ops::Range<usize>> {
+        let skip = self.stride_skip();
+        self.frames_in_range += frames.len();
+        (frames.start + skip..frames.end).step_by(self.stride)
+    }
+
+    /// Number of frames to skip until the stride keeps the next frame.
+    fn stride_skip(&self) -> usize {
+        (self.stride - self.frames_in_range % self.stride) % self.stride
+    }
+
+    /// Selected channels of the file, a single range or one range per selected channel.
+    fn channel_ranges(&self) -> Result<Vec<std::ops::Range<usize>>, AudioReadError> {
+        if let Some(channels) = &self.selected_channels {
+            return Ok(channels.iter().map(|c| *c..*c + 1).collect());
+        }
+        Ok(vec![channel_range(
+            self.start_channel,
+            self.requested_channels,
+            self.source_channels,
+        )?])
+    }
+
+    /// Decode the next packet of the track into the sample buffer and return the range of
+    /// frames in the buffer that are within the selected range.
+    fn decode_next(&mut self) -> Result<Option<std::ops::Range<usize>>, AudioReadError> {
+        if self.finished {
+            return Ok(None);
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() > deadline)
+        {
+            return Err(AudioReadError::TimedOut);
+        }
+
+        let packet = loop {
+            match self.format.next_packet() {
+                Ok(packet) if packet.track_id() == self.track_id => break packet,
+                Ok(_) => continue,
+                Err(Error::ResetRequired) => {
+                    self.restart_stream()?;
+                    continue;
+                }
+                Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    self.finished = true;
+                    return Ok(None);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        };
+
+        let decoded = self.decoder.decode(&packet)?;
+
+        // Get the timestamp of this packet to know our position
+        if self.current_sample.is_none() {
+            let ts = packet.ts();
+            if let Some(tb) = self.time_base {
+                // Convert timestamp to sample position
+                self.current_sample =
+                    Some(self.stream_offset + (ts * self.sample_rate as u64) / tb.denom as u64);
+            } else {
+                self.current_sample = Some(self.stream_offset);
+            }
+        }
+
+        if self.sample_buf.is_none() {
+            let spec = *decoded.spec();
+            // not all containers report the channels, so check them again before allocating
+            check_max_channels(spec.channels.count(), self.max_channels)?;
+            let duration = decoded.capacity() as u64;
+            if self.packed {
+                (self.decode_into, self.bits_per_sample) = DecodeInto::native(&decoded);
+            }
+            self.sample_buf = Some(PacketBuffer::new(self.decode_into, duration, spec));
+
+            // Get the number of channels from the spec
+            let channels = spec.channels.count();
+            if self.source_channels != 0 && self.source_channels != channels {
+                return Err(AudioReadError::ChannelCountChanged(
+                    self.source_channels,
+                    channels,
+                ));
+            }
+            self.source_channels = channels;
+
+            // Validate channel range
+            match (&self.channel_labels, &self.channel_list) {
+                (Some(labels), _) => {
+                    self.selected_channels = Some(label_channels(labels, spec.channels)?);
+                }
+                (None, Some(channels)) => {
+                    check_channels(channels, self.source_channels)?;
+                    self.selected_channels = Some(channels.clone());
+                }
+                (None, None) => {
+                    channel_range(
+                        self.start_channel,
+                        self.requested_channels,
+                        self.source_channels,
+                    )?;
+                }
+            }
+        }
+
+        let Some(buf) = &mut self.sample_buf else {
+            return Ok(Some(0..0));
+        };
+        let num_samples = buf.copy(decoded);
+
+        // Calculate frames using the ORIGINAL channel count from the file
+        let frames = num_samples / self.source_channels;
+        let pos = self.current_sample.unwrap_or(0);
+        self.packet_ts = packet.ts();
+        self.packet_pos = pos;
+
+        // Start collecting samples once we reach start_frame
+        let first = (self.start_frame as u64)
+            .saturating_sub(pos)
+            .min(frames as u64) as usize;
+        self.discarded_lead_frames += first;
+        let mut last = frames;
+        if let Some(end) = self.end_frame
+            && pos + frames as u64 >= end as u64
+        {
+            // Stop at the end frame
+            last = (end as u64).saturating_sub(pos).max(first as u64) as usize;
+            self.finished = true;
+        }
+
+        if first < last {
+            if self
+                .max_packets
+                .is_some_and(|max_packets| self.num_packets >= max_packets)
+            {
+                // a limit of 0 packets, larger limits finish with the last counted packet
+                last = first;
+                self.finished = true;
+            } else {
+                self.num_packets += 1;
+                if let Some(max_packets) = self.max_packets
+                    && self.num_packets >= max_packets
+                {
+                    self.finished = true;
+                }
+            }
+        }
+
+        // Update our position tracker
+        self.current_sample = Some(pos + frames as u64);
+
+        Ok(Some(first..last))
+    }
+}
+
+/// Interleaved samples of a decoded packet in the type selected with [`DecodeInto`].
+enum PacketBuffer {
+    I16(SampleBuffer<i16>),
+    I32(SampleBuffer<i32>),
+    F32(SampleBuffer<f32>),
+    F64(SampleBuffer<f64>),
+}
+
+impl PacketBuffer {
+    fn new(decode_into: DecodeInto, duration: u64, spec: SignalSpec) -> Self {
+        match decode_into {
+            DecodeInto::I16 => Self::I16(SampleBuffer::new(duration, spec)),
+            DecodeInto::I32 => Self::I32(SampleBuffer::new(duration, spec)),
+            DecodeInto::Auto | DecodeInto::F32 => Self::F32(SampleBuffer::new(duration, spec)),
+            DecodeInto::F64 => Self::F64(SampleBuffer::new(duration, spec)),
+        }
+    }
+
+    /// Copy the decoded packet into the buffer and return the number of samples.
+    fn copy(&mut self, decoded: AudioBufferRef) -> usize {
+        match self {
+            Self::I16(buf) => {
+                buf.copy_interleaved_ref(decoded);
+                buf.len()
+            }
+            Self::I32(buf) => {
+                buf.copy_interleaved_ref(decoded);
+                buf.len()
+            }
+            Self::F32(buf) => {
+                buf.copy_interleaved_ref(decoded);
+                buf.len()
+            }
+            Self::F64(buf) => {
+                buf.copy_interleaved_ref(decoded);
+                buf.len()
+            }
+        }
+    }
+
+    /// Append the samples in `range` to `out` as little endian bytes.
+    /// With 24 bits, the upper 3 bytes of 32-bit samples are written.
+    fn extend_bytes(&self, range: std::ops::Range<usize>, bits_per_sample: u32, out: &mut Vec<u8>) {
+        match self {
+            Self::I16(buf) => out.extend(buf.samples()[range].iter().flat_map(|s| s.to_le_bytes())),
+            Self::I32(buf) if bits_per_sample == 24 => out.extend(
+                buf.samples()[range]
+                    .iter()
+                    .flat_map(|s| s.to_le_bytes().into_iter().skip(1)),
+            ),
+            Self::I32(buf) => out.extend(buf.samples()[range].iter().flat_map(|s| s.to_le_bytes())),
+            Self::F32(buf) => out.extend(buf.samples()[range].iter().flat_map(|s| s.to_le_bytes())),
+            Self::F64(buf) => out.extend(buf.samples()[range].iter().flat_map(|s| s.to_le_bytes())),
+        }
+    }
+
+    /// Append the samples in `range` to `out`, integer samples are scaled to -1.0..1.0.
+    fn extend_scaled(&self, range: std::ops::Range<usize>, out: &mut Vec<f64>) {
+        match self {
+            Self::I16(buf) => {
+                let scale = 1.0 / -(i16::MIN as f64);
+                out.extend(buf.samples()[range].iter().map(|s| *s as f64 * scale));
+            }
+            Self::I32(buf) => {
+                let scale = 1.0 / -(i32::MIN as f64);
+                out.extend(buf.samples()[range].iter().map(|s| *s as f64 * scale));
+            }
+            Self::F32(buf) => out.extend(buf.samples()[range].iter().map(|s| *s as f64)),
+            Self::F64(buf) => out.extend_from_slice(&buf.samples()[range]),
+        }
+    }
+}
+
+/// Convert a decoded sample into the output type.
+fn convert<F: Float, S: num::ToPrimitive>(sample: S) -> Result<F, AudioReadError> {
+    F::from(sample).ok_or(AudioReadError::SampleConversion)
+}
+
+/// Append the decoded samples converted into the output type.
+fn extend_converted<F: Float, S: num::ToPrimitive + Copy>(
+    out: &mut Vec<F>,
+    samples: &[S],
+) -> Result<(), AudioReadError> {
+    out.reserve(samples.len());
+    for sample in samples {
+        out.push(convert(*sample)?);
+    }
+    Ok(())
+}
+
+/// Validate the selected channels against the channels in the file.
+fn channel_range(
+    start_channel: Option<usize>,
+    num_channels: Option<usize>,
+    source_channels: usize,
+) -> Result<std::ops::Range<usize>, AudioReadError> {
+    let ch_start = start_channel.unwrap_or(0);
+    if ch_start >= source_channels {
+        return Err(AudioReadError::InvalidStartChannel(
+            ch_start,
+            source_channels,
+        ));
+    }
+
+    let ch_count = num_channels.unwrap_or(source_channels - ch_start);
+    if ch_count == 0 {
+        return Err(AudioReadError::InvalidNumChannels(0));
+    }
+    if ch_start + ch_count > source_channels {
+        return Err(AudioReadError::InvalidNumChannels(ch_count));
+    }
+
+    Ok(ch_start..ch_start + ch_count)
+}
+
+/// Validate a list of selected channels against the channels in the file.
+fn check_channels(channels: &[usize], source_channels: usize) -> Result<(), AudioReadError> {
+    if channels.is_empty() {
+        return Err(AudioReadError::InvalidNumChannels(0));
+    }
+    match channels.iter().find(|channel| **channel >= source_channels) {
+        Some(channel) => Err(AudioReadError::InvalidChannel(*channel, source_channels)),
+        None => Ok(()),
+    }
+}
+
+/// Channels at the positions of `labels` in the interleaved channels of `layout`.
+fn label_channels(labels: &[ChannelLabel], layout: Channels) -> Result<Vec<usize>, AudioReadError> {
+    if labels.is_empty() {
+        return Err(AudioReadError::InvalidNumChannels(0));
+    }
+    let mask = layout.bits();
+    labels
+        .iter()
+        .map(|label| {
+            if mask & label.bit() == 0 {
+                return Err(AudioReadError::ChannelLabelNotFound(*label));
+            }
+            // the channels are interleaved in the order of their bits
+            Ok((mask & (label.bit() - 1)).count_ones() as usize)
+        })
+        .collect()
+}
+
+/// Fail with [`AudioReadError::TooManyChannels`] if `found` is above the limit.
+fn check_max_channels(found: usize, max: Option<usize>) -> Result<(), AudioReadError> {
+    match max {
+        Some(max) if found > max => Err(AudioReadError::TooManyChannels { found, max }),
+        _ => Ok(()),
+    }
+}
+
+/// Create the decoder for a track, failing with [`AudioReadError::UnsupportedCodec`] if no
+/// decoder is registered for its codec.
+fn make_decoder(params: &CodecParameters) -> Result<Box<dyn Decoder>, AudioReadError> {
+    let registry = symphonia::default::get_codecs();
+    if registry.get_codec(params.codec).is_none() {
+        return Err(AudioReadError::UnsupportedCodec(codec_name(params.codec)));
+    }
+    Ok(registry.make(params, &Default::default())?)
+}
+
+/// Name of a codec, which also works for codecs that are not compiled in.
+fn codec_name(codec: CodecType) -> String {
+    const NAMES: &[(CodecType, &str)] = &[
+        (codecs::CODEC_TYPE_PCM_S16LE, "PCM"),
+        (codecs::CODEC_TYPE_PCM_S24LE, "PCM"),
+        (codecs::CODEC_TYPE_PCM_S32LE, "PCM"),
+        (codecs::CODEC_TYPE_PCM_F32LE, "PCM"),
+        (codecs::CODEC_TYPE_PCM_S16BE, "PCM"),
+        (codecs::CODEC_TYPE_PCM_S24BE, "PCM"),
+        (codecs::CODEC_TYPE_ADPCM_MS, "ADPCM"),
+        (codecs::CODEC_TYPE_ADPCM_IMA_WAV, "ADPCM"),
+        (codecs::CODEC_TYPE_VORBIS, "Vorbis"),
+        (codecs::CODEC_TYPE_MP1, "MP1"),
+        (codecs::CODEC_TYPE_MP2, "MP2"),
+        (codecs::CODEC_TYPE_MP3, "MP3"),
+        (codecs::CODEC_TYPE_AAC, "AAC"),
+        (codecs::CODEC_TYPE_OPUS, "Opus"),
+        (codecs::CODEC_TYPE_FLAC, "FLAC"),
+        (codecs::CODEC_TYPE_ALAC, "ALAC"),
+        (codecs::CODEC_TYPE_WAVPACK, "WavPack"),
+    ];
+    NAMES
+        .iter()
+        .find(|(c, _)| *c == codec)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| codec.to_string())
+}
+
+/// The first track that contains audio.
+fn first_audio_track(format: &dyn FormatReader) -> Result<&Track, AudioReadError> {
+    format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != codecs::CODEC_TYPE_NULL)
+        .ok_or(AudioReadError::NoTrack)
+}
+
+/// Select the track to decode.
+///
+/// The streams of a chained file have their own track ids, so the audio track at
+/// `fallback_index` is taken if the stream has no track with the selected id.
+fn select_track(
+    format: &dyn FormatReader,
+    selection: TrackSelection,
+    fallback_index: Option<usize>,
+) -> Result<&Track, AudioReadError> {
+    let TrackSelection::Id(id) = selection else {
+        return select_audio_track(format, selection);
+    };
+    let tracks = format.tracks();
+    tracks
+        .iter()
+        .find(|t| t.id == id)
+        .or_else(|| {
+            tracks
+                .iter()
+                .filter(|t| t.codec_params.codec != codecs::CODEC_TYPE_NULL)
+                .nth(fallback_index?)
+        })
+        .ok_or(AudioReadError::TrackNotFound(id))
+}
+
+/// Position of the track among the audio tracks.
+fn audio_track_index(format: &dyn FormatReader, id: u32) -> usize {
+    format
+        .tracks()
+        .iter()
+        .filter(|t| t.codec_params.codec != codecs::CODEC_TYPE_NULL)
+        .position(|t| t.id == id)
+        .unwrap_or(0)
+}
+
+/// Select one of the audio tracks, `TrackSelection::Id` is not handled here.
+fn select_audio_track(
+    format: &dyn FormatReader,
+    selection: TrackSelection,
+) -> Result<&Track, AudioReadError> {
+    let first = first_audio_track(format)?;
+    let audio_tracks = || {
+        format
+            .tracks()
+            .iter()
+            .filter(|t| t.codec_params.codec != codecs::CODEC_TYPE_NULL)
+    };
+    // the first track wins if several have the same value
+    let max_by_key = |key: fn(&Track) -> u32| {
+        audio_tracks().fold(first, |best, t| if key(t) > key(best) { t } else { best })
+    };
+
+    let track = match selection {
+        TrackSelection::Lossless => audio_tracks()
+            .find(|t| is_lossless(t.codec_params.codec))
+            .unwrap_or(first),
+        TrackSelection::MostChannels => max_by_key(|t| {
+            t.codec_params
+                .channels
+                .map_or(0, |channels| channels.count() as u32)
+        }),
+        TrackSelection::HighestSampleRate => {
+            max_by_key(|t| t.codec_params.sample_rate.unwrap_or(0))
+        }
+        _ => first,
+    };
+    Ok(track)
+}
+
+/// Whether the codec decodes to exactly the samples that were encoded.
+fn is_lossless(codec: CodecType) -> bool {
+    is_pcm(codec)
+        || [
+            codecs::CODEC_TYPE_FLAC,
+            codecs::CODEC_TYPE_WAVPACK,
+            codecs::CODEC_TYPE_MONKEYS_AUDIO,
+            codecs::CODEC_TYPE_ALAC,
+            codecs::CODEC_TYPE_TTA,
+        ]
+        .contains(&codec)
+}
+
+/// Whether the codec stores uncompressed samples.
+fn is_pcm(codec: CodecType) -> bool {
+    // A-law and µ-law are companded and not part of the list
+    const PCM: &[CodecType] = &[
+        codecs::CODEC_TYPE_PCM_S32LE,
+        codecs::CODEC_TYPE_PCM_S32LE_PLANAR,
+        codecs::CODEC_TYPE_PCM_S32BE,
+        codecs::CODEC_TYPE_PCM_S32BE_PLANAR,
+        codecs::CODEC_TYPE_PCM_S24LE,
+        codecs::CODEC_TYPE_PCM_S24LE_PLANAR,
+        codecs::CODEC_TYPE_PCM_S24BE,
+        codecs::CODEC_TYPE_PCM_S24BE_PLANAR,
+        codecs::CODEC_TYPE_PCM_S16LE,
+        codecs::CODEC_TYPE_PCM_S16LE_PLANAR,
+        codecs::CODEC_TYPE_PCM_S16BE,
+        codecs::CODEC_TYPE_PCM_S16BE_PLANAR,
+        codecs::CODEC_TYPE_PCM_S8,
+        codecs::CODEC_TYPE_PCM_S8_PLANAR,
+        codecs::CODEC_TYPE_PCM_U32LE,
+        codecs::CODEC_TYPE_PCM_U32LE_PLANAR,
+        codecs::CODEC_TYPE_PCM_U32BE,
+        codecs::CODEC_TYPE_PCM_U32BE_PLANAR,
+        codecs::CODEC_TYPE_PCM_U24LE,
+        codecs::CODEC_TYPE_PCM_U24LE_PLANAR,
+        codecs::CODEC_TYPE_PCM_U24BE,
+        codecs::CODEC_TYPE_PCM_U24BE_PLANAR,
+        codecs::CODEC_TYPE_PCM_U16LE,
+        codecs::CODEC_TYPE_PCM_U16LE_PLANAR,
+        codecs::CODEC_TYPE_PCM_U16BE,
+        codecs::CODEC_TYPE_PCM_U16BE_PLANAR,
+        codecs::CODEC_TYPE_PCM_U8,
+        codecs::CODEC_TYPE_PCM_U8_PLANAR,
+        codecs::CODEC_TYPE_PCM_F32LE,
+        codecs::CODEC_TYPE_PCM_F32LE_PLANAR,
+        codecs::CODEC_TYPE_PCM_F32BE,
+        codecs::CODEC_TYPE_PCM_F32BE_PLANAR,
+        codecs::CODEC_TYPE_PCM_F64LE,
+        codecs::CODEC_TYPE_PCM_F64LE_PLANAR,
+        codecs::CODEC_TYPE_PCM_F64BE,
+        codecs::CODEC_TYPE_PCM_F64BE_PLANAR,
+    ];
+    PCM.contains(&codec)
+}
+
+/// Use the file extension as hint for the probe.
+fn path_extension(path: &Path) -> Option<&str> {
+    path.extension().and_then(|ext| ext.to_str())
+}
+
+/// File extensions of the containers that can be read with the enabled features.
+///
+/// A container is listed if its reader and at least one codec it can contain are enabled.
+pub fn supported_read_formats() -> &'static [&'static str] {
+    &[
+        #[cfg(any(
+            all(feature = "wav", any(feature = "pcm", feature = "adpcm")),
+            feature = "all"
+        ))]
+        "wav",
+        // the reader of this crate, which only supports PCM and float samples
+        #[cfg(any(feature = "pcm", feature = "all"))]
+        "w64",
+        #[cfg(any(feature = "flac", feature = "all"))]
+        "flac",
+        #[cfg(any(
+            all(feature = "ogg", any(feature = "vorbis", feature = "flac")),
+            feature = "all"
+        ))]
+        "ogg",
+        #[cfg(any(
+            all(
+                feature = "mkv",
+                any(feature = "vorbis", feature = "flac", feature = "pcm", feature = "aac")
+            ),
+            feature = "all"
+        ))]
+        "mkv",
+        #[cfg(any(all(feature = "mkv", feature = "vorbis"), feature = "all"))]
+        "webm",
+        #[cfg(any(
+            all(
+                feature = "caf",
+                any(feature = "pcm", feature = "adpcm", feature = "alac", feature = "aac")
+            ),
+            feature = "all"
+        ))]
+        "caf",
+        #[cfg(any(all(feature = "aiff", feature = "pcm"), feature = "all"))]
+        "aiff",
+        #[cfg(any(
+            all(feature = "isomp4", any(feature = "aac", feature = "alac")),
+            feature = "all"
+        ))]
+        "mp4",
+        #[cfg(any(
+            all(feature = "isomp4", any(feature = "aac", feature = "alac")),
+            feature = "all"
+        ))]
+        "m4a",
+        #[cfg(any(feature = "aac", feature = "all"))]
+        "aac",
+        #[cfg(any(feature = "mp1", feature = "all"))]
+        "mp1",
+        #[cfg(any(feature = "mp2", feature = "all"))]
+        "mp2",
+        #[cfg(any(feature = "mp3", feature = "all"))]
+        "mp3",
+    ]
+}
+
+/// The default probe of symphonia with the additional formats of this crate.
+fn probe() -> &'static Probe {
+    static PROBE: std::sync::OnceLock<Probe> = std::sync::OnceLock::new();
+    PROBE.get_or_init(|| {
+        let mut probe = Probe::default();
+        symphonia::default::register_enabled_formats(&mut probe);
+        probe.register_all::<W64Reader>();
+        probe
+    })
+}
+
+/// Tags in front of the container (e.g. ID3v2), followed by the tags of the container.
+fn read_tags(probed: &mut MetadataLog, format: &mut dyn FormatReader) -> Tags {
+    let mut tags = Tags::default();
+    let mut push = |revision: Option<&MetadataRevision>| {
+        for tag in revision.map(MetadataRevision::tags).unwrap_or_default() {
+            tags.push(tag.key.clone(), tag.value.to_string());
+        }
+    };
+    push(probed.metadata().current());
+    push(format.metadata().current());
+    tags
+}
+
+/// Bytes searched behind the first container for a container of the hinted type
+const HINT_SEARCH_LIMIT: u64 = 1 << 20;
+
+/// Does the same as `Probe::format`, but additionally identifies the container that was found.
+///
+/// The probe takes the first marker it finds. If it belongs to another container than the hint
+/// (e.g. bytes in front of the actual container that look like a marker), the stream is searched
+/// further for a container of the hinted type. If there is none, the first container is used.
+fn probe_format(
+    mut mss: MediaSourceStream,
+    hint: Option<&str>,
+    fmt_opts: &FormatOptions,
+    meta_opts: &MetadataOptions,
+) -> Result<(Box<dyn FormatReader>, Container, MetadataLog), AudioReadError> {
+    let probe = probe();
+    let hinted = hint.map_or(Container::Unknown, Container::from_extension);
+    // metadata in front of the container (e.g. ID3v2)
+    let mut metadata = MetadataLog::default();
+    // first container that does not match the hint, and the metadata found behind it
+    let mut fallback = None;
+    let mut skipped_metadata = Vec::new();
+
+    let (instantiate, container) = loop {
+        let next = match probe.next(&mut mss) {
+            Ok(next) => next,
+            Err(err) => {
+                let Some((pos, instantiate, container)) = fallback else {
+                    return Err(err.into());
+                };
+                mss.seek(SeekFrom::Start(pos))?;
+                break (instantiate, container);
+            }
+        };
+        match next {
+            Instantiate::Format(instantiate) => {
+                // the probe aligned the stream to the start of the marker
+                let mut marker = [0u8; 16];
+                mss.read_buf_exact(&mut marker)?;
+                mss.seek_buffered_rev(marker.len());
+                let container = Container::from_marker(&marker);
+
+                let pos = mss.pos();
+                if hinted == Container::Unknown || container == hinted || !mss.is_seekable() {
+                    for revision in skipped_metadata {
+                        metadata.push(revision);
+                    }
+                    break (instantiate, container);
+                }
+                match fallback {
+                    None => fallback = Some((pos, instantiate, container)),
+                    Some((start, instantiate, container)) if pos - start > HINT_SEARCH_LIMIT => {
+                        mss.seek(SeekFrom::Start(start))?;
+                        break (instantiate, container);
+                    }
+                    Some(_) => {}
+                }
+                // continue the search behind the start of the marker
+                mss.ignore_bytes(1)?;
+            }
+            Instantiate::Metadata(instantiate) => {
+                let mut reader = instantiate(meta_opts);
+                let revision = reader.read_all(&mut mss)?;
+                match fallback {
+                    None => metadata.push(revision),
+                    Some(_) => skipped_metadata.push(revision),
+                }
+            }
+        }
+    };
+
+    let format = match instantiate(mss, fmt_opts) {
+        Ok(format) => format,
+        Err(Error::Unsupported(what)) => {
+            return Err(AudioReadError::UnsupportedContainer(format!(
+                "{container} ({what})"
+            )));
+        }
+        Err(err) => return Err(err.into()),
+    };
+    Ok((format, container, metadata))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use audio_blocks::AudioBlock;
+
+    use super::*;
+
+    #[test]
+    fn test_samples_selection() {
+        let data1: AudioData<f32> =
+            audio_read("test_data/test_1ch.wav", AudioReadConfig::default()).unwrap();
+        let block1 = data1.audio_block();
+        assert_eq!(data1.sample_rate, 48000);
+        assert_eq!(data1.sample_rate_typed(), Ok(SampleRate::SR_48000));
+        assert_eq!(block1.num_frames(), 48000);
+        assert_eq!(block1.num_channels(), 1);
+
+        let data2: AudioData<f32> = audio_read(
+            "test_data/test_1ch.wav",
+            AudioReadConfig {
+                start: Position::Frame(1100),
+                stop: Position::Frame(1200),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let block2 = data2.audio_block();
+        assert_eq!(data2.sample_rate, 48000);
+        assert_eq!(block2.num_frames(), 100);
+        assert_eq!(block2.num_channels(), 1);
+        assert_eq!(block1.raw_data()[1100..1200], block2.raw_data()[..]);
+    }
+
+    #[test]
+    fn test_time_selection() {
+        let data1: AudioData<f32> =
+            audio_read("test_data/test_1ch.wav", AudioReadConfig::default()).unwrap();
+        let block1 = data1.audio_block();
+        assert_eq!(data1.sample_rate, 48000);
+        assert_eq!(block1.num_frames(), 48000);
+        assert_eq!(block1.num_channels(), 1);
+
+        let data2: AudioData<f32> = audio_read(
+            "test_data/test_1ch.wav",
+            AudioReadConfig {
+                start: Position::Time(Duration::from_secs_f32(0.5)),
+                stop: Position::Time(Duration::from_secs_f32(0.6)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let block2 = data2.audio_block();
+        assert_eq!(data2.sample_rate, 48000);
+        assert_eq!(block2.num_frames(), 4800);
+        assert_eq!(block2.num_channels(), 1);
+        assert_eq!(block1.raw_data()[24000..28800], block2.raw_data()[..]);
+    }
+
+    #[test]
+    fn test_channel_selection() {
+        let data1: AudioData<f32> =
+            audio_read("test_data/test_4ch.wav", AudioReadConfig::default()).unwrap();
+        let block1 = data1.audio_block();
+        assert_eq!(data1.sample_rate, 48000);
+        assert_eq!(block1.num_frames(), 48000);
+        assert_eq!(block1.num_channels(), 4);
+
+        let data2: AudioData<f32> = audio_read(
+            "test_data/test_4ch.wav",
+            AudioReadConfig {
+                start_channel: Some(1),
+                num_channels: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let block2 = data2.audio_block();
+        assert_eq!(data2.sample_rate, 48000);
+        assert_eq!(block2.num_frames(), 48000);
+        assert_eq!(block2.num_channels(), 2);
+
+        // Verify we extracted channels 1 and 2 (skipping channel 0 and 3)
+        for frame in 0..10 {
+            assert_eq!(block2.sample(0, frame), block1.sample(1, frame));
+            assert_eq!(block2.sample(1, frame), block1.sample(2, frame));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "write")]
+    fn test_right_channel_of_stereo() {
+        // frames and sample indices have to use the channels of the file, not of the output
+        let samples: Vec<f32> = (0..2 * 5000)
+            .map(|i| {
+                if i % 2 == 0 {
+                    0.25
+                } else {
+                    (i / 2) as f32 / 10000.0
+                }
+            })
+            .collect();
+        let block = AudioBlockInterleavedView::from_slice(&samples, 2, 5000);
+        crate::audio_write("tmp22.wav", block, 48000, Default::default()).unwrap();
+
+        let full: AudioData<f32> = audio_read("tmp22.wav", AudioReadConfig::default()).unwrap();
+        let right: AudioData<f32> = audio_read(
+            "tmp22.wav",
+            AudioReadConfig {
+                start_channel: Some(1),
+                num_channels: Some(1),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        std::fs::remove_file("tmp22.wav").unwrap();
+
+        assert_eq!(right.num_channels, 1);
+        assert_eq!(right.num_frames, 5000);
+        let expected: Vec<f32> = full.channel(1).copied().collect();
+        assert_eq!(right.interleaved_samples, expected);
+    }
+
+    #[test]
+    fn test_audio_reader() {
+        let reader = AudioReader::new(AudioReadConfig {
+            stop: Position::Frame(1000),
+            num_channels: Some(1),
+            ..Default::default()
+        });
+
+        let worker = reader.clone();
+        let handle =
+            std::thread::spawn(move || worker.read::<_, f32>("test_data/test_4ch.wav").unwrap());
+        let data1: AudioData<f32> = reader.read("test_data/test_1ch.wav").unwrap();
+        let data2 = handle.join().unwrap();
+
+        for data in [&data1, &data2] {
+            assert_eq!(data.num_frames, 1000);
+            assert_eq!(data.num_channels, 1);
+        }
+        let expected: AudioData<f32> = audio_read(
+            "test_data/test_4ch.wav",
+            AudioReadConfig::clone(reader.config()),
+        )
+        .unwrap();
+        assert_eq!(data2.interleaved_samples, expected.interleaved_samples);
+    }
+
+    #[test]
+    #[cfg(feature = "write")]
+    fn test_max_channels() {
+        crate::test_util::write_test_tone("tmp17.wav", 48000, 2, 1000.0, 4800);
+        let read = |max_channels| {
+            audio_read::<_, f32>(
+                "tmp17.wav",
+                AudioReadConfig {
+                    max_channels,
+                    // the limit applies to the file, not to the selected channels
+                    num_channels: Some(1),
+                    ..Default::default()
+                },
+            )
+            .map_err(AudioReadError::without_path)
+        };
+        let too_many = read(Some(1));
+        let stereo = read(Some(2));
+        std::fs::remove_file("tmp17.wav").unwrap();
+
+        assert!(matches!(
+            too_many,
+            Err(AudioReadError::TooManyChannels { found: 2, max: 1 })
+        ));
+        assert_eq!(stereo.unwrap().num_frames, 4800);
+    }
+
+    #[test]
+    fn test_read_segment() {
+        let full: AudioData<f32> =
+            audio_read("test_data/test_4ch.wav", AudioReadConfig::default()).unwrap();
+        let config = || AudioReadConfig {
+            // ignored, the segments always cover the whole file
+            stop: Position::Frame(100),
+            ..Default::default()
+        };
+
+        // 48000 frames don't divide into 7 segments, the last one is longer
+        let segments: Vec<AudioData<f32>> = (0..7)
+            .map(|i| audio_read_segment("test_data/test_4ch.wav", i, 7, config()).unwrap())
+            .collect();
+        assert_eq!(segments[0].num_frames, 6857);
+        assert_eq!(segments[6].num_frames, 6858);
+        let concatenated: Vec<f32> = segments
+            .iter()
+            .flat_map(|segment| segment.interleaved_samples.iter().copied())
+            .collect();
+        assert_eq!(concatenated, full.interleaved_samples);
+
+        for (index, num_segments) in [(3, 3), (0, 0)] {
+            assert!(matches!(
+                audio_read_segment::<_, f32>("test_data/test_4ch.wav", index, num_segments, config())
+                    .map_err(AudioReadError::without_path),
+                Err(AudioReadError::InvalidSegment(i, n)) if i == index && n == num_segments
+            ));
+        }
+    }
+
+    #[test]
+    fn test_open_format() {
+        let (mut format, info) = open_format("test_data/test_1ch.wav").unwrap();
+        assert_eq!(info.container, Container::Wav);
+        assert_eq!(info.codec_params.sample_rate, Some(48000));
+
+        let packet = format.next_packet().unwrap();
+        assert_eq!(packet.track_id(), info.id);
+        assert_eq!(packet.ts(), 0);
+        assert!(!packet.data.is_empty());
+
+        assert!(matches!(
+            open_format("test_data/missing.wav")
+                .map(|_| ())
+                .map_err(AudioReadError::without_path),
+            Err(AudioReadError::FileError(_))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "write")]
+    fn test_sanitize_input() {
+        use crate::writer::{AudioWriteConfig, WriteSampleFormat};
+
+        let mut samples = vec![0.5f32; 2 * 1000];
+        samples[10] = f32::NAN;
+        samples[501] = f32::INFINITY;
+        samples[1999] = f32::NEG_INFINITY;
+        let block = AudioBlockInterleavedView::from_slice(&samples, 2, 1000);
+        let config = AudioWriteConfig {
+            sample_format: WriteSampleFormat::Float32,
+            ..Default::default()
+        };
+        crate::audio_write("tmp19.wav", block, 48000, config).unwrap();
+
+        let read = |sanitize_input| {
+            audio_read_with_stats::<_, f32>(
+                "tmp19.wav",
+                AudioReadConfig {
+                    sanitize_input,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        let (raw, raw_stats) = read(false);
+        let (sanitized, stats) = read(true);
+        std::fs::remove_file("tmp19.wav").unwrap();
+
+        assert!(raw.interleaved_samples[10].is_nan());
+        assert_eq!(raw_stats.sanitized_samples, 0);
+        assert_eq!(stats.sanitized_samples, 3);
+        assert!(sanitized.interleaved_samples.iter().all(|s| s.is_finite()));
+        assert_eq!(sanitized.interleaved_samples[501], 0.0);
+        assert_eq!(sanitized.interleaved_samples[500], 0.5);
+    }
+
+    #[test]
+    fn test_as_f32_bytes() {
+        let data =
+            audio_read::<_, f32>("test_data/test_1ch.wav", AudioReadConfig::default()).unwrap();
+        let bytes = data.as_f32_bytes();
+        assert_eq!(bytes.len(), data.interleaved_samples.len() * 4);
+        let first = f32::from_ne_bytes(bytes[..4].try_into().unwrap());
+        assert_eq!(first, data.interleaved_samples[0]);
+    }
+
+    #[test]
+    #[cfg(feature = "timestretch")]
+    fn test_time_stretch() {
+        let read = |time_stretch| {
+            audio_read::<_, f32>(
+                "test_data/test_1ch.wav",
+                AudioReadConfig {
+                    time_stretch,
+                    ..Default::default()
+                },
+            )
+            .map_err(AudioReadError::without_path)
+        };
+        let original = read(None).unwrap();
+        let stretched = read(Some(0.5)).unwrap();
+        assert_eq!(stretched.num_frames, 2 * original.num_frames);
+        assert_eq!(stretched.sample_rate, original.sample_rate);
+        assert_eq!(
+            stretched.interleaved_samples.len(),
+            stretched.num_frames * stretched.num_channels
+        );
+        for speed in [0.0, 1e-12, 0.09, 10.5, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                read(Some(speed)),
+                Err(AudioReadError::InvalidTimeStretch(_))
+            ));
+        }
+        assert_eq!(
+            read(Some(10.0)).unwrap().num_frames,
+            original.num_frames / 10
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        fn round_trip<T>(value: &T) -> T
+        where
+            T: serde::Serialize + serde::de::DeserializeOwned,
+        {
+            serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+        }
+
+        let (data, stats) =
+            audio_read_with_stats::<_, f32>("test_data/test_smpl.wav", AudioReadConfig::default())
+                .unwrap();
+        assert_eq!(round_trip(&stats), stats);
+        assert_eq!(round_trip(&data.container), data.container);
+        assert_eq!(round_trip(&data.source_format), data.source_format);
+        assert_eq!(round_trip(&data.instrument), data.instrument);
+        assert_eq!(round_trip(&data.tags), data.tags);
+        assert_eq!(round_trip(&data.cues), data.cues);
+        let info = audio_probe_info("test_data/test_smpl.wav").unwrap();
+        assert_eq!(round_trip(&info), info);
+    }
+
+    #[test]
+    fn test_channel_list() {
+        let read = |channels: Vec<usize>| {
+            audio_read::<_, f32>(
+                "test_data/test_4ch.wav",
+                AudioReadConfig {
+                    channels: Some(channels),
+                    ..Default::default()
+                },
+            )
+            .map_err(AudioReadError::without_path)
+        };
+        let all: AudioData<f32> =
+            audio_read("test_data/test_4ch.wav", AudioReadConfig::default()).unwrap();
+
+        // the channels are in the requested order, and can be repeated
+        let data = read(vec![3, 0, 2, 0]).unwrap();
+        assert_eq!(data.num_channels, 4);
+        assert_eq!(data.num_frames, all.num_frames);
+        for (frame, expected) in data.frames().zip(all.frames()) {
+            assert_eq!(frame, [expected[3], expected[0], expected[2], expected[0]]);
+        }
+        assert_eq!(data.source_format.unwrap().channel_mask, None);
+
+        assert!(matches!(
+            read(vec![1, 4]),
+            Err(AudioReadError::InvalidChannel(4, 4))
+        ));
+        assert!(matches!(
+            read(vec![]),
+            Err(AudioReadError::InvalidNumChannels(0))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "write")]
+    fn test_channels_by_label() {
+        // 5.1 file where every channel has a constant value
+        let samples: Vec<f32> = (0..6 * 1000).map(|i| (i % 6 + 1) as f32 / 10.0).collect();
+        let block = AudioBlockInterleavedView::from_slice(&samples, 6, 1000);
+        crate::audio_write("tmp20.wav", block, 48000, Default::default()).unwrap();
+
+        let read = |labels| {
+            audio_read::<_, f32>(
+                "tmp20.wav",
+                AudioReadConfig {
+                    channels_by_label: Some(labels),
+                    ..Default::default()
+                },
+            )
+            .map_err(AudioReadError::without_path)
+        };
+        let data = read(vec![ChannelLabel::FrontCenter, ChannelLabel::Lfe]).unwrap();
+        let swapped = read(vec![ChannelLabel::FrontRight, ChannelLabel::FrontLeft]).unwrap();
+        let missing = read(vec![ChannelLabel::FrontLeft, ChannelLabel::SideLeft]);
+        std::fs::remove_file("tmp20.wav").unwrap();
+
+        assert_eq!(data.num_channels, 2);
+        assert_eq!(data.num_frames, 1000);
+        approx::assert_abs_diff_eq!(data.interleaved_samples[0], 0.3, epsilon = 1e-4);
+        approx::assert_abs_diff_eq!(data.interleaved_samples[1], 0.4, epsilon = 1e-4);
+        approx::assert_abs_diff_eq!(swapped.interleaved_samples[0], 0.2, epsilon = 1e-4);
+        approx::assert_abs_diff_eq!(swapped.interleaved_samples[1], 0.1, epsilon = 1e-4);
+        assert!(data.source_format.unwrap().channel_mask.is_none());
+        assert!(matches!(
+            missing,
+            Err(AudioReadError::ChannelLabelNotFound(ChannelLabel::SideLeft))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "write")]
+    fn test_read_region_by_cues() {
+        use crate::writer::{AudioWriteConfig, FourCC};
+
+        // cue points "A" at frame 1000 and "B" at frame 3500, and an unlabeled one
+        let mut cue = 3u32.to_le_bytes().to_vec();
+        for (id, frame) in [(1u32, 1000u32), (2, 3500), (3, 200)] {
+            cue.extend(id.to_le_bytes());
+            cue.extend(0u32.to_le_bytes());
+            cue.extend(b"data");
+            cue.extend([0; 8]);
+            cue.extend(frame.to_le_bytes());
+        }
+        let mut adtl = b"adtl".to_vec();
+        for (id, label) in [(1u32, b"A\0"), (2, b"B\0")] {
+            adtl.extend(b"labl");
+            adtl.extend(6u32.to_le_bytes());
+            adtl.extend(id.to_le_bytes());
+            adtl.extend(label);
+        }
+        let config = AudioWriteConfig::builder()
+            .extra_chunk(FourCC::new("cue ").unwrap(), cue)
+            .extra_chunk(FourCC::new("LIST").unwrap(), adtl)
+            .build()
+            .unwrap();
+        let samples: Vec<f32> = (0..5000).map(|i| i as f32 / 8000.0).collect();
+        let block = AudioBlockInterleavedView::from_slice(&samples, 1, 5000);
+        crate::audio_write("tmp21.wav", block, 48000, config).unwrap();
+
+        let read = |start, stop| {
+            audio_read_region_by_cues::<_, f32>("tmp21.wav", start, stop, Default::default())
+                .map_err(AudioReadError::without_path)
+        };
+        let region = read("A", "B").unwrap();
+        let cues = region.cues.clone();
+        let missing = read("A", "C");
+        let reversed = read("B", "A");
+        #[cfg(feature = "resample")]
+        let resampled = audio_read::<_, f32>(
+            "tmp21.wav",
+            AudioReadConfig {
+                target_sample_rate: Some(24000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        std::fs::remove_file("tmp21.wav").unwrap();
+        #[cfg(feature = "resample")]
+        assert_eq!(
+            resampled
+                .cues
+                .iter()
+                .map(|cue| cue.frame)
+                .collect::<Vec<_>>(),
+            [500, 1750, 100]
+        );
+
+        assert_eq!(region.num_frames, 2500);
+        approx::assert_abs_diff_eq!(
+            region.interleaved_samples[0],
+            1000.0 / 8000.0,
+            epsilon = 1e-4
+        );
+        assert_eq!(cues.len(), 3);
+        assert_eq!(cues[0].label, "A");
+        assert_eq!(cues[1].frame, 3500);
+        assert_eq!(cues[2].label, "");
+        assert!(matches!(missing, Err(AudioReadError::CueNotFound(label)) if label == "C"));
+        assert!(matches!(
+            reversed,
+            Err(AudioReadError::CuesOutOfOrder(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_block_reader() {
+        let config = || AudioReadConfig {
+            start: Position::Frame(1000),
+            stop: Position::Frame(9000),
+            start_channel: Some(1),
+            num_channels: Some(2),
+            ..Default::default()
+        };
+        let expected: AudioData<f32> = audio_read("test_data/test_4ch.wav", config()).unwrap();
+
+        for max_frames in [1000, 777, 100_000] {
+            let mut reader = BlockReader::<f32>::open("test_data/test_4ch.wav", config()).unwrap();
+            let mut samples = Vec::new();
+            let mut positions = Vec::new();
+            while let Some(block) = reader.next_block(max_frames).unwrap() {
+                assert_eq!(block.num_channels, 2);
+                assert!(block.num_frames <= max_frames);
+                assert_eq!(block.interleaved_samples.len(), block.num_frames * 2);
+                positions.push(reader.position() - block.num_frames);
+                samples.extend(block.interleaved_samples);
+            }
+            assert!(reader.next_block(max_frames).unwrap().is_none());
+            assert_eq!(reader.position(), 8000);
+            assert_eq!(samples, expected.interleaved_samples);
+            assert!(
+                positions
+                    .iter()
+                    .enumerate()
+                    .all(|(i, p)| *p == i * max_frames)
+            );
+        }
+
+        // padding to the stop position continues after the end of the file
+        let config = AudioReadConfig {
+            start: Position::Frame(expected.source_num_frames.unwrap() - 100),
+            stop: Position::Frame(expected.source_num_frames.unwrap() + 500),
+            pad_to_stop: true,
+            ..Default::default()
+        };
+        let mut reader = BlockReader::<f32>::open("test_data/test_4ch.wav", config).unwrap();
+        let mut num_frames = 0;
+        while let Some(block) = reader.next_block(256).unwrap() {
+            num_frames += block.num_frames;
+        }
+        assert_eq!(num_frames, 600);
+
+        let mut reader =
+            BlockReader::<f32>::open("test_data/test_4ch.wav", Default::default()).unwrap();
+        assert!(matches!(
+            reader.next_block(0),
+            Err(AudioReadError::InvalidBlockSize(0))
+        ));
+    }
+
+    #[test]
+    fn test_collect_stats() {
+        let read = |mono| {
+            audio_read::<_, f32>(
+                "test_data/test_4ch.wav",
+                AudioReadConfig {
+                    start: Position::Frame(1000),
+                    stop: Position::Frame(9000),
+                    collect_stats: true,
+                    mono,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        let expected_stats = |data: &AudioData<f32>| {
+            let channels = (0..data.num_channels).map(|c| data.channel(c).map(|s| *s as f64));
+            let peak = channels
+                .clone()
+                .map(|channel| channel.fold(0.0f64, |peak, s| peak.max(s.abs())))
+                .collect::<Vec<_>>();
+            let rms = channels
+                .map(|channel| {
+                    (channel.map(|s| s * s).sum::<f64>() / data.num_frames as f64).sqrt()
+                })
+                .collect::<Vec<_>>();
+            (peak, rms)
+        };
+
+        // measured while decoding, or after the mono selection changed the output
+        for mono in [None, Some(MonoSelect::Downmix)] {
+            let data = read(mono);
+            let stats = data.stats.clone().unwrap();
+            let (peak, rms) = expected_stats(&data);
+            assert_eq!(stats.peak.len(), data.num_channels);
+            assert!(stats.peak.iter().all(|peak| *peak > 0.0));
+            approx::assert_abs_diff_eq!(stats.peak.as_slice(), peak.as_slice(), epsilon = 1e-9);
+            approx::assert_abs_diff_eq!(stats.rms.as_slice(), rms.as_slice(), epsilon = 1e-9);
+        }
+
+        let data: AudioData<f32> =
+            audio_read("test_data/test_4ch.wav", AudioReadConfig::default()).unwrap();
+        assert!(data.stats.is_none());
+    }
+
+    #[test]
+    fn test_read_planar() {
+        let config = || AudioReadConfig {
+            start: Position::Frame(1000),
+            stop: Position::Frame(9000),
+            start_channel: Some(1),
+            num_channels: Some(2),
+            ..Default::default()
+        };
+        let expected: AudioData<f32> = audio_read("test_data/test_4ch.wav", config()).unwrap();
+        let planar = audio_read_planar::<_, f32>("test_data/test_4ch.wav", config()).unwrap();
+        assert_eq!(planar.num_channels, 2);
+        assert_eq!(planar.num_frames, 8000);
+        assert_eq!(planar.sample_rate, expected.sample_rate);
+        let source_num_frames = expected.source_num_frames.unwrap();
+        let PlanarLayout::Separate(channels) = expected.into_planar(PlanarMode::Separate) else {
+            panic!("expected separate channels");
+        };
+        assert_eq!(planar.channels, channels);
+
+        // padding to the stop position continues after the end of the file
+        let planar = audio_read_planar::<_, f64>(
+            "test_data/test_4ch.wav",
+            AudioReadConfig {
+                start: Position::Frame(source_num_frames - 100),
+                stop: Position::Frame(source_num_frames + 500),
+                pad_to_stop: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(planar.num_frames, 600);
+        assert!(planar.channels.iter().all(|channel| channel.len() == 600));
+        // a stop far past the end of the file reads until the end
+        for stop in [1 << 36, usize::MAX / 4] {
+            let planar = audio_read_planar::<_, f32>(
+                "test_data/test_4ch.wav",
+                AudioReadConfig {
+                    stop: Position::Frame(stop),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            assert_eq!(planar.num_frames, source_num_frames);
+            assert!(
+                planar
+                    .channels
+                    .iter()
+                    .all(|channel| channel.len() == source_num_frames)
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_from_source() {
+        let bytes = std::fs::read("test_data/test_1ch.wav").unwrap();
+        let expected: AudioData<f32> =
+            audio_read("test_data/test_1ch.wav", AudioReadConfig::default()).unwrap();
+
+        for hint in [None, Some("wav")] {
+            let source = Box::new(std::io::Cursor::new(bytes.clone()));
+            let data: AudioData<f32> =
+                audio_read_from_source(source, hint, AudioReadConfig::default()).unwrap();
+            assert_eq!(data.interleaved_samples, expected.interleaved_samples);
+            assert_eq!(data.container, Container::Wav);
+        }
+
+        // bytes in front of the file that look like a FLAC marker are only skipped with a hint
+        let mut prefixed = b"fLaC".to_vec();
+        prefixed.extend_from_slice(&[0; 12]);
+        prefixed.extend_from_slice(&bytes);
+        let read_prefixed = |hint| {
+            let source = Box::new(std::io::Cursor::new(prefixed.clone()));
+            audio_read_from_source::<f32>(source, hint, AudioReadConfig::default())
+        };
+        #[cfg(feature = "flac")]
+        assert!(read_prefixed(None).is_err());
+        let data = read_prefixed(Some("wav")).unwrap();
+        assert_eq!(data.interleaved_samples, expected.interleaved_samples);
+        assert_eq!(data.container, Container::Wav);
+
+        // a stream that can't seek is decoded from the start
+        let config = || AudioReadConfig {
+            start: Position::Frame(1000),
+            ..Default::default()
+        };
+        let source = symphonia::core::io::ReadOnlySource::new(std::io::Cursor::new(bytes));
+        let data: AudioData<f32> =
+            audio_read_from_source(Box::new(source), Some("wav"), config()).unwrap();
+        assert_eq!(
+            data.interleaved_samples,
+            expected.interleaved_samples[1000..]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_read_async() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let expected: AudioData<f32> =
+            audio_read("test_data/test_1ch.wav", AudioReadConfig::default()).unwrap();
+        let data: AudioData<f32> = runtime
+            .block_on(audio_read_async(
+                "test_data/test_1ch.wav",
+                AudioReadConfig::default(),
+            ))
+            .unwrap();
+        assert_eq!(data.interleaved_samples, expected.interleaved_samples);
+
+        let missing = runtime.block_on(audio_read_async::<_, f32>(
+            "test_data/missing.wav",
+            AudioReadConfig::default(),
+        ));
+        let Err(err) = missing else {
+            panic!("reading a missing file succeeded");
+        };
+        assert_eq!(err.path(), Some(Path::new("test_data/missing.wav")));
+    }
+
+    #[test]
+    #[cfg(feature = "write")]
+    fn test_read_from_bytes() {
+        // 3 seconds, so a start after the first second is reached by seeking
+        let samples: Vec<f32> = (0..3 * 48000)
+            .map(|n| (n as f32 * 0.001).sin() * 0.5)
+            .collect();
+        let block = AudioBlockInterleavedView::from_slice(&samples, 1, samples.len());
+        let bytes = crate::audio_write_to_writer(
+            std::io::Cursor::new(Vec::new()),
+            block,
+            48000,
+            Default::default(),
+        )
+        .unwrap()
+        .into_inner();
+
+        let config = |disable_seek| AudioReadConfig {
+            start: Position::Frame(100000),
+            stop: Position::Frame(101000),
+            disable_seek,
+            ..Default::default()
+        };
+        let read = |disable_seek| {
+            read_tracks::<f32>(config(disable_seek), |config| {
+                let source = std::io::Cursor::new(bytes.clone());
+                Decoding::from_source(Box::new(source), None, config)
+            })
+            .unwrap()
+        };
+        let (expected, decoded) = read(true);
+        let (_, seeked) = read(false);
+        assert_eq!(decoded.discarded_lead_frames, 100000);
+        assert!(seeked.discarded_lead_frames < 20000);
+        assert!(seeked.num_packets < decoded.num_packets);
+
+        let data: AudioData<f32> =
+            audio_read_from_bytes(bytes.clone(), Some("wav"), config(false)).unwrap();
+        assert_eq!(data.num_frames, 1000);
+        assert_eq!(data.num_channels, 1);
+        assert_eq!(data.interleaved_samples, expected.interleaved_samples);
+
+        assert!(
+            audio_read_from_bytes::<f32>(Vec::new(), None, AudioReadConfig::default()).is_err()
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "ogg", feature = "flac"))]
+    fn test_read_from_source_all_tracks() {
+        let config = || AudioReadConfig {
+            track: TrackSelection::All { mix: false },
+            ..Default::default()
+        };
+        let expected: AudioData<f32> =
+            audio_read("test_data/test_multitrack.ogg", config()).unwrap();
+        let bytes = std::fs::read("test_data/test_multitrack.ogg").unwrap();
+        let source = Box::new(std::io::Cursor::new(bytes));
+        let data: AudioData<f32> = audio_read_from_source(source, None, config()).unwrap();
+        assert_eq!(data.num_channels, expected.num_channels);
+        assert_eq!(data.interleaved_samples, expected.interleaved_samples);
+    }
+
+    #[test]
+    #[cfg(feature = "resample")]
+    fn test_target_sample_rate() {
+        let read = |target_sample_rate| {
+            let config = AudioReadConfig {
+                start: Position::Frame(4800),
+                stop: Position::Frame(14400),
+                target_sample_rate,
+                ..Default::default()
+            };
+            audio_read::<_, f32>("test_data/test_4ch.wav", config)
+                .map_err(AudioReadError::without_path)
+        };
+        let original = read(None).unwrap();
+        let resampled = read(Some(24000)).unwrap();
+        let same = read(Some(48000)).unwrap();
+
+        // the positions are frames of the file
+        assert_eq!(original.num_frames, 9600);
+        assert_eq!(resampled.sample_rate, 24000);
+        assert_eq!(resampled.num_frames, 4800);
+        assert_eq!(resampled.num_channels, 4);
+        assert_eq!(
+            resampled.interleaved_samples.len(),
+            resampled.num_frames * resampled.num_channels
+        );
+        assert_eq!(same.interleaved_samples, original.interleaved_samples);
+        assert!(matches!(
+            read(Some(0)),
+            Err(AudioReadError::ResampleError(_))
+        ));
+
+        // the loops are moved to the frames of the new rate
+        let data: AudioData<f32> = audio_read(
+            "test_data/test_smpl.wav",
+            AudioReadConfig {
+                target_sample_rate: Some(24000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            data.instrument.unwrap().loops,
+            [LoopRegion {
+                start_frame: 600,
+                end_frame: 1800
+            }]
+        );
+    }
+
+    #[test]
+    fn test_container() {
+        let data: AudioData<f32> =
+            audio_read("test_data/test_1ch.wav", AudioReadConfig::default()).unwrap();
+        assert_eq!(data.container, Container::Wav);
+    }
+
+    #[test]
+    #[cfg(feature = "flac")]
+    fn test_container_flac() {
+        let wav: AudioData<f32> =
+            audio_read("test_data/test_1ch.wav", AudioReadConfig::default()).unwrap();
+        let flac: AudioData<f32> =
+            audio_read("test_data/test_1ch.flac", AudioReadConfig::default()).unwrap();
+        assert_eq!(flac.container, Container::Flac);
+        assert_eq!(flac.container.to_string(), "FLAC");
+        assert_eq!(flac.interleaved_samples, wav.interleaved_samples);
+    }
+
+    #[test]
+    #[cfg(all(feature = "hash", feature = "flac"))]
+    fn test_content_hash() {
+        let hash = |path, start, stop| {
+            let config = AudioReadConfig {
+                start,
+                stop,
+                ..Default::default()
+            };
+            audio_content_hash(path, config).unwrap()
+        };
+        let whole = |path| hash(path, Position::Default, Position::Default);
+        let scoped = |path| hash(path, Position::Frame(100), Position::Frame(200));
+
+        // same audio with different tags and container
+        assert_eq!(
+            whole("test_data/test_1ch.wav"),
+            whole("test_data/test_1ch.flac")
+        );
+        assert_ne!(
+            whole("test_data/test_1ch.wav"),
+            whole("test_data/test_4ch.wav")
+        );
+        assert_eq!(
+            scoped("test_data/test_1ch.wav"),
+            scoped("test_data/test_1ch.flac")
+        );
+        assert_ne!(
+            scoped("test_data/test_1ch.wav"),
+            whole("test_data/test_1ch.wav")
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        audio_validate("test_data/test_1ch.wav").unwrap();
+        audio_validate("test_data/test_4ch.wav").unwrap();
+        #[cfg(feature = "flac")]
+        audio_validate("test_data/test_1ch.flac").unwrap();
+
+        // cut the file in the middle of the data chunk
+        let bytes = std::fs::read("test_data/test_1ch.wav").unwrap();
+        std::fs::write("tmp_truncated.wav", &bytes[..bytes.len() / 2]).unwrap();
+        let result = audio_validate("tmp_truncated.wav").map_err(AudioReadError::without_path);
+        let _ = std::fs::remove_file("tmp_truncated.wav");
+
+        match result {
+            Err(AudioReadError::TruncatedStream(decoded, 48000)) => assert!(decoded < 48000),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn test_mmap() {
+        let data1: AudioData<f32> =
+            audio_read("test_data/test_4ch.wav", AudioReadConfig::default()).unwrap();
+        let data2: AudioData<f32> =
+            audio_read_mmap("test_data/test_4ch.wav", AudioReadConfig::default()).unwrap();
+        assert_eq!(data1.num_channels, data2.num_channels);
+        assert_eq!(data1.interleaved_samples, data2.interleaved_samples);
+
+        let data3: AudioData<f32> = audio_read_mmap(
+            "test_data/test_4ch.wav",
+            AudioReadConfig {
+                start: Position::Frame(30000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(data3.num_frames, 18000);
+        assert_eq!(
+            data1.interleaved_samples[30000 * 4..],
+            data3.interleaved_samples[..]
+        );
+    }
+
+    #[test]
+    fn test_window_helpers() {
+        let Position::Time(t) = Position::from_mmss(1, 30) else {
+            panic!()
+        };
+        assert_eq!(t, Duration::from_secs(90));
+
+        let window = Window::starting_at(Duration::from_secs(90), Duration::from_secs(10));
+        match window.into() {
+            (Position::Time(start), Position::Time(stop)) => {
+                assert_eq!(start, Duration::from_secs(90));
+                assert_eq!(stop, Duration::from_secs(100));
+            }
+            _ => panic!(),
+        }
+
+        let window = Window::centered(Duration::from_secs(10), Duration::from_secs(4));
+        match window.into() {
+            (Position::Time(start), Position::Time(stop)) => {
+                assert_eq!(start, Duration::from_secs(8));
+                assert_eq!(stop, Duration::from_secs(12));
+            }
+            _ => panic!(),
+        }
+
+        // odd widths keep the full length
+        let window = Window::centered(Duration::from_millis(500), Duration::from_millis(101));
+        match window.into() {
+            (Position::Time(start), Position::Time(stop)) => {
+                assert_eq!(stop - start, Duration::from_millis(101));
+            }
+            _ => panic!(),
+        }
+
+        // the start is clamped to the beginning of the file
+        let window = Window::centered(Duration::from_secs(1), Duration::from_secs(4));
+        match window.into() {
+            (Position::Time(start), Position::Time(stop)) => {
+                assert_eq!(start, Duration::ZERO);
+                assert_eq!(stop, Duration::from_secs(3));
+            }
+            _ => panic!(),
+        }
+
+        let window = Window::centered(Duration::from_millis(500), Duration::from_millis(100));
+        let data: AudioData<f32> = audio_read(
+            "test_data/test_1ch.wav",
+            AudioReadConfig {
+                start: window.start,
+                stop: window.stop,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(data.num_frames, 4800);
+    }
+
+    #[test]
+    #[cfg(all(feature = "ogg", feature = "flac"))]
+    fn test_chained_stream() {
+        // two chained streams with 8192 frames each, which contain a continuous ramp
+        let data: AudioData<f32> =
+            audio_read("test_data/test_chained.ogg", AudioReadConfig::default()).unwrap();
+        assert_eq!(data.num_frames, 16384);
+
+        let data: AudioData<f32> = audio_read(
+            "test_data/test_chained.ogg",
+            AudioReadConfig {
+                start: Position::Frame(8000),
+                stop: Position::Frame(8400),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(data.num_frames, 400);
+        for (i, sample) in data.interleaved_samples.iter().enumerate() {
+            let frame = 8000 + i as i32;
+            assert_eq!(*sample, (frame - 8192) as f32 / 32768.0);
+        }
+
+        // the track is selected again in the second stream, which has other track ids
+        let read = |track| {
+            audio_read::<_, f32>(
+                "test_data/test_chained_multitrack.ogg",
+                AudioReadConfig {
+                    track,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        let first = read(TrackSelection::First);
+        assert_eq!(first.num_channels, 1);
+        assert_eq!(first.num_frames, 8192);
+        assert_eq!(first.interleaved_samples[5000], 904.0 / 32768.0);
+        for track in [TrackSelection::MostChannels, TrackSelection::Id(2)] {
+            let data = read(track);
+            assert_eq!(data.num_channels, 2);
+            assert_eq!(data.num_frames, 8192);
+            let level = |frame: usize| data.interleaved_samples[2 * frame] * 32768.0;
+            assert_eq!(level(0), 1000.0);
+            assert_eq!(level(8191), 2000.0);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "ogg", feature = "flac"))]
+    fn test_track_selection() {
+        let read = |track| {
+            audio_read::<_, f32>(
+                "test_data/test_multitrack.ogg",
+                AudioReadConfig {
+                    track,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        let ramp = |frame: usize| (frame as f32 - 4096.0) / 32768.0;
+        let constant = 1000.0 / 32768.0;
+
+        let first = read(TrackSelection::First);
+        assert_eq!(first.num_channels, 1);
+        assert_eq!(first.num_frames, 8192);
+        assert_eq!(first.interleaved_samples[100], ramp(100));
+
+        let second = read(TrackSelection::Id(2));
+        assert_eq!(second.num_channels, 2);
+        assert_eq!(second.num_frames, 8192);
+        assert_eq!(second.interleaved_samples[200..202], [constant, -constant]);
+
+        let stacked = read(TrackSelection::All { mix: false });
+        assert_eq!(stacked.num_channels, 3);
+        assert_eq!(stacked.num_frames, 8192);
+        assert_eq!(
+            stacked.interleaved_samples[300..303],
+            [ramp(100), constant, -constant]
+        );
+
+        let mixed = read(TrackSelection::All { mix: true });
+        assert_eq!(mixed.num_channels, 2);
+        assert_eq!(mixed.num_frames, 8192);
+        assert_eq!(
+            mixed.interleaved_samples[200..202],
+            [ramp(100) + constant, ramp(100) - constant]
+        );
+
+        // channels are selected from the combined tracks
+        let selected = audio_read::<_, f32>(
+            "test_data/test_multitrack.ogg",
+            AudioReadConfig {
+                start_channel: Some(1),
+                num_channels: Some(1),
+                track: TrackSelection::All { mix: false },
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(selected.num_channels, 1);
+        assert!(selected.interleaved_samples.iter().all(|s| *s == constant));
+
+        assert!(matches!(
+            audio_read::<_, f32>(
+                "test_data/test_multitrack.ogg",
+                AudioReadConfig {
+                    track: TrackSelection::Id(3),
+                    ..Default::default()
+                },
+            )
+            .map_err(AudioReadError::without_path),
+            Err(AudioReadError::TrackNotFound(3))
+        ));
+
+        let most_channels = read(TrackSelection::MostChannels);
+        assert_eq!(most_channels.num_channels, 2);
+        assert_eq!(
+            most_channels.interleaved_samples[200..202],
+            [constant, -constant]
+        );
+
+        // both tracks have the same sample rate
+        let highest_rate = read(TrackSelection::HighestSampleRate);
+        assert_eq!(highest_rate.num_channels, 1);
+        assert_eq!(highest_rate.interleaved_samples[100], ramp(100));
+    }
+
+    #[test]
+    #[cfg(all(feature = "ogg", feature = "flac"))]
+    fn test_lossless_track() {
+        // the first track is Opus, which can't be decoded
+        let read = |track| {
+            audio_read::<_, f32>(
+                "test_data/test_lossless.ogg",
+                AudioReadConfig {
+                    track,
+                    ..Default::default()
+                },
+            )
+            .map_err(AudioReadError::without_path)
+        };
+        assert!(read(TrackSelection::First).is_err());
+
+        let lossless = read(TrackSelection::Lossless).unwrap();
+        assert_eq!(lossless.num_channels, 1);
+        assert_eq!(lossless.num_frames, 8192);
+        assert_eq!(
+            lossless.interleaved_samples[100],
+            (100.0 - 4096.0) / 32768.0
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "ogg")]
+    fn test_unsupported_codec() {
+        // symphonia can demux Ogg Opus, but has no Opus decoder
+        let result = audio_read::<_, f32>("test_data/test_opus.ogg", AudioReadConfig::default())
+            .map_err(AudioReadError::without_path);
+        assert!(matches!(result, Err(AudioReadError::UnsupportedCodec(name)) if name == "Opus"));
+    }
+
+    #[test]
+    fn test_pad_to_stop() {
+        let config = |pad_to_stop| AudioReadConfig {
+            start: Position::Frame(47000),
+            stop: Position::Frame(50000),
+            pad_to_stop,
+            ..Default::default()
+        };
+
+        let data: AudioData<f32> = audio_read("test_data/test_1ch.wav", config(false)).unwrap();
+        assert_eq!(data.num_frames, 1000);
+
+        let padded: AudioData<f32> = audio_read("test_data/test_1ch.wav", config(true)).unwrap();
+        assert_eq!(padded.num_frames, 3000);
+        assert_eq!(padded.interleaved_samples.len(), 3000);
+        assert_eq!(padded.interleaved_samples[..1000], data.interleaved_samples);
+        assert!(padded.interleaved_samples[1000..].iter().all(|s| *s == 0.0));
+    }
+
+    #[test]
+    fn test_read_stats() {
+        let (data, stats) =
+            audio_read_with_stats::<_, f32>("test_data/test_1ch.wav", AudioReadConfig::default())
+                .unwrap();
+        assert_eq!(data.num_frames, 48000);
+        assert!(stats.num_packets > 0);
+        assert_eq!(
+            stats.peak_bytes,
+            data.interleaved_samples.capacity() * size_of::<f32>()
+        );
+        assert!(stats.peak_bytes >= 48000 * size_of::<f32>());
+
+        let (_, stats_f64) =
+            audio_read_with_stats::<_, f64>("test_data/test_1ch.wav", AudioReadConfig::default())
+                .unwrap();
+        assert_eq!(stats_f64.num_packets, stats.num_packets);
+        assert_eq!(stats_f64.peak_bytes, 2 * stats.peak_bytes);
+    }
+
+    #[test]
+    #[cfg(feature = "write")]
+    fn test_is_dual_mono() {
+        let samples: Vec<f32> = (0..4800)
+            .flat_map(|n| {
+                let sample = crate::test_util::test_tone_sample(48000, 1000.0, n, 0);
+                // the second half of the right channel differs slightly
+                let offset = if n < 2400 { 0.0 } else { 0.001 };
+                [sample, sample + offset]
+            })
+            .collect();
+        let block = AudioBlockInterleavedView::from_slice(&samples, 2, 4800);
+        let config = crate::AudioWriteConfig {
+            sample_format: crate::writer::WriteSampleFormat::Float32,
+            ..Default::default()
+        };
+        crate::audio_write("tmp11.wav", block, 48000, config).unwrap();
+        let exact = audio_is_dual_mono("tmp11.wav", 0.0f32).unwrap();
+        let within_epsilon = audio_is_dual_mono("tmp11.wav", 0.01f32).unwrap();
+        std::fs::remove_file("tmp11.wav").unwrap();
+        assert!(!exact);
+        assert!(within_epsilon);
+
+        // channels of the test tone have different amplitudes
+        crate::test_util::write_test_tone("tmp11.wav", 48000, 2, 1000.0, 4800);
+        let stereo = audio_is_dual_mono("tmp11.wav", 0.01f32).unwrap();
+        std::fs::remove_file("tmp11.wav").unwrap();
+        assert!(!stereo);
+
+        assert!(matches!(
+            audio_is_dual_mono("test_data/test_1ch.wav", 0.0f32)
+                .map_err(AudioReadError::without_path),
+            Err(AudioReadError::NotStereo(1))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "write")]
+    fn test_energy_envelope() {
+        // silence, a loud and a quiet section of 100 ms each, the right channel is silent
+        let samples: Vec<f32> = (0..14400)
+            .flat_map(|n| {
+                let sample = crate::test_util::test_tone_sample(48000, 1000.0, n, 0);
+                let gain = [0.0, 1.0, 0.1][n / 4800];
+                [sample * gain, 0.0]
+            })
+            .collect();
+        let block = AudioBlockInterleavedView::from_slice(&samples, 2, 14400);
+        crate::audio_write("tmp14.wav", block, 48000, Default::default()).unwrap();
+        let envelope = |config| {
+            audio_energy_envelope::<_, f32>("tmp14.wav", Duration::from_millis(10), config).unwrap()
+        };
+
+        let downmixed = envelope(AudioReadConfig::default());
+        assert_eq!(downmixed.len(), 30);
+        assert!(downmixed[..10].iter().all(|e| *e == 0.0));
+        // RMS of the sine with amplitude 0.5, halved by the downmix with the silent channel
+        for e in &downmixed[10..20] {
+            approx::assert_abs_diff_eq!(*e, 0.5 / 2f32.sqrt() / 2.0, epsilon = 1e-3);
+        }
+        for e in &downmixed[20..] {
+            approx::assert_abs_diff_eq!(*e, 0.05 / 2f32.sqrt() / 2.0, epsilon = 1e-3);
+        }
+
+        // only the left channel from frame 7000 on, the last window has 200 frames
+        let config = AudioReadConfig {
+            start: Position::Frame(7000),
+            num_channels: Some(1),
+            ..Default::default()
+        };
+        let left = envelope(config);
+        std::fs::remove_file("tmp14.wav").unwrap();
+        assert_eq!(left.len(), 16);
+        approx::assert_abs_diff_eq!(left[0], 0.5 / 2f32.sqrt(), epsilon = 1e-3);
+        approx::assert_abs_diff_eq!(left[15], 0.05 / 2f32.sqrt(), epsilon = 2e-3);
+    }
+
+    #[test]
+    #[cfg(feature = "write")]
+    fn test_discarded_lead_frames() {
+        // 3 seconds, so the start position is far enough in the file to seek
+        let samples: Vec<f32> = (0..24000).map(|n| (n % 100) as f32 / 100.0).collect();
+        let block = AudioBlockInterleavedView::from_slice(&samples, 1, samples.len());
+        crate::audio_write("tmp7.wav", block, 8000, Default::default()).unwrap();
+
+        let read = |disable_seek| {
+            audio_read_with_stats::<_, f32>(
+                "tmp7.wav",
+                AudioReadConfig {
+                    start: Position::Frame(20000),
+                    disable_seek,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        let (seeked, stats) = read(false);
+        let (decoded, stats_no_seek) = read(true);
+        std::fs::remove_file("tmp7.wav").unwrap();
+
+        assert_eq!(seeked.interleaved_samples, decoded.interleaved_samples);
+        assert_eq!(seeked.num_frames, 4000);
+        // the seek lands at 90 % of the start position
+        assert!(stats.discarded_lead_frames <= 2000 + 4096);
+        assert_eq!(stats_no_seek.discarded_lead_frames, 20000);
+
+        let (_, stats) =
+            audio_read_with_stats::<_, f32>("test_data/test_1ch.wav", AudioReadConfig::default())
+                .unwrap();
+        assert_eq!(stats.discarded_lead_frames, 0);
+    }
+
+    #[test]
+    fn test_decode_into() {
+        let read = |decode_into| {
+            audio_read::<_, f64>(
+                "test_data/test_1ch.wav",
+                AudioReadConfig {
+                    decode_into,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        let reference: AudioData<f32> =
+            audio_read("test_data/test_1ch.wav", AudioReadConfig::default()).unwrap();
+
+        // a 16-bit source is decoded exactly with every sample type
+        for decode_into in [
+            DecodeInto::I16,
+            DecodeInto::I32,
+            DecodeInto::F32,
+            DecodeInto::F64,
+        ] {
+            let data = read(decode_into);
+            assert_eq!(data.num_frames, reference.num_frames);
+            assert!(
+                data.interleaved_samples
+                    .iter()
+                    .zip(&reference.interleaved_samples)
+                    .all(|(a, b)| *a == *b as f64),
+                "{decode_into:?}"
+            );
+
+            let data_f32 = audio_read::<_, f32>(
+                "test_data/test_1ch.wav",
+                AudioReadConfig {
+                    decode_into,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            assert_eq!(data_f32.interleaved_samples, reference.interleaved_samples);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "write")]
+    fn test_decode_into_32_bit() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 48000,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let samples = [0x12345679, -0x7654321f, 1, i32::MIN];
+        let mut writer = hound::WavWriter::create("tmp_32bit.wav", spec).unwrap();
+        for sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let read = |decode_into| {
+            audio_read::<_, f64>(
+                "tmp_32bit.wav",
+                AudioReadConfig {
+                    decode_into,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .interleaved_samples
+        };
+        let expected: Vec<f64> = samples.iter().map(|s| *s as f64 / 2147483648.0).collect();
+
+        // only the 32-bit types keep all bits
+        assert_eq!(read(DecodeInto::I32), expected);
+        assert_eq!(read(DecodeInto::F64), expected);
+        assert_ne!(read(DecodeInto::F32), expected);
+        approx::assert_abs_diff_eq!(
+            read(DecodeInto::F32).as_slice(),
+            expected.as_slice(),
+            epsilon = 1e-7
+        );
+        approx::assert_abs_diff_eq!(
+            read(DecodeInto::I16).as_slice(),
+            expected.as_slice(),
+            epsilon = 1e-4
+        );
+
+        // by default the precision of the output type is kept
+        assert_eq!(read(DecodeInto::Auto), expected);
+        let single: Vec<f32> = audio_read("tmp_32bit.wav", AudioReadConfig::default())
+            .unwrap()
+            .interleaved_samples;
+        assert!(single.iter().zip(&expected).any(|(s, e)| *s as f64 != *e));
+        approx::assert_abs_diff_eq!(
+            single.as_slice(),
+            read(DecodeInto::F32)
+                .iter()
+                .map(|s| *s as f32)
+                .collect::<Vec<_>>()
+                .as_slice()
+        );
+
+        let _ = std::fs::remove_file("tmp_32bit.wav");
+    }
+
+    #[test]
+    fn test_default_hint() {
+        // bytes in front of the file that look like a FLAC marker
+        let mut bytes = b"fLaC".to_vec();
+        bytes.extend_from_slice(&[0; 12]);
+        bytes.extend_from_slice(&std::fs::read("test_data/test_1ch.wav").unwrap());
+        std::fs::write("tmp_no_extension", bytes).unwrap();
+        let read = |default_hint| {
+            audio_read::<_, f32>(
+                "tmp_no_extension",
+                AudioReadConfig {
+                    default_hint,
+                    ..Default::default()
+                },
+            )
+        };
+        let without_hint = read(None);
+        let data = read(Some("wav".to_string()));
+        let _ = std::fs::remove_file("tmp_no_extension");
+
+        if cfg!(feature = "flac") {
+            assert!(without_hint.is_err());
+        }
+        let data = data.unwrap();
+        assert_eq!(data.container, Container::Wav);
+        assert_eq!(data.num_frames, 48000);
+    }
+
+    #[test]
+    fn test_source_num_frames() {
+        let full: AudioData<f32> =
+            audio_read("test_data/test_1ch.wav", AudioReadConfig::default()).unwrap();
+        assert_eq!(full.source_num_frames, Some(full.num_frames));
+
+        let part: AudioData<f32> = audio_read(
+            "test_data/test_1ch.wav",
+            AudioReadConfig {
+                start: Position::Frame(1000),
+                stop: Position::Frame(2000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(part.num_frames, 1000);
+        assert_eq!(part.source_num_frames, Some(48000));
+    }
+
+    #[test]
+    fn test_read_packed() {
+        let packed =
+            audio_read_packed("test_data/test_1ch.wav", AudioReadConfig::default()).unwrap();
+        assert_eq!(packed.sample_format, SampleFormat::Int);
+        assert_eq!(packed.bits_per_sample, 16);
+        assert_eq!(packed.num_channels, 1);
+        assert_eq!(packed.num_frames, 48000);
+        assert_eq!(packed.sample_rate, 48000);
+
+        // the file has a 44 byte header followed by the data chunk
+        let file = std::fs::read("test_data/test_1ch.wav").unwrap();
+        assert_eq!(&file[36..40], b"data");
+        assert_eq!(packed.bytes, file[44..44 + 2 * 48000]);
+
+        let part = audio_read_packed(
+            "test_data/test_4ch.wav",
+            AudioReadConfig {
+                start: Position::Frame(100),
+                stop: Position::Frame(200),
+                start_channel: Some(1),
+                num_channels: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(part.num_channels, 2);
+        assert_eq!(part.num_frames, 100);
+        assert_eq!(
+            part.bytes.len(),
+            100 * 2 * part.bits_per_sample as usize / 8
+        );
+    }
+
+    #[test]
+    fn test_empty_file() {
+        std::fs::write("tmp_empty.wav", []).unwrap();
+        let result = audio_read::<_, f32>("tmp_empty.wav", AudioReadConfig::default())
+            .map_err(AudioReadError::without_path);
+        let _ = std::fs::remove_file("tmp_empty.wav");
+        assert!(matches!(result, Err(AudioReadError::EmptyStream)));
+
+        // valid header, but the data chunk is empty
+        let mut header = std::fs::read("test_data/test_1ch.wav").unwrap()[..44].to_vec();
+        header[4..8].copy_from_slice(&36u32.to_le_bytes());
+        header[40..44].copy_from_slice(&0u32.to_le_bytes());
+        std::fs::write("tmp_header_only.wav", &header).unwrap();
+        let result = audio_read::<_, f32>("tmp_header_only.wav", AudioReadConfig::default())
+            .map_err(AudioReadError::without_path);
+        let packed = audio_read_packed("tmp_header_only.wav", AudioReadConfig::default())
+            .map_err(AudioReadError::without_path);
+        let _ = std::fs::remove_file("tmp_header_only.wav");
+        assert!(matches!(result, Err(AudioReadError::EmptyStream)));
+        assert!(matches!(packed, Err(AudioReadError::EmptyStream)));
+    }
+
+    #[test]
+    fn test_packets_selection() {
+        let read = |start, packets| {
+            audio_read_with_stats::<_, f32>(
+                "test_data/test_1ch.wav",
+                AudioReadConfig {
+                    start,
+                    stop: Position::Packets(packets),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+
+        let (one, stats) = read(Position::Default, 1);
+        assert_eq!(stats.num_packets, 1);
+        let packet_frames = one.num_frames;
+        assert!(packet_frames > 0 && packet_frames < 48000);
+
+        let (three, stats) = read(Position::Default, 3);
+        assert_eq!(stats.num_packets, 3);
+        assert_eq!(three.num_frames, 3 * packet_frames);
+
+        // no packets, the channels are still known from the first packet
+        let (none, _) = read(Position::Default, 0);
+        assert_eq!(none.num_frames, 0);
+        assert_eq!(none.num_channels, 1);
+        assert!(none.interleaved_samples.is_empty());
+
+        // packets are counted from the start position on, the first one is partially selected
+        let (data, stats) = read(Position::Frame(packet_frames + 10), 2);
+        assert_eq!(data.num_frames, 2 * packet_frames - 10);
+        // the packet before the start is decoded as well
+        assert_eq!(stats.num_packets, 3);
+
+        assert!(matches!(
+            audio_read::<_, f32>(
+                "test_data/test_1ch.wav",
+                AudioReadConfig {
+                    start: Position::Packets(1),
+                    ..Default::default()
+                },
+            )
+            .map_err(AudioReadError::without_path),
+            Err(AudioReadError::PacketsAsStart)
+        ));
+    }
+
+    #[test]
+    fn test_frame_and_channel_iterators() {
+        let data = AudioData {
+            interleaved_samples: vec![0.0f32, 1.0, 0.1, 1.1, 0.2, 1.2],
+            sample_rate: 48000,
+            num_channels: 2,
+            num_frames: 3,
+            ..Default::default()
+        };
+
+        assert_eq!(data.frames().count(), 3);
+        assert!(data.frames().all(|frame| frame.len() == 2));
+        assert_eq!(data.frames().nth(1).unwrap(), [0.1, 1.1]);
+
+        assert_eq!(
+            data.channel(0).copied().collect::<Vec<_>>(),
+            [0.0, 0.1, 0.2]
+        );
+        assert_eq!(
+            data.channel(1).copied().collect::<Vec<_>>(),
+            [1.0, 1.1, 1.2]
+        );
+
+        let empty = AudioData::<f32>::default();
+        assert_eq!(empty.frames().count(), 0);
+    }
+
+    #[test]
+    fn test_into_sequential_block() {
+        let data: AudioData<f32> =
+            audio_read("test_data/test_4ch.wav", AudioReadConfig::default()).unwrap();
+        let channel_0: Vec<f32> = data.channel(0).copied().collect();
+        let channel_3: Vec<f32> = data.channel(3).copied().collect();
+
+        let block = data.into_sequential_block();
+        assert_eq!(block.num_channels(), 4);
+        assert_eq!(block.num_frames(), 48000);
+        assert_eq!(block.raw_data()[..48000], channel_0);
+        assert_eq!(block.raw_data()[3 * 48000..], channel_3);
+    }
+
+    #[test]
+    fn test_into_planar() {
+        let config = AudioReadConfig {
+            num_channels: Some(2),
+            ..Default::default()
+        };
+        let read = || audio_read::<_, f32>("test_data/test_4ch.wav", config.clone()).unwrap();
+        let interleaved = read().interleaved_samples;
+
+        let PlanarLayout::Separate(channels) = read().into_planar(PlanarMode::Separate) else {
+            panic!("expected separate channels");
+        };
+        assert_eq!(channels.len(), 2);
+        for (n, frame) in interleaved.chunks_exact(2).enumerate() {
+            assert_eq!(channels[0][n], frame[0]);
+            assert_eq!(channels[1][n], frame[1]);
+        }
+
+        let PlanarLayout::Contiguous { data, channel_len } =
+            read().into_planar(PlanarMode::Contiguous)
+        else {
+            panic!("expected contiguous channels");
+        };
+        assert_eq!(channel_len, 48000);
+        assert_eq!(data.len(), 2 * 48000);
+        for (n, frame) in interleaved.chunks_exact(2).enumerate() {
+            assert_eq!(data[n], frame[0]);
+            assert_eq!(data[channel_len + n], frame[1]);
+        }
+    }
+
+    #[test]
+    fn test_mix() {
+        let audio = |samples: Vec<f32>, num_channels, sample_rate| AudioData {
+            num_frames: samples.len() / num_channels,
+            interleaved_samples: samples,
+            num_channels,
+            sample_rate,
+            ..Default::default()
+        };
+        let a = audio(vec![0.1, 0.2, 0.3, 0.4], 2, 48000);
+        let b = audio(vec![0.5, 0.5, 1.0, 1.0], 2, 48000);
+        let mixed = a.mix(&b, 0.5).unwrap();
+        assert_eq!(mixed.num_frames, 2);
+        assert_eq!(mixed.interleaved_samples, [0.35, 0.45, 0.8, 0.9]);
+
+        // the shorter one is padded with zeros, in both directions
+        let short = audio(vec![1.0, 1.0], 2, 48000);
+        let mixed = a.mix(&short, 1.0).unwrap();
+        assert_eq!(mixed.num_frames, 2);
+        assert_eq!(mixed.interleaved_samples, [1.1, 1.2, 0.3, 0.4]);
+        let mixed = short.mix(&a, 1.0).unwrap();
+        assert_eq!(mixed.num_frames, 2);
+        assert_eq!(mixed.interleaved_samples, [1.1, 1.2, 0.3, 0.4]);
+
+        assert!(matches!(
+            a.mix(&audio(vec![0.0; 4], 2, 44100), 1.0),
+            Err(MixError::SampleRateMismatch(48000, 44100))
+        ));
+        assert!(matches!(
+            a.mix(&audio(vec![0.0; 4], 1, 48000), 1.0),
+            Err(MixError::ChannelMismatch(2, 1))
+        ));
+    }
+
+    #[test]
+    fn test_center_crop() {
+        // stereo, frame n has the samples n and -n
+        let audio = |num_frames: usize| AudioData {
+            interleaved_samples: (0..num_frames)
+                .flat_map(|n| [n as f32, -(n as f32)])
+                .collect(),
+            num_channels: 2,
+            num_frames,
+            sample_rate: 48000,
+            ..Default::default()
+        };
+
+        let cropped = audio(10).center_crop(4);
+        assert_eq!(cropped.num_frames, 4);
+        assert_eq!(
+            cropped.interleaved_samples,
+            [3.0, -3.0, 4.0, -4.0, 5.0, -5.0, 6.0, -6.0]
+        );
+        // odd difference, the end loses one frame more
+        let cropped = audio(7).center_crop(4);
+        assert_eq!(
+            cropped.interleaved_samples,
+            [1.0, -1.0, 2.0, -2.0, 3.0, -3.0, 4.0, -4.0]
+        );
+
+        let padded = audio(2).center_crop(5);
+        assert_eq!(padded.num_frames, 5);
+        assert_eq!(
+            padded.interleaved_samples,
+            [0.0, 0.0, 0.0, -0.0, 1.0, -1.0, 0.0, 0.0, 0.0, 0.0]
+        );
+
+        assert_eq!(
+            audio(3).center_crop(3).interleaved_samples,
+            audio(3).interleaved_samples
+        );
+        assert!(audio(3).center_crop(0).interleaved_samples.is_empty());
+    }
+
+    #[test]
+    fn test_pad_to_frames() {
+        // stereo, frame n has the samples n + 1 and -(n + 1)
+        let audio = |num_frames: usize| AudioData {
+            interleaved_samples: (1..=num_frames)
+                .flat_map(|n| [n as f32, -(n as f32)])
+                .collect(),
+            num_channels: 2,
+            num_frames,
+            sample_rate: 48000,
+            ..Default::default()
+        };
+        let first_channel = |data: &AudioData<f32>| data.channel(0).copied().collect::<Vec<_>>();
+
+        let zero = audio(3).pad_to_frames(5, PadMode::Zero);
+        assert_eq!(zero.num_frames, 5);
+        assert_eq!(zero.interleaved_samples[6..], [0.0; 4]);
+
+        let edge = audio(3).pad_to_frames(5, PadMode::Edge);
+        assert_eq!(edge.num_frames, 5);
+        assert_eq!(edge.interleaved_samples[6..], [3.0, -3.0, 3.0, -3.0]);
+
+        let reflect = audio(3).pad_to_frames(9, PadMode::Reflect);
+        assert_eq!(reflect.num_frames, 9);
+        assert_eq!(
+            first_channel(&reflect),
+            [1.0, 2.0, 3.0, 2.0, 1.0, 2.0, 3.0, 2.0, 1.0]
+        );
+        assert_eq!(reflect.interleaved_samples[6..8], [2.0, -2.0]);
+
+        // already long enough, single frame and empty audio
+        let long = audio(4).pad_to_frames(2, PadMode::Zero);
+        assert_eq!(long.num_frames, 4);
+        assert_eq!(long.interleaved_samples, audio(4).interleaved_samples);
+        let single = audio(1).pad_to_frames(3, PadMode::Reflect);
+        assert_eq!(first_channel(&single), [1.0, 1.0, 1.0]);
+        let empty = audio(0).pad_to_frames(2, PadMode::Edge);
+        assert_eq!(empty.interleaved_samples, [0.0; 4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_channel_out_of_range() {
+        let data = AudioData {
+            interleaved_samples: vec![0.0f32, 1.0],
+            num_channels: 2,
+            num_frames: 1,
+            ..Default::default()
+        };
+        let _ = data.channel(2);
+    }
+
+    #[test]
+    fn test_read_file() {
+        let expected: AudioData<f32> =
+            audio_read("test_data/test_4ch.wav", AudioReadConfig::default()).unwrap();
+
+        let file = File::open("test_data/test_4ch.wav").unwrap();
+        let data: AudioData<f32> =
+            audio_read_file(file, Some("wav"), AudioReadConfig::default()).unwrap();
+        assert_eq!(data.num_channels, expected.num_channels);
+        assert_eq!(data.interleaved_samples, expected.interleaved_samples);
+
+        // the position of the file does not matter
+        let mut file = File::open("test_data/test_4ch.wav").unwrap();
+        file.seek(SeekFrom::Start(100)).unwrap();
+        let data: AudioData<f32> = audio_read_file(file, None, AudioReadConfig::default()).unwrap();
+        assert_eq!(data.interleaved_samples, expected.interleaved_samples);
+    }
+
+    #[test]
+    #[cfg(feature = "caf")]
+    fn test_caf_markers() {
+        let data: AudioData<f32> =
+            audio_read("test_data/test_loop.caf", AudioReadConfig::default()).unwrap();
+        assert_eq!(data.container, Container::Caf);
+        assert_eq!(data.num_frames, 4800);
+        assert_eq!(
+            data.cues,
+            [
+                Cue {
+                    label: "Intro".to_string(),
+                    frame: 1000
+                },
+                Cue {
+                    label: "Drop".to_string(),
+                    frame: 3000
+                }
+            ]
+        );
+        assert_eq!(
+            data.loops,
+            [LoopRegion {
+                start_frame: 1200,
+                end_frame: 3600
+            }]
+        );
+
+        // same audio as the wav file
+        let wav: AudioData<f32> = audio_read(
+            "test_data/test_1ch.wav",
+            AudioReadConfig {
+                stop: Position::Frame(4800),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(data.interleaved_samples, wav.interleaved_samples);
+        assert!(wav.cues.is_empty());
+        assert!(wav.loops.is_empty());
+    }
+
+    #[test]
+    fn test_wav_instrument() {
+        let data: AudioData<f32> =
+            audio_read("test_data/test_smpl.wav", AudioReadConfig::default()).unwrap();
+        assert_eq!(data.container, Container::Wav);
+        assert_eq!(data.num_frames, 4800);
+        assert_eq!(
+            data.instrument,
+            Some(InstrumentInfo {
+                midi_unity_note: 60,
+                loops: vec![LoopRegion {
+                    start_frame: 1200,
+                    end_frame: 3600
+                }]
+            })
+        );
+
+        let wav: AudioData<f32> =
+            audio_read("test_data/test_1ch.wav", AudioReadConfig::default()).unwrap();
+        assert_eq!(wav.instrument, None);
+        assert_eq!(data.interleaved_samples, wav.interleaved_samples[..4800]);
+    }
+
+    #[test]
+    fn test_data_offset() {
+        let data: AudioData<f32> = audio_read(
+            "test_data/test_1ch.wav",
+            AudioReadConfig {
+                decode_into: DecodeInto::I16,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let data_offset = data.data_offset.unwrap() as usize;
+        assert_eq!(data.data_len, Some(data.num_frames as u64 * 2));
+
+        let bytes = std::fs::read("test_data/test_1ch.wav").unwrap();
+        for frame in [0, 1000] {
+            let pos = data_offset + frame * 2;
+            let sample = i16::from_le_bytes([bytes[pos], bytes[pos + 1]]);
+            assert_eq!(data.interleaved_samples[frame], sample as f32 / 32768.0);
+        }
 
-    let mut hint = Hint::new();
-    if let Some(ext) = path.as_ref().extension()
-        && let Some(ext_str) = ext.to_str()
-    {
-        hint.with_extension(ext_str);
+        #[cfg(feature = "flac")]
+        {
+            let flac: AudioData<f32> =
+                audio_read("test_data/test_1ch.flac", AudioReadConfig::default()).unwrap();
+            assert_eq!((flac.data_offset, flac.data_len), (None, None));
+        }
     }
 
-    let meta_opts: MetadataOptions = Default::default();
-    let fmt_opts: FormatOptions = Default::default();
+    #[test]
+    fn test_whole_packet_fast_path() {
+        // decoding into f64 never takes the fast path
+        let slow = |path| {
+            audio_read::<_, f32>(
+                path,
+                AudioReadConfig {
+                    decode_into: DecodeInto::F64,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
 
-    let probed = symphonia::default::get_probe().format(&hint, mss, &fmt_opts, &meta_opts)?;
+        for path in ["test_data/test_1ch.wav", "test_data/test_4ch.wav"] {
+            let fast: AudioData<f32> = audio_read(path, AudioReadConfig::default()).unwrap();
+            let fast_f64: AudioData<f64> = audio_read(path, AudioReadConfig::default()).unwrap();
+            let slow = slow(path);
+            assert_eq!(fast.num_frames, slow.num_frames);
+            assert_eq!(fast.interleaved_samples, slow.interleaved_samples);
+            assert!(
+                fast_f64
+                    .interleaved_samples
+                    .iter()
+                    .zip(&slow.interleaved_samples)
+                    .all(|(a, b)| *a == *b as f64)
+            );
+        }
+    }
 
-    let mut format = probed.format;
+    #[test]
+    fn test_read_windows() {
+        let config = |partial_window| AudioReadConfig {
+            stop: Position::Frame(1000),
+            partial_window,
+            ..Default::default()
+        };
+        let data: AudioData<f32> =
+            audio_read("test_data/test_1ch.wav", config(PartialWindow::Drop)).unwrap();
 
-    let track = format
-        .tracks()
-        .iter()
-        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
-        .ok_or(AudioReadError::NoTrack)?;
+        let mut windows = Vec::new();
+        audio_read_windows(
+            "test_data/test_1ch.wav",
+            256,
+            128,
+            config(PartialWindow::Drop),
+            |window: &[f32], start| windows.push((start, window.to_vec())),
+        )
+        .unwrap();
+        let starts: Vec<_> = windows.iter().map(|(start, _)| *start).collect();
+        assert_eq!(starts, [0, 128, 256, 384, 512, 640]);
+        for (start, window) in &windows {
+            assert_eq!(window, &data.interleaved_samples[*start..start + 256]);
+        }
 
-    let sample_rate = track
-        .codec_params
-        .sample_rate
-        .ok_or(AudioReadError::NoSampleRate)?;
+        // the last frames are covered by a single padded window
+        let mut windows = Vec::new();
+        audio_read_windows(
+            "test_data/test_1ch.wav",
+            256,
+            128,
+            config(PartialWindow::Pad),
+            |window: &[f32], start| windows.push((start, window.to_vec())),
+        )
+        .unwrap();
+        assert_eq!(windows.len(), 7);
+        let (start, last) = &windows[6];
+        assert_eq!(*start, 768);
+        assert_eq!(last[..232], data.interleaved_samples[768..]);
+        assert!(last[232..].iter().all(|s| *s == 0.0));
 
-    let track_id = track.id;
+        // frames between windows are skipped if the hop size is larger than the window
+        let mut starts = Vec::new();
+        audio_read_windows(
+            "test_data/test_1ch.wav",
+            100,
+            300,
+            config(PartialWindow::Drop),
+            |window: &[f32], start| {
+                assert_eq!(window, &data.interleaved_samples[start..start + 100]);
+                starts.push(start);
+            },
+        )
+        .unwrap();
+        assert_eq!(starts, [0, 300, 600, 900]);
 
-    // Clone codec params before the mutable borrow
-    let codec_params = track.codec_params.clone();
-    let time_base = track.codec_params.time_base;
+        assert!(matches!(
+            audio_read_windows::<_, f32>(
+                "test_data/test_1ch.wav",
+                0,
+                128,
+                AudioReadConfig::default(),
+                |_, _| {}
+            ),
+            Err(AudioReadError::InvalidWindow(0, 128))
+        ));
+    }
 
-    // Convert start/stop positions to frame numbers
-    let start_frame = match config.start {
-        Position::Default => 0,
-        Position::Time(duration) => {
-            let secs = duration.as_secs_f64();
-            (secs * sample_rate as f64) as usize
-        }
-        Position::Frame(frame) => frame,
-    };
+    #[test]
+    fn test_supported_read_formats() {
+        let formats = supported_read_formats();
+        let pcm = cfg!(any(feature = "pcm", feature = "all"));
+        assert_eq!(
+            formats.contains(&"wav"),
+            cfg!(all(
+                feature = "wav",
+                any(feature = "pcm", feature = "adpcm")
+            )) || cfg!(feature = "all")
+        );
+        assert_eq!(formats.contains(&"w64"), pcm);
+        assert_eq!(
+            formats.contains(&"flac"),
+            cfg!(any(feature = "flac", feature = "all"))
+        );
+        assert_eq!(
+            formats.contains(&"aiff"),
+            pcm && cfg!(feature = "aiff") || cfg!(feature = "all")
+        );
+    }
 
-    let end_frame: Option<usize> = match config.stop {
-        Position::Default => None,
-        Position::Time(duration) => {
-            let secs = duration.as_secs_f64();
-            Some((secs * sample_rate as f64) as usize)
+    #[test]
+    fn test_headroom() {
+        let plain: AudioData<f32> =
+            audio_read("test_data/test_1ch.wav", AudioReadConfig::default()).unwrap();
+        let config = AudioReadConfig {
+            headroom_db: Some(20.0 * 2f64.log10()),
+            ..Default::default()
+        };
+        let headroom: AudioData<f32> = audio_read("test_data/test_1ch.wav", config).unwrap();
+        assert_eq!(headroom.num_frames, plain.num_frames);
+        for (h, p) in headroom
+            .interleaved_samples
+            .iter()
+            .zip(&plain.interleaved_samples)
+        {
+            approx::assert_abs_diff_eq!(*h, p / 2.0, epsilon = 1e-6);
         }
-        Position::Frame(frame) => Some(frame),
-    };
 
-    if let Some(end_frame) = end_frame
-        && start_frame > end_frame
-    {
-        return Err(AudioReadError::EndFrameLargerThanStartFrame(
-            end_frame,
-            start_frame,
+        let config = AudioReadConfig {
+            headroom_db: Some(-6.0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            audio_read::<_, f32>("test_data/test_1ch.wav", config)
+                .map_err(AudioReadError::without_path),
+            Err(AudioReadError::InvalidHeadroom(-6.0))
         ));
     }
 
-    // If start_frame is large (more than 1 second), use seeking to avoid decoding everything
-    if start_frame > sample_rate as usize
-        && let Some(tb) = time_base
-    {
-        // Seek to 90% of the target to account for keyframe positioning
-        let seek_sample = (start_frame as f64 * 0.9) as u64;
-        let seek_ts = (seek_sample * tb.denom as u64) / (sample_rate as u64);
-
-        // Try to seek, but don't fail if seeking doesn't work
-        let _ = format.seek(
-            SeekMode::Accurate,
-            SeekTo::TimeStamp {
-                ts: seek_ts,
-                track_id,
-            },
-        );
-    }
+    /// Float type that can only represent values up to 0.01, so converting samples fails.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+    struct Narrow(f32);
 
-    let dec_opts: DecoderOptions = Default::default();
-    let mut decoder = symphonia::default::get_codecs().make(&codec_params, &dec_opts)?;
+    mod narrow {
+        use super::Narrow;
+        use num::{Float, Num, NumCast, One, ToPrimitive, Zero};
+        use std::num::FpCategory;
+        use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
 
-    let mut sample_buf = None;
-    let mut samples = Vec::new();
-    let mut num_channels = 0usize;
-    let start_channel = config.start_channel;
+        macro_rules! ops {
+            ($($trait:ident::$name:ident),*) => {$(
+                impl $trait for Narrow {
+                    type Output = Self;
+                    fn $name(self, other: Self) -> Self {
+                        Self(self.0.$name(other.0))
+                    }
+                }
+            )*};
+        }
+        ops!(Add::add, Sub::sub, Mul::mul, Div::div, Rem::rem);
 
-    // We'll track exact position by counting samples as we decode
-    let mut current_sample: Option<u64> = None;
+        impl Neg for Narrow {
+            type Output = Self;
+            fn neg(self) -> Self {
+                Self(-self.0)
+            }
+        }
 
-    loop {
-        let packet = match format.next_packet() {
-            Ok(packet) => packet,
-            Err(Error::ResetRequired) => {
-                decoder.reset();
-                continue;
+        impl Zero for Narrow {
+            fn zero() -> Self {
+                Self(0.0)
             }
-            Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-                break;
+            fn is_zero(&self) -> bool {
+                self.0 == 0.0
             }
-            Err(err) => return Err(err.into()),
-        };
+        }
 
-        if packet.track_id() != track_id {
-            continue;
+        impl One for Narrow {
+            fn one() -> Self {
+                Self(1.0)
+            }
         }
 
-        let decoded = decoder.decode(&packet)?;
+        impl Num for Narrow {
+            type FromStrRadixErr = num::traits::ParseFloatError;
+            fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+                f32::from_str_radix(s, radix).map(Self)
+            }
+        }
 
-        // Get the timestamp of this packet to know our position
-        if current_sample.is_none() {
-            let ts = packet.ts();
-            if let Some(tb) = time_base {
-                // Convert timestamp to sample position
-                current_sample = Some((ts * sample_rate as u64) / tb.denom as u64);
-            } else {
-                current_sample = Some(0);
+        impl ToPrimitive for Narrow {
+            fn to_i64(&self) -> Option<i64> {
+                self.0.to_i64()
+            }
+            fn to_u64(&self) -> Option<u64> {
+                self.0.to_u64()
+            }
+            fn to_f64(&self) -> Option<f64> {
+                Some(self.0 as f64)
             }
         }
 
-        if sample_buf.is_none() {
-            let spec = *decoded.spec();
-            let duration = decoded.capacity() as u64;
-            sample_buf = Some(SampleBuffer::<f32>::new(duration, spec));
+        impl NumCast for Narrow {
+            fn from<T: ToPrimitive>(n: T) -> Option<Self> {
+                n.to_f32().filter(|v| v.abs() <= 0.01).map(Self)
+            }
+        }
 
-            // Get the number of channels from the spec
-            num_channels = spec.channels.count();
+        macro_rules! constants {
+            ($($name:ident),*) => {$(
+                fn $name() -> Self {
+                    Self(<f32 as Float>::$name())
+                }
+            )*};
+        }
+        macro_rules! predicates {
+            ($($name:ident),*) => {$(
+                fn $name(self) -> bool {
+                    self.0.$name()
+                }
+            )*};
+        }
+        macro_rules! unary {
+            ($($name:ident),*) => {$(
+                fn $name(self) -> Self {
+                    Self(self.0.$name())
+                }
+            )*};
+        }
+        macro_rules! binary {
+            ($($name:ident),*) => {$(
+                fn $name(self, other: Self) -> Self {
+                    Self(self.0.$name(other.0))
+                }
+            )*};
+        }
 
-            // Validate channel range
-            let ch_start = start_channel.unwrap_or(0);
-            let ch_count = config.num_channels.unwrap_or(num_channels - ch_start);
+        impl Float for Narrow {
+            constants!(
+                nan,
+                infinity,
+                neg_infinity,
+                neg_zero,
+                min_value,
+                min_positive_value,
+                max_value
+            );
+            predicates!(
+                is_nan,
+                is_infinite,
+                is_finite,
+                is_normal,
+                is_sign_positive,
+                is_sign_negative
+            );
+            unary!(
+                floor, ceil, round, trunc, fract, abs, signum, recip, sqrt, exp, exp2, ln, log2,
+                log10, cbrt, sin, cos, tan, asin, acos, atan, exp_m1, ln_1p, sinh, cosh, tanh,
+                asinh, acosh, atanh
+            );
+            binary!(powf, log, max, min, hypot, atan2);
 
-            if ch_start >= num_channels {
-                return Err(AudioReadError::InvalidStartChannel(ch_start, num_channels));
+            fn classify(self) -> FpCategory {
+                self.0.classify()
+            }
+            fn mul_add(self, a: Self, b: Self) -> Self {
+                Self(self.0.mul_add(a.0, b.0))
+            }
+            fn powi(self, n: i32) -> Self {
+                Self(self.0.powi(n))
+            }
+            fn abs_sub(self, other: Self) -> Self {
+                Self((self.0 - other.0).max(0.0))
             }
-            if ch_count == 0 {
-                return Err(AudioReadError::InvalidNumChannels(0));
+            fn sin_cos(self) -> (Self, Self) {
+                let (sin, cos) = self.0.sin_cos();
+                (Self(sin), Self(cos))
             }
-            if ch_start + ch_count > num_channels {
-                return Err(AudioReadError::InvalidNumChannels(ch_count));
+            fn integer_decode(self) -> (u64, i16, i8) {
+                Float::integer_decode(self.0)
             }
         }
+    }
 
-        if let Some(buf) = &mut sample_buf {
-            buf.copy_interleaved_ref(decoded);
-            let packet_samples = buf.samples();
+    #[test]
+    fn test_sample_conversion_error() {
+        let result = audio_read::<_, Narrow>("test_data/test_1ch.wav", AudioReadConfig::default())
+            .map_err(AudioReadError::without_path);
+        assert!(matches!(result, Err(AudioReadError::SampleConversion)));
 
-            let mut pos = current_sample.unwrap_or(0);
+        // the first frames are small enough to be converted
+        let config = AudioReadConfig {
+            stop: Position::Frame(1),
+            ..Default::default()
+        };
+        let data: AudioData<Narrow> = audio_read("test_data/test_1ch.wav", config).unwrap();
+        assert_eq!(data.num_frames, 1);
+    }
 
-            // Determine channel range to extract
-            let ch_start = start_channel.unwrap_or(0);
-            let ch_count = config.num_channels.unwrap_or(num_channels - ch_start);
-            let ch_end = ch_start + ch_count;
+    #[test]
+    fn test_stride_frames() {
+        let plain: AudioData<f32> =
+            audio_read("test_data/test_4ch.wav", AudioReadConfig::default()).unwrap();
+        let config = AudioReadConfig {
+            start: Position::Frame(5),
+            stride_frames: Some(10),
+            ..Default::default()
+        };
+        let strided: AudioData<f32> = audio_read("test_data/test_4ch.wav", config).unwrap();
+        assert_eq!(strided.num_channels, plain.num_channels);
+        assert_eq!(strided.num_frames, (plain.num_frames - 5).div_ceil(10));
+        for (n, frame) in strided.frames().enumerate() {
+            assert_eq!(
+                frame,
+                &plain.interleaved_samples[(5 + n * 10) * 4..(6 + n * 10) * 4]
+            );
+        }
 
-            // Calculate frames using the ORIGINAL channel count from the file
-            let frames = packet_samples.len() / num_channels;
+        let config = AudioReadConfig {
+            stride_frames: Some(0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            audio_read::<_, f32>("test_data/test_4ch.wav", config)
+                .map_err(AudioReadError::without_path),
+            Err(AudioReadError::InvalidStride(0))
+        ));
+    }
 
-            // Process all frames, extracting only the requested channel range
-            for frame_idx in 0..frames {
-                // Check if we've reached the end frame
-                if let Some(end) = end_frame
-                    && pos >= end as u64
-                {
-                    let num_frames = samples.len() / ch_count;
-                    return Ok(AudioData {
-                        sample_rate,
-                        num_channels: ch_count,
-                        num_frames,
-                        interleaved_samples: samples,
-                    });
-                }
+    #[test]
+    fn test_read_channel() {
+        let data: AudioData<f32> =
+            audio_read("test_data/test_4ch.wav", AudioReadConfig::default()).unwrap();
 
-                // Start collecting samples once we reach start_frame
-                if pos >= start_frame as u64 {
-                    // Extract the selected channel range from this frame
-                    // When ch_start=0 and ch_count=num_channels, this extracts all channels
-                    for ch in ch_start..ch_end {
-                        let sample_idx = frame_idx * num_channels + ch;
-                        samples.push(F::from(packet_samples[sample_idx]).unwrap());
-                    }
-                }
+        let (receiver, handle) =
+            audio_read_channel::<_, f32>("test_data/test_4ch.wav", 2, AudioReadConfig::default());
+        let mut samples = Vec::new();
+        for chunk in receiver {
+            let chunk = chunk.unwrap();
+            assert_eq!(chunk.len() % 4, 0);
+            samples.extend(chunk);
+        }
+        handle.join().unwrap();
+        assert_eq!(samples, data.interleaved_samples);
 
-                pos += 1;
-            }
+        // the error is the last item
+        let (receiver, handle) =
+            audio_read_channel::<_, f32>("test_data/missing.wav", 2, AudioReadConfig::default());
+        let items: Vec<_> = receiver.into_iter().collect();
+        handle.join().unwrap();
+        assert_eq!(items.len(), 1);
+        assert!(matches!(
+            items
+                .into_iter()
+                .next()
+                .unwrap()
+                .map_err(AudioReadError::without_path),
+            Err(AudioReadError::FileError(_))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "write")]
+    fn test_mono_select() {
+        // loud left and nearly silent right channel
+        let samples: Vec<f32> = (0..4800)
+            .flat_map(|n| {
+                let sample = crate::test_util::test_tone_sample(48000, 1000.0, n, 0);
+                [sample, sample * 0.01]
+            })
+            .collect();
+        let block = AudioBlockInterleavedView::from_slice(&samples, 2, 4800);
+        let config = crate::AudioWriteConfig {
+            sample_format: crate::writer::WriteSampleFormat::Float32,
+            ..Default::default()
+        };
+        crate::audio_write("tmp13.wav", block, 48000, config).unwrap();
+        let read = |mono, start_channel| {
+            let config = AudioReadConfig {
+                mono: Some(mono),
+                start_channel,
+                ..Default::default()
+            };
+            audio_read::<_, f32>("tmp13.wav", config).map_err(AudioReadError::without_path)
+        };
+        let left: Vec<f32> = samples.iter().step_by(2).copied().collect();
+        let right: Vec<f32> = samples.iter().skip(1).step_by(2).copied().collect();
+
+        let loudest = read(MonoSelect::HighestEnergy, None).unwrap();
+        assert_eq!(loudest.num_channels, 1);
+        assert_eq!(loudest.num_frames, 4800);
+        assert_eq!(loudest.interleaved_samples, left);
+
+        // the loudest of the selected channels
+        let loudest = read(MonoSelect::HighestEnergy, Some(1)).unwrap();
+        assert_eq!(loudest.interleaved_samples, right);
 
-            // Update our position tracker
-            current_sample = Some(pos);
+        let channel = read(MonoSelect::Channel(1), None).unwrap();
+        assert_eq!(channel.interleaved_samples, right);
+
+        let downmix = read(MonoSelect::Downmix, None).unwrap();
+        for (n, sample) in downmix.interleaved_samples.iter().enumerate() {
+            assert_eq!(*sample, (left[n] + right[n]) / 2.0);
+        }
+
+        // after the channel selection, and clamped to full scale
+        let weighted = read(MonoSelect::Weighted(vec![0.5]), Some(1)).unwrap();
+        for (n, sample) in weighted.interleaved_samples.iter().enumerate() {
+            assert_eq!(*sample, right[n] * 0.5);
+        }
+        let weighted = read(MonoSelect::Weighted(vec![4.0, 0.0]), None).unwrap();
+        for (n, sample) in weighted.interleaved_samples.iter().enumerate() {
+            assert_eq!(*sample, (left[n] * 4.0).clamp(-1.0, 1.0));
         }
+        assert!(weighted.interleaved_samples.contains(&1.0));
+
+        let invalid = read(MonoSelect::Channel(2), None);
+        let invalid_weights = read(MonoSelect::Weighted(vec![1.0; 3]), None);
+        std::fs::remove_file("tmp13.wav").unwrap();
+        assert!(matches!(
+            invalid,
+            Err(AudioReadError::InvalidMonoChannel(2, 2))
+        ));
+        assert!(matches!(
+            invalid_weights,
+            Err(AudioReadError::InvalidMonoWeights(3, 2))
+        ));
     }
 
-    let ch_start = start_channel.unwrap_or(0);
-    let ch_count = config.num_channels.unwrap_or(num_channels - ch_start);
-    let num_frames = samples.len() / ch_count;
+    #[test]
+    fn test_read_packets() {
+        let data: AudioData<f32> =
+            audio_read("test_data/test_1ch.flac", AudioReadConfig::default()).unwrap();
+        let packets: Vec<PacketInfo<f32>> =
+            audio_read_packets("test_data/test_1ch.flac", AudioReadConfig::default())
+                .collect::<Result<_, _>>()
+                .unwrap();
+        assert!(packets.len() > 1);
+        assert_eq!(packets[0].frame_start, 0);
+        for pair in packets.windows(2) {
+            assert!(pair[1].ts > pair[0].ts);
+            assert_eq!(
+                pair[1].frame_start,
+                pair[0].frame_start + pair[0].num_frames
+            );
+        }
+        let samples: Vec<f32> = packets.into_iter().flat_map(|p| p.samples).collect();
+        assert_eq!(samples, data.interleaved_samples);
 
-    Ok(AudioData {
-        sample_rate,
-        num_channels: ch_count,
-        num_frames,
-        interleaved_samples: samples,
-    })
-}
+        // positions are relative to the start of the file
+        let config = AudioReadConfig {
+            start: Position::Frame(5000),
+            ..Default::default()
+        };
+        let first = audio_read_packets::<_, f32>("test_data/test_1ch.flac", config)
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.frame_start, 5000);
+        assert_eq!(first.samples[0], data.interleaved_samples[5000]);
 
-#[cfg(test)]
-mod tests {
-    use std::time::Duration;
+        let mut missing = audio_read_packets::<_, f32>("test_data/missing.wav", Default::default());
+        assert!(missing.next().unwrap().is_err());
+        assert!(missing.next().is_none());
+    }
 
-    use audio_blocks::AudioBlock;
+    #[test]
+    fn test_gain_envelope() {
+        let read = |gain_envelope| {
+            let config = AudioReadConfig {
+                start: Position::Frame(1000),
+                stop: Position::Frame(2000),
+                gain_envelope,
+                ..Default::default()
+            };
+            audio_read::<_, f64>("test_data/test_1ch.wav", config)
+                .map_err(AudioReadError::without_path)
+        };
+        let plain = read(None).unwrap().interleaved_samples;
 
-    use super::*;
+        // ramp from 0 at frame 100 to 1 at frame 300 of the output
+        let ramped = read(Some(vec![(100, 0.0), (300, 1.0)]))
+            .unwrap()
+            .interleaved_samples;
+        assert_eq!(ramped[50], 0.0);
+        assert_eq!(ramped[100], 0.0);
+        approx::assert_abs_diff_eq!(ramped[200], plain[200] * 0.5, epsilon = 1e-12);
+        approx::assert_abs_diff_eq!(ramped[250], plain[250] * 0.75, epsilon = 1e-12);
+        assert_eq!(ramped[300..], plain[300..]);
+
+        assert!(matches!(
+            read(Some(vec![(300, 1.0), (100, 0.0)])),
+            Err(AudioReadError::UnsortedGainEnvelope(300, 100))
+        ));
+    }
 
     #[test]
-    fn test_samples_selection() {
-        let data1: AudioData<f32> =
+    fn test_cover_art() {
+        // FLAC PICTURE block with a front cover, inserted after the STREAMINFO block
+        let mut picture = Vec::new();
+        picture.extend(3u32.to_be_bytes());
+        picture.extend(9u32.to_be_bytes());
+        picture.extend(b"image/png");
+        picture.extend(5u32.to_be_bytes());
+        picture.extend(b"cover");
+        picture.extend([1u32, 1, 24, 0].iter().flat_map(|v| v.to_be_bytes()));
+        picture.extend(4u32.to_be_bytes());
+        picture.extend(b"\x89PNG");
+
+        let flac = std::fs::read("test_data/test_1ch.flac").unwrap();
+        let streaminfo_end = 4 + 4 + 34;
+        // the STREAMINFO block is not the last one, so the flag is kept
+        assert_eq!(flac[4] & 0x80, 0);
+        let mut file = flac[..streaminfo_end].to_vec();
+        file.push(6);
+        file.extend(&(picture.len() as u32).to_be_bytes()[1..]);
+        file.extend(&picture);
+        file.extend(&flac[streaminfo_end..]);
+        std::fs::write("tmp_cover.flac", &file).unwrap();
+
+        let cover_art = audio_read_cover_art("tmp_cover.flac").unwrap();
+        assert_eq!(
+            cover_art,
+            vec![CoverArt {
+                media_type: "image/png".to_string(),
+                data: b"\x89PNG".to_vec(),
+                usage: Some("FrontCover".to_string()),
+            }]
+        );
+        // the audio is still readable
+        let data: AudioData<f32> =
+            audio_read("tmp_cover.flac", AudioReadConfig::default()).unwrap();
+        assert_eq!(data.tags.get("title"), Some("Test Signal"));
+        std::fs::remove_file("tmp_cover.flac").unwrap();
+
+        assert!(
+            audio_read_cover_art("test_data/test_1ch.flac")
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            audio_read_cover_art("test_data/test_4ch.wav")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_tags() {
+        // vorbis comments, keys are stored with mixed case
+        let flac: AudioData<f32> =
+            audio_read("test_data/test_1ch.flac", AudioReadConfig::default()).unwrap();
+        assert_eq!(flac.tags.get("title"), Some("Test Signal"));
+        assert_eq!(flac.tags.get("ARTIST"), Some("neodsp"));
+        assert_eq!(flac.tags.get("album"), Some("audio-io fixtures"));
+        assert_eq!(flac.tags.get("TPE1"), Some("neodsp"));
+        assert_eq!(flac.tags.encoder(), Some("audio-io fixture generator"));
+        assert_eq!(flac.tags.created(), Some("2025-10-27"));
+        assert!(
+            flac.tags
+                .entries()
+                .contains(&("artist".to_string(), "neodsp".to_string()))
+        );
+
+        // ID3v2 frames in front of the FLAC stream
+        let id3: AudioData<f32> =
+            audio_read("test_data/test_id3.flac", AudioReadConfig::default()).unwrap();
+        assert_eq!(id3.container, Container::Flac);
+        assert_eq!(id3.num_frames, 4800);
+        assert_eq!(id3.tags.get("Title"), Some("ID3 Title"));
+        assert_eq!(id3.tags.get("artist"), Some("ID3 Artist"));
+        assert_eq!(id3.tags.get("IPRD"), Some("ID3 Album"));
+        assert_eq!(id3.tags.get("date"), None);
+        assert_eq!(id3.tags.created(), None);
+        assert!(
+            id3.tags
+                .entries()
+                .contains(&("TPE1".to_string(), "ID3 Artist".to_string()))
+        );
+
+        let wav: AudioData<f32> =
             audio_read("test_data/test_1ch.wav", AudioReadConfig::default()).unwrap();
-        let block1 = data1.audio_block();
-        assert_eq!(data1.sample_rate, 48000);
-        assert_eq!(block1.num_frames(), 48000);
-        assert_eq!(block1.num_channels(), 1);
+        assert!(wav.tags.is_empty());
 
-        let data2: AudioData<f32> = audio_read(
-            "test_data/test_1ch.wav",
-            AudioReadConfig {
-                start: Position::Frame(1100),
-                stop: Position::Frame(1200),
-                ..Default::default()
-            },
-        )
-        .unwrap();
-        let block2 = data2.audio_block();
-        assert_eq!(data2.sample_rate, 48000);
-        assert_eq!(block2.num_frames(), 100);
-        assert_eq!(block2.num_channels(), 1);
-        assert_eq!(block1.raw_data()[1100..1200], block2.raw_data()[..]);
+        // the same tags without decoding
+        assert_eq!(
+            audio_read_tags("test_data/test_1ch.flac").unwrap(),
+            flac.tags
+        );
+        assert_eq!(
+            audio_read_tags("test_data/test_id3.flac").unwrap(),
+            id3.tags
+        );
+        assert!(
+            audio_read_tags("test_data/test_1ch.wav")
+                .unwrap()
+                .is_empty()
+        );
+        assert!(matches!(
+            audio_read_tags("test_data/missing.wav").map_err(AudioReadError::without_path),
+            Err(AudioReadError::FileError(_))
+        ));
     }
 
     #[test]
-    fn test_time_selection() {
-        let data1: AudioData<f32> =
+    fn test_probe_info() {
+        for path in ["test_data/test_1ch.wav", "test_data/test_4ch.wav"] {
+            let data: AudioData<f32> = audio_read(path, AudioReadConfig::default()).unwrap();
+            let info = audio_probe_info(path).unwrap();
+            assert_eq!(info.container, Container::Wav);
+            assert_eq!(info.codec, "PCM");
+            assert_eq!(info.sample_rate, Some(data.sample_rate));
+            assert_eq!(info.num_channels, Some(data.num_channels));
+            assert_eq!(info.num_frames, Some(data.num_frames));
+            approx::assert_abs_diff_eq!(
+                info.duration.unwrap().as_secs_f64(),
+                data.num_frames as f64 / data.sample_rate as f64,
+                epsilon = 1e-9
+            );
+        }
+
+        let info = audio_probe_info("test_data/test_1ch.flac").unwrap();
+        assert_eq!(info.container, Container::Flac);
+        assert_eq!(info.codec, "FLAC");
+
+        assert!(matches!(
+            audio_probe_info("test_data/missing.wav").map_err(AudioReadError::without_path),
+            Err(AudioReadError::FileError(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_w64() {
+        let wav: AudioData<f32> =
             audio_read("test_data/test_1ch.wav", AudioReadConfig::default()).unwrap();
-        let block1 = data1.audio_block();
-        assert_eq!(data1.sample_rate, 48000);
-        assert_eq!(block1.num_frames(), 48000);
-        assert_eq!(block1.num_channels(), 1);
+        let w64: AudioData<f32> =
+            audio_read("test_data/test_1ch.w64", AudioReadConfig::default()).unwrap();
+        assert_eq!(w64.container, Container::W64);
+        assert_eq!(w64.sample_rate, wav.sample_rate);
+        assert_eq!(w64.source_num_frames, Some(48000));
+        assert_eq!(w64.interleaved_samples, wav.interleaved_samples);
 
-        let data2: AudioData<f32> = audio_read(
+        let config = AudioReadConfig {
+            start: Position::Frame(1100),
+            stop: Position::Frame(1200),
+            ..Default::default()
+        };
+        let w64: AudioData<f32> = audio_read("test_data/test_1ch.w64", config).unwrap();
+        assert_eq!(w64.interleaved_samples, wav.interleaved_samples[1100..1200]);
+
+        // ADPCM data is not supported
+        let mut bytes = std::fs::read("test_data/test_1ch.w64").unwrap();
+        bytes[64..66].copy_from_slice(&2u16.to_le_bytes());
+        std::fs::write("tmp_adpcm.w64", bytes).unwrap();
+        let result = audio_read::<_, f32>("tmp_adpcm.w64", AudioReadConfig::default())
+            .map_err(AudioReadError::without_path);
+        std::fs::remove_file("tmp_adpcm.w64").unwrap();
+        assert!(matches!(
+            result,
+            Err(AudioReadError::UnsupportedContainer(e)) if e.starts_with("Wave64")
+        ));
+
+        // a data chunk that claims to extend past the end of any file
+        let mut bytes = std::fs::read("test_data/test_1ch.w64").unwrap();
+        let fmt_size = u64::from_le_bytes(bytes[56..64].try_into().unwrap()) as usize;
+        let data_chunk = 40 + fmt_size.next_multiple_of(8);
+        assert_eq!(&bytes[data_chunk..data_chunk + 4], b"data");
+        bytes[data_chunk + 16..data_chunk + 24].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            audio_read_from_bytes::<f32>(bytes, None, AudioReadConfig::default()),
+            Err(AudioReadError::EncodingError(Error::DecodeError(_)))
+        ));
+    }
+
+    #[test]
+    fn test_read_w64_extensible() {
+        let wav: AudioData<f32> = audio_read(
             "test_data/test_1ch.wav",
             AudioReadConfig {
-                start: Position::Time(Duration::from_secs_f32(0.5)),
-                stop: Position::Time(Duration::from_secs_f32(0.6)),
+                stop: Position::Frame(4800),
                 ..Default::default()
             },
         )
         .unwrap();
+        let w64: AudioData<f32> =
+            audio_read("test_data/test_24in32.w64", AudioReadConfig::default()).unwrap();
+        assert_eq!(w64.num_channels, 2);
+        assert_eq!(w64.source_format.unwrap().channel_mask, Some(0x600));
+        let left: Vec<f32> = w64.interleaved_samples.iter().step_by(2).copied().collect();
+        let right: Vec<f32> = w64.interleaved_samples[1..]
+            .iter()
+            .step_by(2)
+            .copied()
+            .collect();
+        assert_eq!(left, wav.interleaved_samples);
+        assert!(right.iter().zip(&left).all(|(r, l)| *r == -l));
 
-        let block2 = data2.audio_block();
-        assert_eq!(data2.sample_rate, 48000);
-        assert_eq!(block2.num_frames(), 4800);
-        assert_eq!(block2.num_channels(), 1);
-        assert_eq!(block1.raw_data()[24000..28800], block2.raw_data()[..]);
+        // more valid bits than the container has
+        let mut bytes = std::fs::read("test_data/test_24in32.w64").unwrap();
+        bytes[82..84].copy_from_slice(&33u16.to_le_bytes());
+        assert!(matches!(
+            audio_read_from_bytes::<f32>(bytes, None, AudioReadConfig::default()),
+            Err(AudioReadError::EncodingError(Error::DecodeError(_)))
+        ));
     }
 
     #[test]
-    fn test_channel_selection() {
-        let data1: AudioData<f32> =
-            audio_read("test_data/test_4ch.wav", AudioReadConfig::default()).unwrap();
-        let block1 = data1.audio_block();
-        assert_eq!(data1.sample_rate, 48000);
-        assert_eq!(block1.num_frames(), 48000);
-        assert_eq!(block1.num_channels(), 4);
+    fn test_read_unseekable() {
+        let bytes = std::fs::read("test_data/test_1ch.wav").unwrap();
+        let config = || AudioReadConfig {
+            start: Position::Frame(30000),
+            ..Default::default()
+        };
 
-        let data2: AudioData<f32> = audio_read(
-            "test_data/test_4ch.wav",
-            AudioReadConfig {
-                start_channel: Some(1),
-                num_channels: Some(2),
-                ..Default::default()
-            },
-        )
-        .unwrap();
+        let data: AudioData<f32> =
+            read_unseekable(bytes.as_slice(), Some("wav"), bytes.len(), config()).unwrap();
+        let reference: AudioData<f32> = audio_read("test_data/test_1ch.wav", config()).unwrap();
+        assert_eq!(data.num_frames, 18000);
+        assert_eq!(data.interleaved_samples, reference.interleaved_samples);
 
-        let block2 = data2.audio_block();
-        assert_eq!(data2.sample_rate, 48000);
-        assert_eq!(block2.num_frames(), 48000);
-        assert_eq!(block2.num_channels(), 2);
+        assert!(matches!(
+            read_unseekable::<f32>(bytes.as_slice(), None, bytes.len() - 1, config()),
+            Err(AudioReadError::InputTooLarge(_))
+        ));
+        // no limit
+        let data: AudioData<f32> =
+            read_unseekable(bytes.as_slice(), None, usize::MAX, config()).unwrap();
+        assert_eq!(data.num_frames, 18000);
+    }
 
-        // Verify we extracted channels 1 and 2 (skipping channel 0 and 3)
-        for frame in 0..10 {
-            assert_eq!(block2.sample(0, frame), block1.sample(1, frame));
-            assert_eq!(block2.sample(1, frame), block1.sample(2, frame));
-        }
+    #[test]
+    fn test_deadline() {
+        let config = |deadline| AudioReadConfig {
+            deadline,
+            ..Default::default()
+        };
+        let result = audio_read::<_, f32>("test_data/test_4ch.wav", config(Some(Instant::now())))
+            .map_err(AudioReadError::without_path);
+        assert!(matches!(result, Err(AudioReadError::TimedOut)));
+
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let data: AudioData<f32> =
+            audio_read("test_data/test_4ch.wav", config(Some(deadline))).unwrap();
+        assert_eq!(data.num_frames, 48000);
+    }
+
+    #[test]
+    fn test_error_path() {
+        let Err(err) = audio_read::<_, f32>("test_data/missing.wav", AudioReadConfig::default())
+        else {
+            panic!("missing file was read");
+        };
+        assert_eq!(err.path(), Some(Path::new("test_data/missing.wav")));
+        assert!(err.to_string().starts_with("test_data/missing.wav: "));
+        assert!(matches!(err.without_path(), AudioReadError::FileError(_)));
     }
 
     #[test]
@@ -383,7 +5947,9 @@ mod tests {
                 stop: Position::Frame(99),
                 ..Default::default()
             },
-        ) {
+        )
+        .map_err(AudioReadError::without_path)
+        {
             Err(AudioReadError::EndFrameLargerThanStartFrame(_, _)) => (),
             _ => panic!(),
         }
@@ -395,18 +5961,38 @@ mod tests {
                 stop: Position::Time(Duration::from_secs_f32(0.5)),
                 ..Default::default()
             },
-        ) {
+        )
+        .map_err(AudioReadError::without_path)
+        {
             Err(AudioReadError::EndFrameLargerThanStartFrame(_, _)) => (),
             _ => panic!(),
         }
 
+        match audio_read::<_, f32>(
+            "test_data/test_1ch.wav",
+            AudioReadConfig {
+                start: Position::Frame(10_000_000),
+                ..Default::default()
+            },
+        )
+        .map_err(AudioReadError::without_path)
+        {
+            Err(AudioReadError::StartBeyondEnd {
+                start: 10_000_000,
+                length: 48000,
+            }) => (),
+            _ => panic!(),
+        }
+
         match audio_read::<_, f32>(
             "test_data/test_1ch.wav",
             AudioReadConfig {
                 start_channel: Some(1),
                 ..Default::default()
             },
-        ) {
+        )
+        .map_err(AudioReadError::without_path)
+        {
             Err(AudioReadError::InvalidStartChannel(_, _)) => (),
             _ => panic!(),
         }
@@ -417,7 +6003,9 @@ mod tests {
                 num_channels: Some(0),
                 ..Default::default()
             },
-        ) {
+        )
+        .map_err(AudioReadError::without_path)
+        {
             Err(AudioReadError::InvalidNumChannels(0)) => (),
             _ => panic!(),
         }
@@ -428,7 +6016,9 @@ mod tests {
                 num_channels: Some(2),
                 ..Default::default()
             },
-        ) {
+        )
+        .map_err(AudioReadError::without_path)
+        {
             Err(AudioReadError::InvalidNumChannels(2)) => (),
             _ => panic!(),
         }
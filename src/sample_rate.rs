@@ -0,0 +1,96 @@
+//! Validated sample rates.
+
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("invalid sample rate {0}, expected 1 to {max} Hz", max = SampleRate::MAX)]
+pub struct InvalidSampleRate(pub u32);
+
+/// Sample rate in Hz, which is never zero and at most [`SampleRate::MAX`].
+///
+/// The write functions take it directly, see [`IntoSampleRate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SampleRate(u32);
+
+impl SampleRate {
+    pub const SR_8000: Self = Self(8000);
+    pub const SR_16000: Self = Self(16000);
+    pub const SR_22050: Self = Self(22050);
+    pub const SR_44100: Self = Self(44100);
+    pub const SR_48000: Self = Self(48000);
+    pub const SR_88200: Self = Self(88200);
+    pub const SR_96000: Self = Self(96000);
+    pub const SR_192000: Self = Self(192000);
+
+    /// Highest accepted sample rate (8 times 96 kHz)
+    pub const MAX: u32 = 768_000;
+
+    pub fn get(self) -> u32 {
+        self.0
+    }
+}
+
+impl TryFrom<u32> for SampleRate {
+    type Error = InvalidSampleRate;
+
+    fn try_from(sample_rate: u32) -> Result<Self, Self::Error> {
+        if sample_rate == 0 || sample_rate > Self::MAX {
+            return Err(InvalidSampleRate(sample_rate));
+        }
+        Ok(Self(sample_rate))
+    }
+}
+
+impl From<SampleRate> for u32 {
+    fn from(sample_rate: SampleRate) -> Self {
+        sample_rate.0
+    }
+}
+
+/// Sample rate argument of the write functions, a `u32` in Hz or a [`SampleRate`].
+///
+/// Unlike `Into<u32>`, it is only implemented for `u32` among the integer types, so an integer
+/// literal is still inferred as `u32`.
+pub trait IntoSampleRate {
+    /// Sample rate in Hz
+    fn into_hz(self) -> u32;
+}
+
+impl IntoSampleRate for u32 {
+    fn into_hz(self) -> u32 {
+        self
+    }
+}
+
+impl IntoSampleRate for SampleRate {
+    fn into_hz(self) -> u32 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for SampleRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} Hz", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(SampleRate::try_from(48000), Ok(SampleRate::SR_48000));
+        assert_eq!(u32::from(SampleRate::SR_44100), 44100);
+        assert_eq!(SampleRate::SR_96000.get(), 96000);
+        assert_eq!(SampleRate::try_from(0), Err(InvalidSampleRate(0)));
+        assert_eq!(
+            SampleRate::try_from(SampleRate::MAX + 1),
+            Err(InvalidSampleRate(SampleRate::MAX + 1))
+        );
+        assert!(SampleRate::try_from(SampleRate::MAX).is_ok());
+        assert_eq!(SampleRate::SR_22050.to_string(), "22050 Hz");
+        assert_eq!(SampleRate::SR_88200.into_hz(), 88200);
+        assert_eq!(44100.into_hz(), 44100);
+    }
+}
@@ -0,0 +1,171 @@
+//! Metadata tags, unified across the tag formats of the containers.
+
+/// Keys that name the same field in different tag formats, the first one is the canonical key.
+///
+/// Vorbis comments, RIFF INFO chunks and ID3v2 frames (v2.3/v2.4 and v2.2).
+const SYNONYMS: &[&[&str]] = &[
+    &["TITLE", "INAM", "TIT2", "TT2"],
+    &["ARTIST", "IART", "TPE1", "TP1"],
+    &["ALBUM", "IPRD", "TALB", "TAL"],
+    &["ALBUMARTIST", "TPE2", "TP2"],
+    &["COMPOSER", "TCOM", "TCM"],
+    &[
+        "DATE",
+        "ICRD",
+        "TDRC",
+        "TYER",
+        "TYE",
+        "YEAR",
+        "CREATION_TIME",
+    ],
+    &["GENRE", "IGNR", "TCON", "TCO"],
+    &["COMMENT", "ICMT", "COMM", "COM"],
+    &["TRACKNUMBER", "ITRK", "TRCK", "TRK"],
+    &["COPYRIGHT", "ICOP", "TCOP", "TCR"],
+    &["ENCODER", "ISFT", "TSSE", "TSS", "SOFTWARE"],
+];
+
+/// Metadata tags of a file (e.g. Vorbis comments, ID3v2 frames or RIFF INFO chunks)
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tags {
+    entries: Vec<(String, String)>,
+}
+
+/// Picture embedded in the metadata, e.g. the album cover, see
+/// [`audio_read_cover_art`](crate::reader::audio_read_cover_art)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CoverArt {
+    /// Media type of the image (e.g. `"image/jpeg"`)
+    pub media_type: String,
+    /// The encoded image
+    pub data: Vec<u8>,
+    /// What the picture shows (e.g. `"FrontCover"`), if the tag format stores it
+    pub usage: Option<String>,
+}
+
+impl Tags {
+    /// All tags with the keys as they are stored in the file, in the order they were read
+    pub fn entries(&self) -> &[(String, String)] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Value of the first tag matching `key`.
+    ///
+    /// Keys are compared case-insensitively, and common keys of other tag formats are matched
+    /// as well, so `get("artist")` finds `ARTIST`, `IART` and `TPE1`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        let synonyms = SYNONYMS
+            .iter()
+            .find(|synonyms| synonyms.iter().any(|s| s.eq_ignore_ascii_case(key)))
+            .copied()
+            .unwrap_or(&[]);
+
+        self.entries
+            .iter()
+            .find(|(k, _)| {
+                k.eq_ignore_ascii_case(key) || synonyms.iter().any(|s| s.eq_ignore_ascii_case(k))
+            })
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.get("TITLE")
+    }
+
+    pub fn artist(&self) -> Option<&str> {
+        self.get("ARTIST")
+    }
+
+    pub fn album(&self) -> Option<&str> {
+        self.get("ALBUM")
+    }
+
+    pub fn genre(&self) -> Option<&str> {
+        self.get("GENRE")
+    }
+
+    /// Number of the track on the album, without the total of `3/12`
+    pub fn track_number(&self) -> Option<u32> {
+        let value = self.get("TRACKNUMBER")?;
+        value.split('/').next()?.trim().parse().ok()
+    }
+
+    /// Software that encoded the file (e.g. `Lavf58.76.100`)
+    pub fn encoder(&self) -> Option<&str> {
+        self.get("ENCODER")
+    }
+
+    /// Date the file or the recording was created, as it is stored in the file
+    pub fn created(&self) -> Option<&str> {
+        self.get("DATE")
+    }
+
+    pub(crate) fn push(&mut self, key: String, value: String) {
+        self.entries.push((key, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get() {
+        let mut tags = Tags::default();
+        tags.push("TPE1".to_string(), "id3 artist".to_string());
+        tags.push("title".to_string(), "vorbis title".to_string());
+        tags.push("CUSTOM".to_string(), "custom".to_string());
+
+        assert_eq!(tags.get("Artist"), Some("id3 artist"));
+        assert_eq!(tags.get("IART"), Some("id3 artist"));
+        assert_eq!(tags.get("TITLE"), Some("vorbis title"));
+        assert_eq!(tags.get("TIT2"), Some("vorbis title"));
+        assert_eq!(tags.get("custom"), Some("custom"));
+        assert_eq!(tags.get("album"), None);
+        assert_eq!(tags.entries().len(), 3);
+        assert_eq!(tags.encoder(), None);
+        assert_eq!(tags.title(), Some("vorbis title"));
+        assert_eq!(tags.artist(), Some("id3 artist"));
+        assert_eq!(tags.album(), None);
+    }
+
+    #[test]
+    fn test_track_number() {
+        let mut tags = Tags::default();
+        assert_eq!(tags.track_number(), None);
+        tags.push("TRCK".to_string(), "3/12".to_string());
+        assert_eq!(tags.track_number(), Some(3));
+
+        let mut tags = Tags::default();
+        tags.push("tracknumber".to_string(), " 7".to_string());
+        assert_eq!(tags.track_number(), Some(7));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let mut tags = Tags::default();
+        tags.push("TITLE".to_string(), "Song".to_string());
+        tags.push("TPE1".to_string(), "Band".to_string());
+        let json = serde_json::to_string(&tags).unwrap();
+        assert_eq!(serde_json::from_str::<Tags>(&json).unwrap(), tags);
+    }
+
+    #[test]
+    fn test_provenance() {
+        let mut tags = Tags::default();
+        tags.push("ISFT".to_string(), "Lavf58.76.100".to_string());
+        tags.push(
+            "creation_time".to_string(),
+            "2024-05-01T12:00:00Z".to_string(),
+        );
+        assert_eq!(tags.encoder(), Some("Lavf58.76.100"));
+        assert_eq!(tags.created(), Some("2024-05-01T12:00:00Z"));
+    }
+}
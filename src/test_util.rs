@@ -0,0 +1,115 @@
+//! Helpers to write test files with a known signal, so tests can assert exact sample values,
+//! and to compare decoded audio.
+
+#[cfg(all(test, feature = "read", feature = "write"))]
+use std::path::Path;
+
+#[cfg(all(test, feature = "read", feature = "write"))]
+use audio_blocks::AudioBlockInterleavedView;
+#[cfg(feature = "read")]
+use num::Float;
+
+#[cfg(feature = "read")]
+use crate::reader::AudioData;
+#[cfg(all(test, feature = "read", feature = "write"))]
+use crate::writer::{AudioWriteConfig, WriteSampleFormat, audio_write};
+
+/// Assert that two decoded audio files are equal within `epsilon`.
+///
+/// Compares the sample rate, the number of channels and frames and every sample. Panics with
+/// the first frame and channel that differs.
+#[cfg(feature = "read")]
+#[track_caller]
+pub fn assert_audio_eq<F: Float + std::fmt::Debug>(a: &AudioData<F>, b: &AudioData<F>, epsilon: F) {
+    assert_eq!(a.sample_rate, b.sample_rate, "sample rates differ");
+    assert_eq!(a.num_channels, b.num_channels, "number of channels differs");
+    assert_eq!(a.num_frames, b.num_frames, "number of frames differs");
+    for (frame, (frame_a, frame_b)) in a.frames().zip(b.frames()).enumerate() {
+        for (channel, (sample_a, sample_b)) in frame_a.iter().zip(frame_b).enumerate() {
+            if (*sample_a - *sample_b).abs() > epsilon || sample_a.is_nan() != sample_b.is_nan() {
+                panic!(
+                    "samples differ at frame {frame}, channel {channel}: {sample_a:?} != {sample_b:?} (epsilon {epsilon:?})"
+                );
+            }
+        }
+    }
+}
+
+/// Sample of the test tone at `frame` in `channel`.
+///
+/// Every channel is a sine with `freq`, channel `c` has an amplitude of `0.5 / (c + 1)`,
+/// so the channels can be told apart.
+#[cfg(all(test, feature = "read", feature = "write"))]
+pub(crate) fn test_tone_sample(sample_rate: u32, freq: f64, frame: usize, channel: usize) -> f32 {
+    let phase = 2.0 * std::f64::consts::PI * freq * frame as f64 / sample_rate as f64;
+    (0.5 / (channel + 1) as f64 * phase.sin()) as f32
+}
+
+/// Write the test tone as 32-bit float wav file, which is read back without any loss.
+#[cfg(all(test, feature = "read", feature = "write"))]
+pub(crate) fn write_test_tone<P: AsRef<Path>>(
+    path: P,
+    sample_rate: u32,
+    num_channels: u16,
+    freq: f64,
+    num_frames: usize,
+) {
+    let samples: Vec<f32> = (0..num_frames)
+        .flat_map(|frame| {
+            (0..num_channels as usize)
+                .map(move |channel| test_tone_sample(sample_rate, freq, frame, channel))
+        })
+        .collect();
+    let block = AudioBlockInterleavedView::from_slice(&samples, num_channels, num_frames);
+    let config = AudioWriteConfig {
+        sample_format: WriteSampleFormat::Float32,
+        ..Default::default()
+    };
+    audio_write(path, block, sample_rate, config).unwrap();
+}
+
+#[cfg(all(test, feature = "read"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "write")]
+    fn test_tone_round_trip() {
+        write_test_tone("tmp_tone.wav", 44100, 3, 1000.0, 4410);
+        let data: crate::AudioData<f32> =
+            crate::audio_read("tmp_tone.wav", Default::default()).unwrap();
+        std::fs::remove_file("tmp_tone.wav").unwrap();
+
+        assert_eq!(data.sample_rate, 44100);
+        assert_eq!(data.num_channels, 3);
+        assert_eq!(data.num_frames, 4410);
+        for (frame, samples) in data.frames().enumerate() {
+            for (channel, sample) in samples.iter().enumerate() {
+                assert_eq!(*sample, test_tone_sample(44100, 1000.0, frame, channel));
+            }
+        }
+    }
+
+    #[test]
+    fn test_assert_audio_eq() {
+        let a = AudioData {
+            interleaved_samples: vec![0.0f32, 0.5, 0.25, -0.5],
+            sample_rate: 48000,
+            num_channels: 2,
+            num_frames: 2,
+            ..Default::default()
+        };
+        let mut b = a.clone();
+        b.interleaved_samples[3] = -0.5001;
+        assert_audio_eq(&a, &b, 1e-3);
+
+        let message = std::panic::catch_unwind(|| assert_audio_eq(&a, &b, 1e-5))
+            .unwrap_err()
+            .downcast::<String>()
+            .unwrap();
+        assert!(message.starts_with("samples differ at frame 1, channel 1"));
+
+        b.sample_rate = 44100;
+        assert!(std::panic::catch_unwind(|| assert_audio_eq(&a, &b, 1e-3)).is_err());
+    }
+}
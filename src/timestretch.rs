@@ -0,0 +1,167 @@
+//! Tempo change without pitch change for reading.
+//!
+//! The audio is stretched with WSOLA (waveform similarity overlap-add): Hann-windowed frames
+//! are taken from the input at the new tempo and added with 50 % overlap. Each frame is shifted
+//! by up to a quarter of its length to where it continues the previous frame best, found by
+//! the cross-correlation of the downmixed channels. All channels use the same shift, so the
+//! stereo image is kept.
+//!
+//! The search correlates a whole frame for every shift, which makes stretching much slower
+//! than decoding.
+
+use num::Float;
+
+/// Length of the frames, long enough to contain a few periods of low voices
+const FRAME_SECONDS: f64 = 0.03;
+
+/// Speeds that can be stretched, larger changes need too much memory or drop most of the input
+pub(crate) const SPEED_RANGE: std::ops::RangeInclusive<f64> = 0.1..=10.0;
+
+/// Stretch the interleaved `samples` to `round(num_frames / speed)` frames, where a `speed`
+/// of 0.5 plays at half the tempo.
+pub(crate) fn time_stretch<F: Float>(
+    samples: &[F],
+    num_channels: usize,
+    sample_rate: u32,
+    speed: f64,
+) -> Vec<F> {
+    let num_frames = samples.len() / num_channels.max(1);
+    let out_frames = (num_frames as f64 / speed).round() as usize;
+    if num_channels == 0 || num_frames == 0 {
+        return Vec::new();
+    }
+
+    let frame_len = ((FRAME_SECONDS * sample_rate as f64) as usize / 2 * 2).max(4);
+    let hop = frame_len / 2;
+    let tolerance = (frame_len / 4) as isize;
+    // periodic Hann window, which sums to one with an overlap of half the frame
+    let window: Vec<f64> = (0..frame_len)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / frame_len as f64).cos())
+        .collect();
+
+    let input: Vec<f64> = samples
+        .iter()
+        .map(|s| s.to_f64().filter(|s| s.is_finite()).unwrap_or(0.0))
+        .collect();
+    let mono: Vec<f64> = input
+        .chunks_exact(num_channels)
+        .map(|frame| frame.iter().sum())
+        .collect();
+    let mono_at = |frame: isize| {
+        usize::try_from(frame)
+            .ok()
+            .and_then(|frame| mono.get(frame))
+            .copied()
+            .unwrap_or(0.0)
+    };
+
+    let mut output = vec![0.0; out_frames * num_channels];
+    let mut weights = vec![0.0; out_frames];
+    // the first window starts one hop before the output, so every output frame is covered twice
+    let mut previous: Option<isize> = None;
+    for k in 0..out_frames / hop + 2 {
+        let out_start = (k * hop) as isize - hop as isize;
+        let nominal = (out_start as f64 * speed).round() as isize;
+        let start = match previous {
+            None => nominal,
+            Some(previous) => {
+                // the input that would follow the previous frame without a jump
+                let natural = previous + hop as isize;
+                (nominal - tolerance..=nominal + tolerance)
+                    .map(|start| {
+                        let similarity: f64 = (0..frame_len as isize)
+                            .map(|i| mono_at(natural + i) * mono_at(start + i))
+                            .sum();
+                        (start, similarity)
+                    })
+                    .fold((nominal, f64::NEG_INFINITY), |best, candidate| {
+                        if candidate.1 > best.1 {
+                            candidate
+                        } else {
+                            best
+                        }
+                    })
+                    .0
+            }
+        };
+        previous = Some(start);
+
+        for (i, w) in window.iter().enumerate() {
+            let (Ok(out), Ok(src)) = (
+                usize::try_from(out_start + i as isize),
+                usize::try_from(start + i as isize),
+            ) else {
+                continue;
+            };
+            if out >= out_frames {
+                break;
+            }
+            weights[out] += w;
+            if src >= num_frames {
+                continue;
+            }
+            for channel in 0..num_channels {
+                output[out * num_channels + channel] += w * input[src * num_channels + channel];
+            }
+        }
+    }
+
+    output
+        .chunks_exact(num_channels)
+        .zip(weights)
+        .flat_map(|(frame, weight)| {
+            let norm = if weight > 1e-3 { weight.recip() } else { 0.0 };
+            frame
+                .iter()
+                .map(move |s| F::from(s * norm).unwrap_or(F::zero()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zero_crossings(samples: &[f64]) -> usize {
+        samples
+            .windows(2)
+            .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+            .count()
+    }
+
+    #[test]
+    fn test_time_stretch() {
+        let sample_rate = 16000;
+        let input: Vec<f64> = (0..sample_rate)
+            .map(|n| (2.0 * std::f64::consts::PI * 440.0 * n as f64 / sample_rate as f64).sin())
+            .collect();
+
+        let output = time_stretch(&input, 1, sample_rate as u32, 0.5);
+        assert_eq!(output.len(), 2 * input.len());
+
+        // 2 crossings per period, ignoring the borders
+        let middle = &output[1600..output.len() - 1600];
+        let frequency =
+            zero_crossings(middle) as f64 / 2.0 / (middle.len() as f64 / sample_rate as f64);
+        assert!((frequency - 440.0).abs() < 5.0, "{frequency}");
+        let peak = middle.iter().fold(0.0f64, |max, s| max.max(s.abs()));
+        assert!(peak > 0.9 && peak < 1.1, "{peak}");
+    }
+
+    #[test]
+    fn test_time_stretch_channels() {
+        // the channels keep their order and levels
+        let input: Vec<f32> = (0..8000)
+            .flat_map(|n| {
+                let s = (n as f32 * 0.1).sin();
+                [s, 0.5 * s]
+            })
+            .collect();
+        let output = time_stretch(&input, 2, 8000, 2.0);
+        assert_eq!(output.len(), input.len() / 2);
+        for frame in output[1000..7000].chunks_exact(2) {
+            assert!((frame[1] - 0.5 * frame[0]).abs() < 1e-5);
+        }
+        assert!(time_stretch(&[0.0f32; 0], 2, 8000, 0.5).is_empty());
+    }
+}
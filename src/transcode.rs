@@ -0,0 +1,280 @@
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::reader::{AudioReadConfig, AudioReadError, SampleFormat, audio_read};
+use crate::writer::{AudioWriteConfig, AudioWriteError, ByteOrder, WriteSampleFormat, audio_write};
+
+#[derive(Debug, Error)]
+pub enum TranscodeError {
+    #[error("could not read input")]
+    ReadError(#[from] AudioReadError),
+    #[error("could not write output")]
+    WriteError(#[from] AudioWriteError),
+    #[error("source format {0:?} with {1} bits can not be written")]
+    UnsupportedSourceFormat(SampleFormat, u32),
+    #[error("the input does not report its source format")]
+    UnknownSourceFormat,
+}
+
+/// Read `input` and write it to `output`, keeping the sample rate of the input.
+///
+/// The read config selects the part and channels of the input that are written.
+/// With `preserve_source_format` in the write config, the output is written with the sample
+/// format and channel mask of the input, which fails if the writer does not support it.
+pub fn transcode<P1: AsRef<Path>, P2: AsRef<Path>>(
+    input: P1,
+    output: P2,
+    read_config: AudioReadConfig,
+    mut write_config: AudioWriteConfig,
+) -> Result<(), TranscodeError> {
+    let data = audio_read::<_, f32>(input, read_config)?;
+
+    if write_config.preserve_source_format {
+        let format = data
+            .source_format
+            .ok_or(TranscodeError::UnknownSourceFormat)?;
+        write_config.sample_format = match (format.sample_format, format.bits_per_sample) {
+            (SampleFormat::Int, 16) => WriteSampleFormat::Int16,
+            (SampleFormat::Float, 32) => WriteSampleFormat::Float32,
+            (sample_format, bits) => {
+                return Err(TranscodeError::UnsupportedSourceFormat(sample_format, bits));
+            }
+        };
+        write_config.channel_mask = format.channel_mask;
+    }
+
+    audio_write(output, data.audio_block(), data.sample_rate, write_config)?;
+    Ok(())
+}
+
+/// Copy `input` to `output` if it is already a WAV file in the format that would be written,
+/// otherwise [`transcode`] the whole file.
+///
+/// The file is copied byte by byte if all of these hold:
+/// - the input is a little endian WAV file with 16-bit integer or 32-bit float samples
+/// - `sample_format` of the write config is the format of the input, or
+///   `preserve_source_format` is set
+/// - the write config keeps the samples and the file as they are: no `extra_chunks`,
+///   `clamp_range`, `dither`, channel selection, `channel_mask`, `sanitize` or resampling, and
+///   little endian byte order
+///
+/// Returns `true` if the file was copied.
+pub fn audio_copy_or_transcode<P1: AsRef<Path>, P2: AsRef<Path>>(
+    input: P1,
+    output: P2,
+    mut write_config: AudioWriteConfig,
+) -> Result<bool, TranscodeError> {
+    if can_copy(input.as_ref(), &write_config) {
+        std::fs::copy(input, output).map_err(AudioWriteError::from)?;
+        if let Some(progress) = &mut write_config.progress {
+            progress(1.0);
+        }
+        return Ok(true);
+    }
+    transcode(input, output, AudioReadConfig::default(), write_config)?;
+    Ok(false)
+}
+
+/// Whether writing `input` with the config results in the same file, see
+/// [`audio_copy_or_transcode`].
+fn can_copy(input: &Path, config: &AudioWriteConfig) -> bool {
+    // inputs that hound can't read (e.g. RIFX or other containers) are transcoded
+    let Ok(spec) = hound::WavReader::open(input).map(|reader| reader.spec()) else {
+        return false;
+    };
+    let source_format = match (spec.sample_format, spec.bits_per_sample) {
+        (hound::SampleFormat::Int, 16) => WriteSampleFormat::Int16,
+        (hound::SampleFormat::Float, 32) => WriteSampleFormat::Float32,
+        _ => return false,
+    };
+    let same_format = config.preserve_source_format
+        || matches!(
+            (source_format, config.sample_format),
+            (WriteSampleFormat::Int16, WriteSampleFormat::Int16)
+                | (WriteSampleFormat::Float32, WriteSampleFormat::Float32)
+        );
+    let all_channels = config.start_channel.unwrap_or(0) == 0
+        && config
+            .num_channels
+            .is_none_or(|num_channels| num_channels == spec.channels as usize);
+    #[cfg(feature = "resample")]
+    let same_rate = config
+        .target_sample_rate
+        .is_none_or(|sample_rate| sample_rate == spec.sample_rate);
+    #[cfg(not(feature = "resample"))]
+    let same_rate = true;
+
+    same_format
+        && all_channels
+        && same_rate
+        && config.extra_chunks.is_empty()
+        && config.clamp_range.is_none()
+        && config.dither == crate::dither::Dither::None
+        && config.channel_mask.is_none()
+        && !config.sanitize
+        && config.byte_order == ByteOrder::LittleEndian
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "flac")]
+    fn test_transcode_flac_to_wav() {
+        use crate::reader::Position;
+
+        transcode(
+            "test_data/test_1ch.flac",
+            "tmp_transcode.wav",
+            AudioReadConfig {
+                start: Position::Frame(1000),
+                stop: Position::Frame(2000),
+                ..Default::default()
+            },
+            AudioWriteConfig::default(),
+        )
+        .unwrap();
+
+        let expected = audio_read::<_, f32>(
+            "test_data/test_1ch.flac",
+            AudioReadConfig {
+                start: Position::Frame(1000),
+                stop: Position::Frame(2000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let data = audio_read::<_, f32>("tmp_transcode.wav", AudioReadConfig::default()).unwrap();
+        let _ = std::fs::remove_file("tmp_transcode.wav");
+
+        assert_eq!(data.num_frames, 1000);
+        crate::test_util::assert_audio_eq(&data, &expected, 1e-4);
+    }
+
+    /// Data of the format chunk of a WAV file
+    fn fmt_chunk(path: &str) -> Vec<u8> {
+        let bytes = std::fs::read(path).unwrap();
+        let pos = bytes.windows(4).position(|id| id == b"fmt ").unwrap();
+        let len = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        bytes[pos + 8..pos + 8 + len].to_vec()
+    }
+
+    #[test]
+    fn test_preserve_source_format() {
+        use audio_blocks::AudioBlockInterleavedView;
+
+        let preserve = || AudioWriteConfig {
+            preserve_source_format: true,
+            ..Default::default()
+        };
+
+        // 16-bit integer
+        transcode(
+            "test_data/test_1ch.wav",
+            "tmp_preserve.wav",
+            AudioReadConfig::default(),
+            preserve(),
+        )
+        .unwrap();
+        assert_eq!(
+            fmt_chunk("tmp_preserve.wav"),
+            fmt_chunk("test_data/test_1ch.wav")
+        );
+
+        // 32-bit float
+        crate::test_util::write_test_tone("tmp_preserve_src.wav", 48000, 2, 440.0, 4800);
+        transcode(
+            "tmp_preserve_src.wav",
+            "tmp_preserve.wav",
+            AudioReadConfig::default(),
+            preserve(),
+        )
+        .unwrap();
+        assert_eq!(
+            fmt_chunk("tmp_preserve.wav"),
+            fmt_chunk("tmp_preserve_src.wav")
+        );
+
+        // extensible header with a custom channel mask (5.1 with side channels)
+        let samples = vec![0.0f32; 6 * 100];
+        let block = AudioBlockInterleavedView::from_slice(&samples, 6, 100);
+        let config = AudioWriteConfig {
+            channel_mask: Some(0x60f),
+            ..Default::default()
+        };
+        audio_write("tmp_preserve_src.wav", block, 48000, config).unwrap();
+        transcode(
+            "tmp_preserve_src.wav",
+            "tmp_preserve.wav",
+            AudioReadConfig::default(),
+            preserve(),
+        )
+        .unwrap();
+        let fmt = fmt_chunk("tmp_preserve.wav");
+        assert_eq!(fmt, fmt_chunk("tmp_preserve_src.wav"));
+        assert_eq!(fmt[20..24], 0x60fu32.to_le_bytes());
+
+        let _ = std::fs::remove_file("tmp_preserve_src.wav");
+        let _ = std::fs::remove_file("tmp_preserve.wav");
+    }
+
+    #[test]
+    fn test_copy_or_transcode() {
+        // same format, the file is copied
+        let copied = audio_copy_or_transcode(
+            "test_data/test_1ch.wav",
+            "tmp_copy.wav",
+            AudioWriteConfig::default(),
+        )
+        .unwrap();
+        assert!(copied);
+        assert_eq!(
+            std::fs::read("tmp_copy.wav").unwrap(),
+            std::fs::read("test_data/test_1ch.wav").unwrap()
+        );
+
+        // a different sample format or an option that changes the samples is transcoded
+        let expected =
+            audio_read::<_, f32>("test_data/test_1ch.wav", AudioReadConfig::default()).unwrap();
+        for config in [
+            AudioWriteConfig {
+                sample_format: WriteSampleFormat::Float32,
+                ..Default::default()
+            },
+            AudioWriteConfig {
+                clamp_range: Some((-0.5, 0.5)),
+                ..Default::default()
+            },
+        ] {
+            let clamped = config.clamp_range.is_some();
+            let copied =
+                audio_copy_or_transcode("test_data/test_1ch.wav", "tmp_copy.wav", config).unwrap();
+            assert!(!copied);
+
+            let data = audio_read::<_, f32>("tmp_copy.wav", AudioReadConfig::default()).unwrap();
+            assert_eq!(data.num_frames, expected.num_frames);
+            if clamped {
+                assert!(data.interleaved_samples.iter().all(|s| s.abs() <= 0.5));
+            } else {
+                crate::test_util::assert_audio_eq(&data, &expected, 0.0);
+            }
+        }
+        let _ = std::fs::remove_file("tmp_copy.wav");
+    }
+
+    #[test]
+    fn test_transcode_missing_input() {
+        assert!(matches!(
+            transcode(
+                "test_data/missing.wav",
+                "tmp_missing.wav",
+                AudioReadConfig::default(),
+                AudioWriteConfig::default(),
+            ),
+            Err(TranscodeError::ReadError(_))
+        ));
+        assert!(!Path::new("tmp_missing.wav").exists());
+    }
+}
@@ -0,0 +1,276 @@
+//! Reader for Sony Wave64 files, which symphonia does not support.
+//!
+//! Wave64 is WAV with 64-bit chunk sizes and GUIDs as chunk identifiers, so files can be larger
+//! than 4 GB. Only uncompressed PCM and IEEE float data can be read, which is decoded with the
+//! PCM decoder of symphonia.
+
+use std::io::{Seek, SeekFrom};
+
+use symphonia::core::audio::Channels;
+use symphonia::core::codecs::{self, CodecParameters, CodecType};
+use symphonia::core::errors::{
+    Result, SeekErrorKind, decode_error, end_of_stream_error, seek_error, unsupported_error,
+};
+use symphonia::core::formats::{
+    Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track,
+};
+use symphonia::core::io::{MediaSource, MediaSourceStream, ReadBytes};
+use symphonia::core::meta::{Metadata, MetadataLog};
+use symphonia::core::probe::{Descriptor, Instantiate, QueryDescriptor};
+use symphonia::core::support_format;
+use symphonia::core::units::TimeBase;
+
+/// GUID of the outer `riff` chunk, which is also the marker of the file
+pub(crate) const W64_RIFF_GUID: [u8; 16] = [
+    0x72, 0x69, 0x66, 0x66, 0x2e, 0x91, 0xcf, 0x11, 0xa5, 0xd6, 0x28, 0xdb, 0x04, 0xc1, 0x00, 0x00,
+];
+const W64_WAVE_GUID: [u8; 16] = [
+    0x77, 0x61, 0x76, 0x65, 0xf3, 0xac, 0xd3, 0x11, 0x8c, 0xd1, 0x00, 0xc0, 0x4f, 0x8e, 0xdb, 0x8a,
+];
+const W64_FMT_GUID: [u8; 16] = [
+    0x66, 0x6d, 0x74, 0x20, 0xf3, 0xac, 0xd3, 0x11, 0x8c, 0xd1, 0x00, 0xc0, 0x4f, 0x8e, 0xdb, 0x8a,
+];
+const W64_DATA_GUID: [u8; 16] = [
+    0x64, 0x61, 0x74, 0x61, 0xf3, 0xac, 0xd3, 0x11, 0x8c, 0xd1, 0x00, 0xc0, 0x4f, 0x8e, 0xdb, 0x8a,
+];
+/// Size of a chunk header (GUID and 64-bit size), which is included in the chunk size
+const CHUNK_HEADER_SIZE: u64 = 24;
+
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// Number of frames in a packet, Wave64 is not packetized
+const FRAMES_PER_PACKET: u64 = 4096;
+
+pub(crate) struct W64Reader {
+    reader: MediaSourceStream,
+    tracks: Vec<Track>,
+    metadata: MetadataLog,
+    /// Size of a frame in bytes
+    block_align: u64,
+    data_start: u64,
+    data_end: u64,
+}
+
+impl QueryDescriptor for W64Reader {
+    fn query() -> &'static [Descriptor] {
+        &[support_format!(
+            "w64",
+            "Sony Wave64",
+            &["w64"],
+            &["audio/x-w64"],
+            &[&W64_RIFF_GUID]
+        )]
+    }
+
+    fn score(_context: &[u8]) -> u8 {
+        255
+    }
+}
+
+impl FormatReader for W64Reader {
+    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        let mut guid = [0u8; 16];
+        source.read_buf_exact(&mut guid)?;
+        if guid != W64_RIFF_GUID {
+            return unsupported_error("w64: missing riff guid");
+        }
+        let _riff_size = source.read_u64()?;
+        source.read_buf_exact(&mut guid)?;
+        if guid != W64_WAVE_GUID {
+            return unsupported_error("w64: riff form is not wave");
+        }
+
+        let mut codec_params = None;
+        let mut block_align = 0;
+
+        loop {
+            if source.read_buf_exact(&mut guid).is_err() {
+                return unsupported_error("w64: missing data chunk");
+            }
+            let size = source.read_u64()?;
+            let Some(len) = size.checked_sub(CHUNK_HEADER_SIZE) else {
+                return decode_error("w64: invalid chunk size");
+            };
+
+            match guid {
+                W64_FMT_GUID => {
+                    let params = read_format(&mut source, len)?;
+                    block_align = params.1;
+                    codec_params = Some(params.0);
+                }
+                W64_DATA_GUID => {
+                    let Some(mut codec_params) = codec_params else {
+                        return decode_error("w64: data chunk in front of format chunk");
+                    };
+                    if block_align == 0 {
+                        return decode_error("w64: invalid block align");
+                    }
+                    codec_params.with_n_frames(len / block_align);
+
+                    let data_start = source.pos();
+                    let Some(data_end) = data_start.checked_add(len) else {
+                        return decode_error("w64: invalid chunk size");
+                    };
+                    return Ok(Self {
+                        reader: source,
+                        tracks: vec![Track::new(0, codec_params)],
+                        metadata: MetadataLog::default(),
+                        block_align,
+                        data_start,
+                        data_end,
+                    });
+                }
+                // chunks are aligned to 8 bytes
+                _ => match len.checked_next_multiple_of(8) {
+                    Some(len) => source.ignore_bytes(len)?,
+                    None => return decode_error("w64: invalid chunk size"),
+                },
+            }
+        }
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let pos = self.reader.pos();
+        let remaining_frames = self.data_end.saturating_sub(pos) / self.block_align;
+        if remaining_frames == 0 {
+            return end_of_stream_error();
+        }
+        let frames = remaining_frames.min(FRAMES_PER_PACKET);
+        let ts = (pos - self.data_start) / self.block_align;
+        let buf = self
+            .reader
+            .read_boxed_slice_exact((frames * self.block_align) as usize)?;
+        Ok(Packet::new_from_boxed_slice(0, ts, frames, buf))
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &[]
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
+        let params = &self.tracks[0].codec_params;
+        let ts = match to {
+            SeekTo::TimeStamp { ts, .. } => ts,
+            SeekTo::Time { time, .. } => match params.sample_rate {
+                Some(sample_rate) => TimeBase::new(1, sample_rate).calc_timestamp(time),
+                None => return seek_error(SeekErrorKind::Unseekable),
+            },
+        };
+        if params.n_frames.is_some_and(|n_frames| ts > n_frames) {
+            return seek_error(SeekErrorKind::OutOfRange);
+        }
+
+        let seek_pos = self.data_start + ts * self.block_align;
+        if self.reader.is_seekable() {
+            self.reader.seek(SeekFrom::Start(seek_pos))?;
+        } else if seek_pos >= self.reader.pos() {
+            self.reader.ignore_bytes(seek_pos - self.reader.pos())?;
+        } else {
+            return seek_error(SeekErrorKind::ForwardOnly);
+        }
+
+        Ok(SeekedTo {
+            track_id: 0,
+            actual_ts: ts,
+            required_ts: ts,
+        })
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.reader
+    }
+}
+
+/// Read the format chunk, which is the same as in WAV files, and return the codec parameters and
+/// the block align.
+fn read_format(source: &mut MediaSourceStream, len: u64) -> Result<(CodecParameters, u64)> {
+    if len < 16 {
+        return decode_error("w64: format chunk too small");
+    }
+    let mut format_tag = source.read_u16()?;
+    let num_channels = source.read_u16()?;
+    let sample_rate = source.read_u32()?;
+    let _byte_rate = source.read_u32()?;
+    let block_align = source.read_u16()?;
+    let bits_per_sample = source.read_u16()?;
+    let mut valid_bits = bits_per_sample;
+    // without a channel mask the channels are in the default order
+    let mut channel_mask = 0;
+    let mut read = 16;
+
+    if format_tag == WAVE_FORMAT_EXTENSIBLE && len >= 40 {
+        let _extension_size = source.read_u16()?;
+        valid_bits = source.read_u16()?;
+        channel_mask = source.read_u32()?;
+        // the first two bytes of the sub format GUID are the format tag
+        format_tag = source.read_u16()?;
+        source.ignore_bytes(14)?;
+        read = 40;
+    }
+    source.ignore_bytes(len.next_multiple_of(8) - read)?;
+    if valid_bits > bits_per_sample {
+        return decode_error("w64: more valid bits than bits per sample");
+    }
+
+    let codec: CodecType = match (format_tag, bits_per_sample) {
+        (WAVE_FORMAT_PCM, 8) => codecs::CODEC_TYPE_PCM_U8,
+        (WAVE_FORMAT_PCM, 16) => codecs::CODEC_TYPE_PCM_S16LE,
+        (WAVE_FORMAT_PCM, 24) => codecs::CODEC_TYPE_PCM_S24LE,
+        (WAVE_FORMAT_PCM, 32) => codecs::CODEC_TYPE_PCM_S32LE,
+        (WAVE_FORMAT_IEEE_FLOAT, 32) => codecs::CODEC_TYPE_PCM_F32LE,
+        (WAVE_FORMAT_IEEE_FLOAT, 64) => codecs::CODEC_TYPE_PCM_F64LE,
+        _ => return unsupported_error("w64: only PCM and IEEE float data is supported"),
+    };
+    let channels = (1..=26)
+        .contains(&num_channels)
+        .then(|| Channels::from_bits(fix_channel_mask(channel_mask, num_channels)))
+        .flatten();
+    let Some(channels) = channels else {
+        return decode_error("w64: invalid channels");
+    };
+    if sample_rate == 0 {
+        return decode_error("w64: invalid sample rate");
+    }
+
+    let mut params = CodecParameters::new();
+    params
+        .for_codec(codec)
+        .with_sample_rate(sample_rate)
+        .with_time_base(TimeBase::new(1, sample_rate))
+        .with_channels(channels)
+        .with_max_frames_per_packet(FRAMES_PER_PACKET);
+    if format_tag == WAVE_FORMAT_PCM {
+        params
+            .with_bits_per_sample(u32::from(valid_bits))
+            .with_bits_per_coded_sample(u32::from(bits_per_sample));
+    }
+    Ok((params, u64::from(block_align)))
+}
+
+/// Make the channel mask describe `num_channels` channels, like the WAV reader of symphonia:
+/// missing positions are added above the highest one, extra positions are removed from the top.
+fn fix_channel_mask(mut mask: u32, num_channels: u16) -> u32 {
+    while mask.count_ones() < u32::from(num_channels) {
+        // the lowest free position if the highest one is taken
+        let above = 1u32.checked_shl(32 - mask.leading_zeros()).unwrap_or(0);
+        mask |= if above != 0 {
+            above
+        } else {
+            1 << mask.trailing_ones()
+        };
+    }
+    while mask.count_ones() > u32::from(num_channels) {
+        mask &= !(1 << (31 - mask.leading_zeros()));
+    }
+    mask
+}
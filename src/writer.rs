@@ -1,14 +1,92 @@
-use std::path::Path;
+use std::any::TypeId;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use audio_blocks::AudioBlock;
 use hound::{SampleFormat, WavSpec, WavWriter};
 use num::Float;
 use thiserror::Error;
 
+use crate::dither::{Dither, Quantizer};
+use crate::sample_rate::IntoSampleRate;
+
 #[derive(Debug, Error)]
 pub enum AudioWriteError {
     #[error("could not decode audio")]
     DecodingError(#[from] hound::Error),
+    #[error("could not write file")]
+    FileError(#[from] std::io::Error),
+    #[error("invalid chunk id {0:?}, expected exactly 4 ASCII characters")]
+    InvalidFourCC(String),
+    #[error("chunk {0} is too large for a RIFF container")]
+    ChunkTooLarge(FourCC),
+    #[error("invalid clamp range {0}..{1}")]
+    InvalidClampRange(f64, f64),
+    #[error("start channel {0} invalid, audio block has only {1} channels")]
+    InvalidStartChannel(usize, usize),
+    #[error("invalid number of channels to write: {0}")]
+    InvalidNumChannels(usize),
+    #[error("options {0} and {1} can't be combined")]
+    ConflictingOptions(&'static str, &'static str),
+    #[error("option {0} needs the whole audio and can't be used when writing blocks")]
+    UnsupportedStreamingOption(&'static str),
+    #[error("block has {0} channels, but the writer was created for {1} channels")]
+    ChannelCountMismatch(usize, usize),
+    #[cfg(feature = "limiter")]
+    #[error("invalid true-peak ceiling {0} dBFS")]
+    InvalidTruePeakCeiling(f64),
+    #[cfg(feature = "resample")]
+    #[error("could not resample audio")]
+    ResampleError(#[from] crate::resample::ResampleError),
+}
+
+/// Four-character code identifying a chunk in the RIFF container
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FourCC([u8; 4]);
+
+impl FourCC {
+    /// Create a chunk id, which has to consist of exactly 4 ASCII characters (e.g. `"iXML"`)
+    pub fn new(id: &str) -> Result<Self, AudioWriteError> {
+        let bytes: [u8; 4] = id
+            .as_bytes()
+            .try_into()
+            .map_err(|_| AudioWriteError::InvalidFourCC(id.to_string()))?;
+        if !bytes.is_ascii() {
+            return Err(AudioWriteError::InvalidFourCC(id.to_string()));
+        }
+        Ok(Self(bytes))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 4] {
+        &self.0
+    }
+}
+
+impl TryFrom<&str> for FourCC {
+    type Error = AudioWriteError;
+
+    fn try_from(id: &str) -> Result<Self, Self::Error> {
+        Self::new(id)
+    }
+}
+
+impl std::fmt::Display for FourCC {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // only ASCII is accepted on construction
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}
+
+/// Byte order of the written file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// Standard RIFF container
+    #[default]
+    LittleEndian,
+    /// RIFX container, as used by some legacy tools
+    BigEndian,
 }
 
 /// Sample format for writing audio
@@ -26,59 +104,940 @@ pub enum WriteSampleFormat {
 pub struct AudioWriteConfig {
     /// Sample format to use when writing
     pub sample_format: WriteSampleFormat,
+    /// Additional chunks (e.g. iXML, aXML) written into the RIFF container after the standard ones.
+    /// The data is written as is, a pad byte is added for odd lengths.
+    pub extra_chunks: Vec<(FourCC, Vec<u8>)>,
+    /// Samples are clamped to this range before writing, defaults to -1.0..1.0.
+    /// Float samples are only clamped if a range is set, integer samples are always limited
+    /// to full scale in addition.
+    pub clamp_range: Option<(f64, f64)>,
+    /// Dither applied when converting to integer samples
+    pub dither: Dither,
+    /// Seed of the dither noise. The same seed results in the same file, None uses a fixed
+    /// default seed.
+    pub dither_seed: Option<u64>,
+    /// First channel of the block to write (0-indexed). None means start from channel 0.
+    pub start_channel: Option<usize>,
+    /// Number of channels to write. None means write all remaining channels.
+    pub num_channels: Option<usize>,
+    /// Speaker positions of the channels as WAV channel mask. It is only stored in the extensible
+    /// header, which is written for more than 2 channels. None assigns the channels in order.
+    pub channel_mask: Option<u32>,
+    /// Byte order of the file. Big endian writes a RIFX container, which symphonia can't read.
+    /// The data of `extra_chunks` is written as is.
+    pub byte_order: ByteOrder,
+    /// Write with the sample format and channel mask of the source file, instead of
+    /// `sample_format` and `channel_mask`. Only used by [`crate::transcode()`].
+    pub preserve_source_format: bool,
+    /// Replace NaN with zero and infinite samples with the bound of the clamp range
+    pub sanitize: bool,
+    /// Resample the selected channels to this sample rate before writing.
+    /// Samples are sanitized after resampling, so non-finite samples spread to their neighbours.
+    #[cfg(feature = "resample")]
+    pub target_sample_rate: Option<u32>,
+    /// Limit the true peaks, including peaks between samples, to this level before quantizing.
+    /// This changes the signal: the gain is lowered around loud peaks, starting 1.5 ms in front
+    /// of them, and recovers within 50 ms. The output is not delayed.
+    #[cfg(feature = "limiter")]
+    pub true_peak_ceiling_dbfs: Option<f64>,
+    /// Called with the written fraction of the frames (0.0 to 1.0) while writing, e.g. to show
+    /// the progress of large exports. It is called every few thousand frames and with 1.0 once
+    /// the file is complete.
+    pub progress: Option<Box<dyn FnMut(f32) + Send>>,
+    /// Write into a temporary file next to the target and rename it over the target once it is
+    /// complete, so an existing file is never left half written. If the rename fails because
+    /// the target is on another file system, the file is copied, which is not atomic.
+    pub atomic: bool,
+}
+
+impl AudioWriteConfig {
+    pub fn builder() -> AudioWriteConfigBuilder {
+        AudioWriteConfigBuilder::default()
+    }
+}
+
+/// Builder for [`AudioWriteConfig`], which rejects invalid and conflicting options
+#[derive(Default)]
+pub struct AudioWriteConfigBuilder {
+    config: AudioWriteConfig,
+}
+
+impl AudioWriteConfigBuilder {
+    pub fn sample_format(mut self, sample_format: WriteSampleFormat) -> Self {
+        self.config.sample_format = sample_format;
+        self
+    }
+
+    /// Add a chunk, see [`AudioWriteConfig::extra_chunks`]
+    pub fn extra_chunk(mut self, id: FourCC, data: Vec<u8>) -> Self {
+        self.config.extra_chunks.push((id, data));
+        self
+    }
+
+    pub fn clamp_range(mut self, min: f64, max: f64) -> Self {
+        self.config.clamp_range = Some((min, max));
+        self
+    }
+
+    pub fn dither(mut self, dither: Dither) -> Self {
+        self.config.dither = dither;
+        self
+    }
+
+    pub fn dither_seed(mut self, seed: u64) -> Self {
+        self.config.dither_seed = Some(seed);
+        self
+    }
+
+    pub fn start_channel(mut self, start_channel: usize) -> Self {
+        self.config.start_channel = Some(start_channel);
+        self
+    }
+
+    pub fn num_channels(mut self, num_channels: usize) -> Self {
+        self.config.num_channels = Some(num_channels);
+        self
+    }
+
+    pub fn channel_mask(mut self, channel_mask: u32) -> Self {
+        self.config.channel_mask = Some(channel_mask);
+        self
+    }
+
+    pub fn byte_order(mut self, byte_order: ByteOrder) -> Self {
+        self.config.byte_order = byte_order;
+        self
+    }
+
+    pub fn preserve_source_format(mut self, preserve_source_format: bool) -> Self {
+        self.config.preserve_source_format = preserve_source_format;
+        self
+    }
+
+    pub fn sanitize(mut self, sanitize: bool) -> Self {
+        self.config.sanitize = sanitize;
+        self
+    }
+
+    #[cfg(feature = "resample")]
+    pub fn target_sample_rate(mut self, target_sample_rate: u32) -> Self {
+        self.config.target_sample_rate = Some(target_sample_rate);
+        self
+    }
+
+    #[cfg(feature = "limiter")]
+    pub fn true_peak_ceiling_dbfs(mut self, ceiling: f64) -> Self {
+        self.config.true_peak_ceiling_dbfs = Some(ceiling);
+        self
+    }
+
+    pub fn progress(mut self, progress: impl FnMut(f32) + Send + 'static) -> Self {
+        self.config.progress = Some(Box::new(progress));
+        self
+    }
+
+    pub fn atomic(mut self, atomic: bool) -> Self {
+        self.config.atomic = atomic;
+        self
+    }
+
+    pub fn build(self) -> Result<AudioWriteConfig, AudioWriteError> {
+        let config = self.config;
+        if let Some((min, max)) = config.clamp_range
+            && (min.is_nan() || max.is_nan() || min > max)
+        {
+            return Err(AudioWriteError::InvalidClampRange(min, max));
+        }
+        if config.num_channels == Some(0) {
+            return Err(AudioWriteError::InvalidNumChannels(0));
+        }
+        if config.preserve_source_format && config.channel_mask.is_some() {
+            return Err(AudioWriteError::ConflictingOptions(
+                "preserve_source_format",
+                "channel_mask",
+            ));
+        }
+        // float samples are never dithered, with a preserved format it depends on the source
+        if !config.preserve_source_format
+            && matches!(config.sample_format, WriteSampleFormat::Float32)
+            && config.dither != Dither::None
+        {
+            return Err(AudioWriteError::ConflictingOptions(
+                "sample_format",
+                "dither",
+            ));
+        }
+        Ok(config)
+    }
+}
+
+/// Information about a write, see [`audio_write_with_report`].
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct WriteReport {
+    /// Number of written frames
+    pub num_frames: usize,
+    /// Number of non-finite samples that were replaced, see [`AudioWriteConfig::sanitize`]
+    pub sanitized_samples: usize,
+    /// Number of samples outside of the clamp range, which is full scale for `Int16`. `Float32`
+    /// samples are only clipped if a clamp range is set.
+    pub clipped_samples: usize,
+    /// Highest absolute sample value before clamping
+    pub peak: f32,
+}
+
+/// File extensions of the containers that can be written
+pub fn supported_write_formats() -> &'static [&'static str] {
+    &["wav"]
 }
 
 pub fn audio_write<P: AsRef<Path>, F: Float + 'static>(
     path: P,
     audio_block: impl AudioBlock<F>,
-    sample_rate: u32,
+    sample_rate: impl IntoSampleRate,
     config: AudioWriteConfig,
 ) -> Result<(), AudioWriteError> {
-    let spec = WavSpec {
-        channels: audio_block.num_channels(),
+    audio_write_with_report(path, audio_block, sample_rate, config)?;
+    Ok(())
+}
+
+/// Same as [`audio_write`], but additionally returns information about the write.
+pub fn audio_write_with_report<P: AsRef<Path>, F: Float + 'static>(
+    path: P,
+    audio_block: impl AudioBlock<F>,
+    sample_rate: impl IntoSampleRate,
+    config: AudioWriteConfig,
+) -> Result<WriteReport, AudioWriteError> {
+    let sample_rate = sample_rate.into_hz();
+    if config.atomic {
+        let temp = TempFile::new(path.as_ref());
+        let config = AudioWriteConfig {
+            atomic: false,
+            ..config
+        };
+        let report = audio_write_with_report(&temp.path, audio_block, sample_rate, config)?;
+        temp.persist(path.as_ref())?;
+        return Ok(report);
+    }
+
+    // readable as well, for converting the finished file to big endian
+    let open = || {
+        File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path.as_ref())
+    };
+    let (_, report) = write_wav(open, audio_block, sample_rate, config)?;
+    Ok(report)
+}
+
+/// Same as [`audio_write`], but writes into `writer` instead of a file, e.g. a
+/// `Cursor<Vec<u8>>` to keep the encoded file in memory.
+///
+/// The file is written from the start of `writer`, which is returned once the file is complete.
+/// Bytes that `writer` already held behind the end of the file are kept, the RIFF header gives
+/// the length of the file. The writer needs to be readable as well, e.g. for the conversion to
+/// big endian.
+/// [`AudioWriteConfig::atomic`] has no effect.
+pub fn audio_write_to_writer<W: Read + Write + Seek, F: Float + 'static>(
+    mut writer: W,
+    audio_block: impl AudioBlock<F>,
+    sample_rate: impl IntoSampleRate,
+    config: AudioWriteConfig,
+) -> Result<W, AudioWriteError> {
+    let sample_rate = sample_rate.into_hz();
+    let open = move || writer.seek(SeekFrom::Start(0)).map(|_| writer);
+    let (writer, _) = write_wav(open, audio_block, sample_rate, config)?;
+    Ok(writer)
+}
+
+/// Write the WAV file into the destination returned by `open`, positioned at its start.
+///
+/// The destination is opened after the config was validated, so no file is created for an
+/// invalid config.
+fn write_wav<W: Read + Write + Seek, F: Float + 'static>(
+    open: impl FnOnce() -> std::io::Result<W>,
+    audio_block: impl AudioBlock<F>,
+    sample_rate: u32,
+    mut config: AudioWriteConfig,
+) -> Result<(W, WriteReport), AudioWriteError> {
+    let mut encoder = SampleEncoder::<F>::new(&config, audio_block.num_channels() as usize)?;
+
+    let channels = channel_range(
+        config.start_channel,
+        config.num_channels,
+        audio_block.num_channels() as usize,
+    )?;
+
+    #[cfg(feature = "resample")]
+    if let Some(target_sample_rate) = config.target_sample_rate
+        && target_sample_rate != sample_rate
+    {
+        let samples: Vec<F> = audio_block
+            .frame_iters()
+            .flat_map(|frame| frame.skip(channels.start).take(channels.len()).copied())
+            .collect();
+        let resampled =
+            crate::resample::resample(&samples, channels.len(), sample_rate, target_sample_rate)?;
+        let block = audio_blocks::AudioBlockInterleavedView::from_slice(
+            &resampled,
+            channels.len() as u16,
+            resampled.len() / channels.len(),
+        );
+        let config = AudioWriteConfig {
+            start_channel: None,
+            num_channels: None,
+            target_sample_rate: None,
+            ..config
+        };
+        return write_wav(open, block, target_sample_rate, config);
+    }
+
+    #[cfg(feature = "limiter")]
+    if let Some(ceiling) = config.true_peak_ceiling_dbfs {
+        if !ceiling.is_finite() {
+            return Err(AudioWriteError::InvalidTruePeakCeiling(ceiling));
+        }
+        let mut samples: Vec<F> = audio_block
+            .frame_iters()
+            .flat_map(|frame| frame.skip(channels.start).take(channels.len()).copied())
+            .collect();
+        crate::limiter::limit(
+            &mut samples,
+            channels.len(),
+            sample_rate,
+            10f64.powf(ceiling / 20.0),
+        );
+        let block = audio_blocks::AudioBlockInterleavedView::from_slice(
+            &samples,
+            channels.len() as u16,
+            samples.len() / channels.len(),
+        );
+        let config = AudioWriteConfig {
+            start_channel: None,
+            num_channels: None,
+            true_peak_ceiling_dbfs: None,
+            ..config
+        };
+        return write_wav(open, block, sample_rate, config);
+    }
+
+    let num_frames = audio_block.num_frames();
+    let mut progress = Progress {
+        callback: config.progress.take(),
+        num_frames,
+        frames: 0,
+    };
+    let spec = wav_spec(channels.len(), sample_rate, config.sample_format)?;
+
+    let mut dest = open()?;
+    let mut file = BufWriter::new(&mut dest);
+    let mut writer = WavWriter::new(&mut file, spec)?;
+
+    match config.sample_format {
+        // without dither, f32 samples are converted in blocks, which can use SIMD
+        WriteSampleFormat::Int16
+            if encoder.quantizer.is_none() && TypeId::of::<F>() == TypeId::of::<f32>() =>
+        {
+            let (min, max) = (
+                encoder.clamp_min.to_f32().unwrap_or(-1.0),
+                encoder.clamp_max.to_f32().unwrap_or(1.0),
+            );
+            let mut block = Vec::with_capacity(CONVERT_BLOCK_SAMPLES);
+            let mut converted = Vec::with_capacity(CONVERT_BLOCK_SAMPLES);
+            let mut frames = audio_block.frame_iters().peekable();
+            while let Some(frame) = frames.next() {
+                let frame = frame.skip(channels.start).take(channels.len());
+                block.extend(frame.map(|sample| encoder.prepare(*sample).to_f32().unwrap_or(0.0)));
+                progress.advance(1);
+                if block.len() >= CONVERT_BLOCK_SAMPLES || frames.peek().is_none() {
+                    crate::convert::f32_to_i16(&block, min, max, &mut converted);
+                    let mut sample_writer = writer.get_i16_writer(converted.len() as u32);
+                    for sample in &converted {
+                        sample_writer.write_sample(*sample);
+                    }
+                    sample_writer.flush()?;
+                    block.clear();
+                    converted.clear();
+                }
+            }
+        }
+        _ => {
+            for frame in audio_block.frame_iters() {
+                progress.advance(1);
+                let frame = frame.skip(channels.start).take(channels.len());
+                for (channel, sample) in frame.enumerate() {
+                    encoder.write(&mut writer, channel, *sample)?;
+                }
+            }
+        }
+    }
+
+    writer.finalize()?;
+    file.flush()?;
+    drop(file);
+    finish_wav(&mut dest, spec, &config, num_frames)?;
+
+    progress.finish();
+    Ok((dest, encoder.report(num_frames)))
+}
+
+fn wav_spec(
+    num_channels: usize,
+    sample_rate: u32,
+    sample_format: WriteSampleFormat,
+) -> Result<WavSpec, AudioWriteError> {
+    let channels = u16::try_from(num_channels)
+        .map_err(|_| AudioWriteError::InvalidNumChannels(num_channels))?;
+    Ok(WavSpec {
+        channels,
         sample_rate,
-        bits_per_sample: match config.sample_format {
+        bits_per_sample: match sample_format {
             WriteSampleFormat::Int16 => 16,
             WriteSampleFormat::Float32 => 32,
         },
-        sample_format: match config.sample_format {
+        sample_format: match sample_format {
             WriteSampleFormat::Int16 => SampleFormat::Int,
             WriteSampleFormat::Float32 => SampleFormat::Float,
         },
-    };
+    })
+}
 
-    let mut writer = WavWriter::create(path.as_ref(), spec)?;
+/// Complete a file that hound finalized: the channel mask, the chunks that hound does not
+/// write and the conversion to big endian.
+fn finish_wav<W: Read + Write + Seek>(
+    file: &mut W,
+    spec: WavSpec,
+    config: &AudioWriteConfig,
+    num_frames: usize,
+) -> Result<(), AudioWriteError> {
+    // hound always writes the default channel mask
+    if let Some(channel_mask) = config.channel_mask
+        && spec.channels > 2
+    {
+        file.seek(SeekFrom::Start(CHANNEL_MASK_OFFSET))?;
+        file.write_all(&channel_mask.to_le_bytes())?;
+    }
 
-    match config.sample_format {
-        WriteSampleFormat::Int16 => {
-            // Convert f32 samples to i16
-            for frame in audio_block.frame_iters() {
-                for sample in frame {
-                    let sample_i16 = (sample.clamp(F::one().neg(), F::one())
-                        * F::from(i16::MAX).unwrap_or(F::zero()))
-                    .to_i16()
-                    .unwrap_or(0);
-                    writer.write_sample(sample_i16)?;
+    // non-PCM formats need a fact chunk with the number of frames, which hound does not write
+    if let WriteSampleFormat::Float32 = config.sample_format {
+        let fact = FourCC(*b"fact");
+        let num_frames =
+            u32::try_from(num_frames).map_err(|_| AudioWriteError::ChunkTooLarge(fact))?;
+        append_chunks(file, &[(fact, num_frames.to_le_bytes().to_vec())])?;
+    }
+    append_chunks(file, &config.extra_chunks)?;
+    file.flush()?;
+
+    #[cfg(debug_assertions)]
+    debug_assert_header(file, spec.channels, spec.sample_rate)?;
+
+    if config.byte_order == ByteOrder::BigEndian {
+        convert_to_rifx(file, spec.bits_per_sample as usize / 8)?;
+    }
+    Ok(())
+}
+
+/// Converts samples to the sample format of the file, with the clamping, sanitizing and
+/// dither of the config.
+struct SampleEncoder<F> {
+    sample_format: WriteSampleFormat,
+    /// Clamp range of integer samples, limited to full scale
+    clamp_min: F,
+    clamp_max: F,
+    /// Clamp range of float samples, only if a range is set
+    float_clamp: Option<(f32, f32)>,
+    /// Values of positive and negative infinite samples, if non-finite samples are replaced
+    sanitize_bounds: Option<(F, F)>,
+    quantizer: Option<Quantizer>,
+    sanitized_samples: usize,
+    clipped_samples: usize,
+    peak: F,
+}
+
+impl<F: Float> SampleEncoder<F> {
+    fn new(config: &AudioWriteConfig, num_channels: usize) -> Result<Self, AudioWriteError> {
+        let (min, max) = config.clamp_range.unwrap_or((-1.0, 1.0));
+        if min.is_nan() || max.is_nan() || min > max {
+            return Err(AudioWriteError::InvalidClampRange(min, max));
+        }
+        let quantize = matches!(config.sample_format, WriteSampleFormat::Int16)
+            && config.dither != Dither::None;
+        Ok(Self {
+            sample_format: config.sample_format,
+            // a range outside of full scale can't be written as integers, so both bounds are
+            // limited, which keeps clamp_min <= clamp_max
+            clamp_min: F::from(min.clamp(-1.0, 1.0)).unwrap_or(F::one().neg()),
+            clamp_max: F::from(max.clamp(-1.0, 1.0)).unwrap_or(F::one()),
+            float_clamp: config.clamp_range.map(|_| (min as f32, max as f32)),
+            sanitize_bounds: config.sanitize.then(|| {
+                (
+                    F::from(min).unwrap_or(F::zero()),
+                    F::from(max).unwrap_or(F::zero()),
+                )
+            }),
+            quantizer: quantize
+                .then(|| Quantizer::new(config.dither, num_channels, config.dither_seed)),
+            sanitized_samples: 0,
+            clipped_samples: 0,
+            peak: F::zero(),
+        })
+    }
+
+    /// Replace a non-finite sample if enabled, and record the level of the sample.
+    fn prepare(&mut self, sample: F) -> F {
+        let sample = match self.sanitize_bounds {
+            Some((min, max)) if !sample.is_finite() => {
+                self.sanitized_samples += 1;
+                if sample.is_nan() {
+                    F::zero()
+                } else if sample > F::zero() {
+                    max
+                } else {
+                    min
                 }
             }
+            _ => sample,
+        };
+
+        self.peak = self.peak.max(sample.abs());
+        let clipped = match self.sample_format {
+            WriteSampleFormat::Int16 => sample < self.clamp_min || sample > self.clamp_max,
+            WriteSampleFormat::Float32 => self.float_clamp.is_some_and(|(min, max)| {
+                let sample = sample.to_f32().unwrap_or(0.0);
+                sample < min || sample > max
+            }),
+        };
+        if clipped {
+            self.clipped_samples += 1;
         }
-        WriteSampleFormat::Float32 => {
-            // Write f32 samples directly
-            for frame in audio_block.frame_iters() {
-                for sample in frame {
-                    writer.write_sample(sample.to_f32().unwrap_or(0.0))?;
+        sample
+    }
+
+    fn report(&self, num_frames: usize) -> WriteReport {
+        WriteReport {
+            num_frames,
+            sanitized_samples: self.sanitized_samples,
+            clipped_samples: self.clipped_samples,
+            peak: self.peak.to_f32().unwrap_or(f32::INFINITY),
+        }
+    }
+
+    /// Convert and write a sample of the given channel.
+    fn write<W: Write + Seek>(
+        &mut self,
+        writer: &mut WavWriter<W>,
+        channel: usize,
+        sample: F,
+    ) -> Result<(), AudioWriteError> {
+        let sample = self.prepare(sample);
+        match self.sample_format {
+            WriteSampleFormat::Int16 => {
+                let scaled = sample.clamp(self.clamp_min, self.clamp_max)
+                    * F::from(i16::MAX).unwrap_or(F::zero());
+                let sample_i16 = match &mut self.quantizer {
+                    Some(quantizer) => {
+                        let quantized = quantizer.quantize(channel, scaled.to_f64().unwrap_or(0.0));
+                        quantized.clamp(i16::MIN as f64, i16::MAX as f64) as i16
+                    }
+                    None => scaled.to_i16().unwrap_or(0),
+                };
+                writer.write_sample(sample_i16)?;
+            }
+            WriteSampleFormat::Float32 => {
+                let mut sample = sample.to_f32().unwrap_or(0.0);
+                if let Some((min, max)) = self.float_clamp {
+                    sample = sample.clamp(min, max);
                 }
+                writer.write_sample(sample)?;
             }
         }
+        Ok(())
     }
+}
 
-    writer.finalize()?;
+/// Writes a WAV file block by block, e.g. while the audio is generated in real time, so the
+/// whole audio never has to be in memory.
+///
+/// The options of the config apply as in [`audio_write`], dither continues across blocks.
+/// The options that need the whole audio (`target_sample_rate` and `true_peak_ceiling_dbfs`)
+/// are rejected. `progress` is called after each block if the length was announced with
+/// [`Self::with_expected_frames`], and with 1.0 once the file is finalized.
+/// Call [`Self::finalize`] to complete the file.
+pub struct StreamingWavWriter<F: Float + 'static> {
+    writer: WavWriter<BufWriter<File>>,
+    /// Second handle of the file, to complete it after hound finalized it
+    file: File,
+    /// Temporary file and target of an atomic write
+    atomic: Option<(TempFile, PathBuf)>,
+    spec: WavSpec,
+    config: AudioWriteConfig,
+    encoder: SampleEncoder<F>,
+    /// Number of channels of the blocks
+    block_channels: usize,
+    /// Channels of the blocks that are written
+    channels: std::ops::Range<usize>,
+    num_frames: usize,
+    /// Length of the whole audio, for the progress
+    expected_frames: Option<usize>,
+}
+
+impl<F: Float + 'static> StreamingWavWriter<F> {
+    /// Create the file for blocks with `num_channels` channels.
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        sample_rate: impl IntoSampleRate,
+        num_channels: usize,
+        config: AudioWriteConfig,
+    ) -> Result<Self, AudioWriteError> {
+        let sample_rate = sample_rate.into_hz();
+        #[cfg(feature = "resample")]
+        if config.target_sample_rate.is_some() {
+            return Err(AudioWriteError::UnsupportedStreamingOption(
+                "target_sample_rate",
+            ));
+        }
+        #[cfg(feature = "limiter")]
+        if config.true_peak_ceiling_dbfs.is_some() {
+            return Err(AudioWriteError::UnsupportedStreamingOption(
+                "true_peak_ceiling_dbfs",
+            ));
+        }
+        let encoder = SampleEncoder::new(&config, num_channels)?;
+        let channels = channel_range(config.start_channel, config.num_channels, num_channels)?;
+        let spec = wav_spec(channels.len(), sample_rate, config.sample_format)?;
+
+        let atomic = config
+            .atomic
+            .then(|| (TempFile::new(path.as_ref()), path.as_ref().to_path_buf()));
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(
+                atomic
+                    .as_ref()
+                    .map_or(path.as_ref(), |(temp, _)| &temp.path),
+            )?;
+        let writer = WavWriter::new(BufWriter::new(file.try_clone()?), spec)?;
+
+        Ok(Self {
+            writer,
+            file,
+            atomic,
+            spec,
+            config,
+            encoder,
+            block_channels: num_channels,
+            channels,
+            num_frames: 0,
+            expected_frames: None,
+        })
+    }
+
+    /// Announce the number of frames that will be written, so the progress can be reported
+    /// after each block.
+    pub fn with_expected_frames(mut self, num_frames: usize) -> Self {
+        self.expected_frames = Some(num_frames);
+        self
+    }
+
+    /// Append a block, which needs the number of channels the writer was created for.
+    pub fn write_block(&mut self, audio_block: impl AudioBlock<F>) -> Result<(), AudioWriteError> {
+        let block_channels = audio_block.num_channels() as usize;
+        if block_channels != self.block_channels {
+            return Err(AudioWriteError::ChannelCountMismatch(
+                block_channels,
+                self.block_channels,
+            ));
+        }
+        for frame in audio_block.frame_iters() {
+            let frame = frame.skip(self.channels.start).take(self.channels.len());
+            for (channel, sample) in frame.enumerate() {
+                self.encoder.write(&mut self.writer, channel, *sample)?;
+            }
+        }
+        self.num_frames += audio_block.num_frames();
+        // the end is reported by `finalize`, once the file is complete
+        if let (Some(callback), Some(expected)) = (&mut self.config.progress, self.expected_frames)
+            && self.num_frames < expected
+        {
+            callback(self.num_frames as f32 / expected as f32);
+        }
+        Ok(())
+    }
+
+    /// Number of frames written so far
+    pub fn num_frames(&self) -> usize {
+        self.num_frames
+    }
+
+    /// Complete the file, and move it to the target with an atomic write.
+    pub fn finalize(mut self) -> Result<WriteReport, AudioWriteError> {
+        self.writer.finalize()?;
+        finish_wav(&mut self.file, self.spec, &self.config, self.num_frames)?;
+        drop(self.file);
+        if let Some((temp, target)) = self.atomic {
+            temp.persist(&target)?;
+        }
+        if let Some(callback) = &mut self.config.progress {
+            callback(1.0);
+        }
+        Ok(self.encoder.report(self.num_frames))
+    }
+}
+
+/// Temporary file of an atomic write, see [`AudioWriteConfig::atomic`].
+///
+/// It is removed when dropped, also if the write fails or panics, unless it was persisted.
+struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    /// Hidden file in the directory of `target`, unique within this process
+    fn new(target: &Path) -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let name = target.file_name().unwrap_or_default().to_string_lossy();
+        let path = target.with_file_name(format!(
+            ".{name}.{}.{}.tmp",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        Self { path }
+    }
+
+    /// Move the file over `target`, or copy it if they are on different file systems.
+    fn persist(self, target: &Path) -> std::io::Result<()> {
+        match std::fs::rename(&self.path, target) {
+            Err(err) if err.kind() == std::io::ErrorKind::CrossesDevices => {
+                std::fs::copy(&self.path, target).map(|_| ())
+            }
+            result => result,
+        }
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        // after a successful rename the file does not exist anymore
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Number of frames between calls of [`AudioWriteConfig::progress`]
+const PROGRESS_INTERVAL_FRAMES: usize = 4096;
+
+/// Calls the progress callback of the config while frames are written.
+struct Progress {
+    callback: Option<Box<dyn FnMut(f32) + Send>>,
+    num_frames: usize,
+    /// Frames written so far
+    frames: usize,
+}
+
+impl Progress {
+    fn advance(&mut self, frames: usize) {
+        let Some(callback) = &mut self.callback else {
+            return;
+        };
+        let previous = self.frames;
+        self.frames += frames;
+        // the end is reported by `finish`, once the file is complete
+        if self.frames / PROGRESS_INTERVAL_FRAMES != previous / PROGRESS_INTERVAL_FRAMES
+            && self.frames < self.num_frames
+        {
+            callback(self.frames as f32 / self.num_frames as f32);
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some(callback) = &mut self.callback {
+            callback(1.0);
+        }
+    }
+}
+
+/// Convert a finalized RIFF file into RIFX, where all numbers are big endian.
+///
+/// The samples are swapped in place, so the file is never fully loaded into memory.
+/// The data of unknown chunks is left as is.
+fn convert_to_rifx<W: Read + Write + Seek>(
+    file: &mut W,
+    bytes_per_sample: usize,
+) -> Result<(), AudioWriteError> {
+    /// Sizes of the fields in the fmt chunk, up to the GUID of the extensible header
+    const FMT_FIELDS: [usize; 9] = [2, 2, 4, 4, 2, 2, 2, 2, 4];
+    const BLOCK_SIZE: usize = 1 << 16;
+
+    let mut header = [0u8; 8];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)?;
+    let riff_len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(b"RIFX")?;
+    file.write_all(&riff_len.to_be_bytes())?;
+
+    let end = 8 + riff_len as u64;
+    let mut pos = 12;
+    while pos + 8 <= end {
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut header)?;
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        file.seek(SeekFrom::Start(pos + 4))?;
+        file.write_all(&len.to_be_bytes())?;
+
+        let field_sizes: &[usize] = match &header[..4] {
+            b"fmt " => &FMT_FIELDS,
+            b"fact" => &[4],
+            _ => &[],
+        };
+        if !field_sizes.is_empty() {
+            let mut data = vec![0u8; len as usize];
+            file.read_exact(&mut data)?;
+            let mut offset = 0;
+            for size in field_sizes {
+                if let Some(field) = data.get_mut(offset..offset + size) {
+                    field.reverse();
+                }
+                offset += size;
+            }
+            file.seek(SeekFrom::Start(pos + 8))?;
+            file.write_all(&data)?;
+        } else if &header[..4] == b"data" {
+            let mut block = vec![0u8; BLOCK_SIZE - BLOCK_SIZE % bytes_per_sample];
+            let mut block_pos = pos + 8;
+            let data_end = block_pos + len as u64;
+            while block_pos < data_end {
+                let block = &mut block[..(data_end - block_pos).min(BLOCK_SIZE as u64) as usize];
+                file.seek(SeekFrom::Start(block_pos))?;
+                file.read_exact(block)?;
+                block
+                    .chunks_exact_mut(bytes_per_sample)
+                    .for_each(|sample| sample.reverse());
+                file.seek(SeekFrom::Start(block_pos))?;
+                file.write_all(block)?;
+                block_pos += block.len() as u64;
+            }
+        }
+
+        pos += 8 + len as u64 + len as u64 % 2;
+    }
+
+    Ok(())
+}
+
+/// Number of samples that are converted to integers at once
+const CONVERT_BLOCK_SAMPLES: usize = 4096;
+
+/// Position of the channel mask in the extensible header written by hound
+const CHANNEL_MASK_OFFSET: u64 = 40;
+
+/// Check that the fmt chunk of the written file contains the expected channels and sample rate.
+#[cfg(debug_assertions)]
+fn debug_assert_header<R: Read + Seek>(
+    file: &mut R,
+    num_channels: u16,
+    sample_rate: u32,
+) -> Result<(), AudioWriteError> {
+    /// Position of the number of channels in the fmt chunk, followed by the sample rate
+    const NUM_CHANNELS_OFFSET: u64 = 22;
+
+    let mut fields = [0u8; 6];
+    file.seek(SeekFrom::Start(NUM_CHANNELS_OFFSET))?;
+    file.read_exact(&mut fields)?;
+    let header_channels = u16::from_le_bytes([fields[0], fields[1]]);
+    let header_sample_rate = u32::from_le_bytes([fields[2], fields[3], fields[4], fields[5]]);
+    debug_assert_eq!(header_channels, num_channels, "wrong channels in header");
+    debug_assert_eq!(
+        header_sample_rate, sample_rate,
+        "wrong sample rate in header"
+    );
+    Ok(())
+}
+
+/// Range of the channels to write, validated against the channels of the block.
+fn channel_range(
+    start_channel: Option<usize>,
+    num_channels: Option<usize>,
+    block_channels: usize,
+) -> Result<std::ops::Range<usize>, AudioWriteError> {
+    let start = start_channel.unwrap_or(0);
+    if start >= block_channels {
+        return Err(AudioWriteError::InvalidStartChannel(start, block_channels));
+    }
+
+    let count = num_channels.unwrap_or(block_channels - start);
+    match start.checked_add(count) {
+        Some(end) if count > 0 && end <= block_channels => Ok(start..end),
+        _ => Err(AudioWriteError::InvalidNumChannels(count)),
+    }
+}
+
+/// Append chunks to a finalized RIFF file and update the RIFF size accordingly.
+fn append_chunks<W: Read + Write + Seek>(
+    writer: &mut W,
+    chunks: &[(FourCC, Vec<u8>)],
+) -> Result<(), AudioWriteError> {
+    if chunks.is_empty() {
+        return Ok(());
+    }
+
+    // the end of the file from the RIFF header, the writer may hold more bytes behind it
+    let mut riff_len = [0u8; 4];
+    writer.seek(SeekFrom::Start(4))?;
+    writer.read_exact(&mut riff_len)?;
+    let mut end = 8 + u64::from(u32::from_le_bytes(riff_len));
+    writer.seek(SeekFrom::Start(end))?;
+
+    // chunks have to start at even offsets
+    if end % 2 == 1 {
+        writer.write_all(&[0])?;
+        end += 1;
+    }
+
+    for (id, data) in chunks {
+        let len = u32::try_from(data.len()).map_err(|_| AudioWriteError::ChunkTooLarge(*id))?;
+        writer.write_all(id.as_bytes())?;
+        writer.write_all(&len.to_le_bytes())?;
+        writer.write_all(data)?;
+        end += 8 + data.len() as u64;
+        if data.len() % 2 == 1 {
+            writer.write_all(&[0])?;
+            end += 1;
+        }
+    }
+
+    let riff_len =
+        u32::try_from(end - 8).map_err(|_| AudioWriteError::ChunkTooLarge(FourCC(*b"RIFF")))?;
+    writer.seek(SeekFrom::Start(4))?;
+    writer.write_all(&riff_len.to_le_bytes())?;
 
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    /// Read the ids and data of all chunks in a RIFF file.
+    fn read_chunks(path: &str) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let bytes = std::fs::read(path).unwrap();
+
+        assert_eq!(&bytes[0..4], b"RIFF");
+        let riff_len = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+        assert_eq!(riff_len + 8, bytes.len());
+
+        let mut chunks = Vec::new();
+        let mut pos = 12;
+        while pos + 8 <= bytes.len() {
+            let id = bytes[pos..pos + 4].to_vec();
+            let len = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().unwrap()) as usize;
+            chunks.push((id, bytes[pos + 8..pos + 8 + len].to_vec()));
+            pos += 8 + len + len % 2;
+        }
+        chunks
+    }
 
     #[test]
     #[cfg(all(feature = "read", feature = "write"))]
@@ -95,21 +1054,150 @@ mod tests {
             data1.sample_rate,
             AudioWriteConfig {
                 sample_format: WriteSampleFormat::Int16,
+                ..Default::default()
             },
         )
         .unwrap();
 
         let data2 = audio_read::<_, f32>("tmp1.wav", AudioReadConfig::default()).unwrap();
-        assert_eq!(data1.sample_rate, data2.sample_rate);
-        approx::assert_abs_diff_eq!(
-            data1.audio_block().raw_data(),
-            data2.audio_block().raw_data(),
-            epsilon = 1e-4
-        );
+        crate::test_util::assert_audio_eq(&data1, &data2, 1e-4);
 
         let _ = std::fs::remove_file("tmp1.wav");
     }
 
+    #[test]
+    fn test_header_i16() {
+        use super::*;
+        use audio_blocks::AudioBlockInterleavedView;
+
+        let samples: Vec<f32> = (0..300).map(|i| (i as f32 / 300.0) - 0.5).collect();
+        let block = AudioBlockInterleavedView::from_slice(&samples, 3, 100);
+        audio_write(
+            "tmp15.wav",
+            block,
+            crate::SampleRate::SR_22050,
+            AudioWriteConfig {
+                sample_format: WriteSampleFormat::Int16,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let spec = hound::WavReader::open("tmp15.wav").unwrap().spec();
+        let _ = std::fs::remove_file("tmp15.wav");
+        assert_eq!(spec.sample_rate, 22050);
+        assert_eq!(spec.channels, 3);
+        assert_eq!(spec.bits_per_sample, 16);
+        assert_eq!(spec.sample_format, SampleFormat::Int);
+    }
+
+    #[test]
+    #[cfg(all(feature = "limiter", feature = "read"))]
+    fn test_true_peak_ceiling() {
+        use super::*;
+        use crate::reader::{AudioReadConfig, audio_read};
+        use audio_blocks::AudioBlockInterleavedView;
+
+        // a sine at a quarter of the sample rate, sampled 45 degrees off its peaks, has a true
+        // peak of 0.99 with samples of at most 0.7
+        let samples: Vec<f32> = (0..48000 * 2)
+            .map(|i| {
+                let phase =
+                    std::f32::consts::FRAC_PI_2 * (i / 2) as f32 + std::f32::consts::FRAC_PI_4;
+                0.99 * phase.sin()
+            })
+            .collect();
+        let true_peak = |samples: &[f32]| {
+            crate::limiter::true_peaks(samples, 2)
+                .into_iter()
+                .fold(0.0f64, f64::max)
+        };
+        let ceiling_dbfs = -3.0;
+        let ceiling = 10f64.powf(ceiling_dbfs / 20.0);
+        assert!(true_peak(&samples) > ceiling);
+
+        let block = AudioBlockInterleavedView::from_slice(&samples, 2, 48000);
+        let config = AudioWriteConfig::builder()
+            .true_peak_ceiling_dbfs(ceiling_dbfs)
+            .build()
+            .unwrap();
+        audio_write("tmp18.wav", block, 48000, config).unwrap();
+        let data = audio_read::<_, f32>("tmp18.wav", AudioReadConfig::default()).unwrap();
+        let _ = std::fs::remove_file("tmp18.wav");
+
+        assert_eq!(data.num_frames, 48000);
+        let peak = true_peak(&data.interleaved_samples);
+        assert!(peak <= ceiling && peak > ceiling * 0.95, "{peak}");
+
+        let config = AudioWriteConfig {
+            true_peak_ceiling_dbfs: Some(f64::NAN),
+            ..Default::default()
+        };
+        let block = AudioBlockInterleavedView::from_slice(&samples, 2, 48000);
+        assert!(matches!(
+            audio_write("tmp18.wav", block, 48000, config),
+            Err(AudioWriteError::InvalidTruePeakCeiling(_))
+        ));
+    }
+
+    #[test]
+    fn test_progress() {
+        use super::*;
+        use audio_blocks::AudioBlockInterleavedView;
+        use std::sync::{Arc, Mutex};
+
+        let samples = vec![0.25f32; 2 * 10000];
+        for sample_format in [WriteSampleFormat::Int16, WriteSampleFormat::Float32] {
+            let values = Arc::new(Mutex::new(Vec::new()));
+            let captured = values.clone();
+            let config = AudioWriteConfig::builder()
+                .sample_format(sample_format)
+                .progress(move |fraction| captured.lock().unwrap().push(fraction))
+                .build()
+                .unwrap();
+            let block = AudioBlockInterleavedView::from_slice(&samples, 2, 10000);
+            audio_write("tmp16.wav", block, 48000, config).unwrap();
+
+            let values = values.lock().unwrap();
+            assert_eq!(*values, [0.4096, 0.8192, 1.0]);
+        }
+        let _ = std::fs::remove_file("tmp16.wav");
+    }
+
+    #[test]
+    fn test_atomic() {
+        use super::*;
+        use audio_blocks::AudioBlockInterleavedView;
+
+        std::fs::create_dir_all("tmp_atomic").unwrap();
+        let target = Path::new("tmp_atomic/out.wav");
+        std::fs::write(target, b"original").unwrap();
+        let samples = vec![0.25f32; 2 * 10000];
+
+        // a write that is interrupted in the middle leaves the target as it was
+        let result = std::panic::catch_unwind(|| {
+            let config = AudioWriteConfig::builder()
+                .atomic(true)
+                .progress(|_| panic!("interrupted"))
+                .build()
+                .unwrap();
+            let block = AudioBlockInterleavedView::from_slice(&samples, 2, 10000);
+            audio_write(target, block, 48000, config)
+        });
+        assert!(result.is_err());
+        assert_eq!(std::fs::read(target).unwrap(), b"original");
+        assert_eq!(std::fs::read_dir("tmp_atomic").unwrap().count(), 1);
+
+        let config = AudioWriteConfig::builder().atomic(true).build().unwrap();
+        let block = AudioBlockInterleavedView::from_slice(&samples, 2, 10000);
+        audio_write(target, block, 48000, config).unwrap();
+        let written = std::fs::read(target).unwrap();
+        assert_eq!(&written[..4], b"RIFF");
+        assert_eq!(std::fs::read_dir("tmp_atomic").unwrap().count(), 1);
+
+        std::fs::remove_dir_all("tmp_atomic").unwrap();
+    }
+
     #[test]
     #[cfg(all(feature = "read", feature = "write"))]
     fn test_round_trip_f32() {
@@ -125,18 +1213,631 @@ mod tests {
             data1.sample_rate,
             AudioWriteConfig {
                 sample_format: WriteSampleFormat::Float32,
+                ..Default::default()
             },
         )
         .unwrap();
 
         let data2 = audio_read::<_, f32>("tmp2.wav", AudioReadConfig::default()).unwrap();
-        assert_eq!(data1.sample_rate, data2.sample_rate);
+        crate::test_util::assert_audio_eq(&data1, &data2, 1e-6);
+
+        let _ = std::fs::remove_file("tmp2.wav");
+    }
+
+    #[test]
+    fn test_invalid_four_cc() {
+        use super::*;
+
+        assert!(FourCC::new("iXML").is_ok());
+        assert!(matches!(
+            FourCC::new("XML"),
+            Err(AudioWriteError::InvalidFourCC(_))
+        ));
+        assert!(matches!(
+            FourCC::new("iXMLs"),
+            Err(AudioWriteError::InvalidFourCC(_))
+        ));
+        assert!(matches!(
+            FourCC::new("äXM"),
+            Err(AudioWriteError::InvalidFourCC(_))
+        ));
+    }
+
+    #[test]
+    fn test_extra_chunks() {
+        use super::*;
+
+        let samples = [0.0f32, 0.5, -0.5, 1.0];
+        let ixml = b"<BWFXML><PROJECT>test</PROJECT></BWFXML>".to_vec();
+        let odd = vec![1u8, 2, 3];
+
+        audio_write(
+            "tmp3.wav",
+            audio_blocks::AudioBlockInterleavedView::from_slice(&samples, 1, 4),
+            48000,
+            AudioWriteConfig {
+                extra_chunks: vec![
+                    (FourCC::new("iXML").unwrap(), ixml.clone()),
+                    (FourCC::new("odd ").unwrap(), odd.clone()),
+                ],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let chunks = read_chunks("tmp3.wav");
+        let _ = std::fs::remove_file("tmp3.wav");
+
+        let ids: Vec<_> = chunks.iter().map(|(id, _)| id.as_slice()).collect();
+        assert_eq!(ids, [b"fmt ", b"data", b"iXML", b"odd "]);
+        assert_eq!(chunks[2].1, ixml);
+        assert_eq!(chunks[3].1, odd);
+    }
+
+    #[test]
+    #[cfg(all(feature = "read", feature = "write"))]
+    fn test_clamp_range() {
+        use super::*;
+        use crate::reader::{AudioReadConfig, audio_read};
+
+        let samples = [0.0f32, 0.5, -0.5, 1.5, -1.5];
+        let block = || audio_blocks::AudioBlockInterleavedView::from_slice(&samples, 1, 5);
+
+        audio_write(
+            "tmp4.wav",
+            block(),
+            48000,
+            AudioWriteConfig {
+                clamp_range: Some((-0.25, 0.4)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let data = audio_read::<_, f32>("tmp4.wav", AudioReadConfig::default()).unwrap();
         approx::assert_abs_diff_eq!(
-            data1.audio_block().raw_data(),
-            data2.audio_block().raw_data(),
-            epsilon = 1e-6
+            data.interleaved_samples.as_slice(),
+            [0.0, 0.4, -0.25, 0.4, -0.25].as_slice(),
+            epsilon = 1e-4
         );
 
-        let _ = std::fs::remove_file("tmp2.wav");
+        // float samples are only clamped with an explicit range
+        audio_write(
+            "tmp4.wav",
+            block(),
+            48000,
+            AudioWriteConfig {
+                sample_format: WriteSampleFormat::Float32,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let data = audio_read::<_, f32>("tmp4.wav", AudioReadConfig::default()).unwrap();
+        assert_eq!(data.interleaved_samples, samples);
+
+        audio_write(
+            "tmp4.wav",
+            block(),
+            48000,
+            AudioWriteConfig {
+                sample_format: WriteSampleFormat::Float32,
+                clamp_range: Some((-2.0, 1.25)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let data = audio_read::<_, f32>("tmp4.wav", AudioReadConfig::default()).unwrap();
+        assert_eq!(data.interleaved_samples, [0.0, 0.5, -0.5, 1.25, -1.5]);
+
+        // a range above full scale limits integer samples to full scale
+        for dither in [Dither::None, Dither::Tpdf] {
+            audio_write(
+                "tmp4.wav",
+                block(),
+                48000,
+                AudioWriteConfig {
+                    clamp_range: Some((1.5, 2.0)),
+                    dither,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let data = audio_read::<_, f32>("tmp4.wav", AudioReadConfig::default()).unwrap();
+            // the dither changes the last bit
+            assert!(
+                data.interleaved_samples
+                    .iter()
+                    .all(|s| (s - 32767.0 / 32768.0).abs() <= 1.0 / 32768.0)
+            );
+        }
+
+        assert!(matches!(
+            audio_write(
+                "tmp4.wav",
+                block(),
+                48000,
+                AudioWriteConfig {
+                    clamp_range: Some((0.5, -0.5)),
+                    ..Default::default()
+                },
+            ),
+            Err(AudioWriteError::InvalidClampRange(_, _))
+        ));
+
+        let _ = std::fs::remove_file("tmp4.wav");
+    }
+
+    #[test]
+    fn test_fact_chunk() {
+        use super::*;
+
+        let samples = [0.0f32, 0.5, -0.5, 1.0, 0.25, -0.25];
+        let write = |sample_format| {
+            audio_write(
+                "tmp5.wav",
+                audio_blocks::AudioBlockInterleavedView::from_slice(&samples, 2, 3),
+                48000,
+                AudioWriteConfig {
+                    sample_format,
+                    extra_chunks: vec![(FourCC::new("iXML").unwrap(), vec![1, 2])],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let chunks = read_chunks("tmp5.wav");
+            let _ = std::fs::remove_file("tmp5.wav");
+            chunks
+        };
+
+        let chunks = write(WriteSampleFormat::Float32);
+        let ids: Vec<_> = chunks.iter().map(|(id, _)| id.as_slice()).collect();
+        assert_eq!(ids, [b"fmt ", b"data", b"fact", b"iXML"]);
+        assert_eq!(chunks[2].1, 3u32.to_le_bytes());
+
+        // PCM does not need a fact chunk
+        let chunks = write(WriteSampleFormat::Int16);
+        let ids: Vec<_> = chunks.iter().map(|(id, _)| id.as_slice()).collect();
+        assert_eq!(ids, [b"fmt ", b"data", b"iXML"]);
+    }
+
+    #[test]
+    #[cfg(all(feature = "read", feature = "write"))]
+    fn test_dither() {
+        use super::*;
+        use crate::dither::ShaperCurve;
+        use crate::reader::{AudioReadConfig, audio_read};
+
+        let samples: Vec<f32> = (0..4800).map(|n| 0.5 * (n as f32 * 0.01).sin()).collect();
+
+        for dither in [
+            Dither::Rectangular,
+            Dither::Tpdf,
+            Dither::NoiseShaped(ShaperCurve::SecondOrder),
+        ] {
+            audio_write(
+                "tmp6.wav",
+                audio_blocks::AudioBlockInterleavedView::from_slice(&samples, 1, 4800),
+                48000,
+                AudioWriteConfig {
+                    dither,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+            let data = audio_read::<_, f32>("tmp6.wav", AudioReadConfig::default()).unwrap();
+            assert_ne!(data.interleaved_samples, samples);
+            approx::assert_abs_diff_eq!(
+                data.interleaved_samples.as_slice(),
+                samples.as_slice(),
+                epsilon = 1e-3
+            );
+        }
+
+        let _ = std::fs::remove_file("tmp6.wav");
+    }
+
+    #[test]
+    #[cfg(all(feature = "read", feature = "write"))]
+    fn test_channel_selection() {
+        use audio_blocks::AudioBlockInterleavedView;
+
+        use super::*;
+        use crate::reader::{AudioReadConfig, audio_read};
+
+        // the tenths are the channel, the thousandths the frame
+        let samples: Vec<f32> = (0..100)
+            .flat_map(|frame| (0..6).map(move |ch| ch as f32 / 10.0 + frame as f32 / 1000.0))
+            .collect();
+        let block = || AudioBlockInterleavedView::from_slice(&samples, 6, 100);
+
+        let config = |start_channel, num_channels| AudioWriteConfig {
+            sample_format: WriteSampleFormat::Float32,
+            start_channel,
+            num_channels,
+            ..Default::default()
+        };
+        audio_write("tmp8.wav", block(), 48000, config(Some(1), Some(2))).unwrap();
+        let data = audio_read::<_, f32>("tmp8.wav", AudioReadConfig::default()).unwrap();
+        let _ = std::fs::remove_file("tmp8.wav");
+
+        assert_eq!(data.num_channels, 2);
+        assert_eq!(data.num_frames, 100);
+        for (frame, samples) in data.frames().enumerate() {
+            assert_eq!(
+                samples,
+                &[0.1 + frame as f32 / 1000.0, 0.2 + frame as f32 / 1000.0]
+            );
+        }
+
+        assert!(matches!(
+            audio_write("tmp8.wav", block(), 48000, config(Some(6), None)),
+            Err(AudioWriteError::InvalidStartChannel(6, 6))
+        ));
+        assert!(matches!(
+            audio_write("tmp8.wav", block(), 48000, config(Some(4), Some(3))),
+            Err(AudioWriteError::InvalidNumChannels(3))
+        ));
+        assert!(matches!(
+            audio_write(
+                "tmp8.wav",
+                block(),
+                48000,
+                config(Some(1), Some(usize::MAX))
+            ),
+            Err(AudioWriteError::InvalidNumChannels(usize::MAX))
+        ));
+        assert!(!Path::new("tmp8.wav").exists());
+    }
+
+    #[test]
+    fn test_big_endian() {
+        use audio_blocks::AudioBlockInterleavedView;
+
+        use super::*;
+
+        let samples = [0.5f32, -0.25, 0.125, -1.0];
+        for sample_format in [WriteSampleFormat::Int16, WriteSampleFormat::Float32] {
+            audio_write(
+                "tmp9.wav",
+                AudioBlockInterleavedView::from_slice(&samples, 2, 2),
+                44100,
+                AudioWriteConfig {
+                    sample_format,
+                    byte_order: ByteOrder::BigEndian,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let bytes = std::fs::read("tmp9.wav").unwrap();
+            let _ = std::fs::remove_file("tmp9.wav");
+
+            assert_eq!(&bytes[0..4], b"RIFX");
+            let riff_len = u32::from_be_bytes(bytes[4..8].try_into().unwrap()) as usize;
+            assert_eq!(riff_len + 8, bytes.len());
+            assert_eq!(&bytes[8..16], b"WAVEfmt ");
+
+            let fmt = &bytes[20..];
+            let channels = u16::from_be_bytes(fmt[2..4].try_into().unwrap());
+            let sample_rate = u32::from_be_bytes(fmt[4..8].try_into().unwrap());
+            assert_eq!((channels, sample_rate), (2, 44100));
+
+            let pos = bytes.windows(4).position(|id| id == b"data").unwrap();
+            let len = u32::from_be_bytes(bytes[pos + 4..pos + 8].try_into().unwrap()) as usize;
+            let data = &bytes[pos + 8..pos + 8 + len];
+            let decoded: Vec<f32> = match sample_format {
+                WriteSampleFormat::Int16 => data
+                    .chunks_exact(2)
+                    .map(|s| i16::from_be_bytes([s[0], s[1]]) as f32 / i16::MAX as f32)
+                    .collect(),
+                WriteSampleFormat::Float32 => data
+                    .chunks_exact(4)
+                    .map(|s| f32::from_be_bytes(s.try_into().unwrap()))
+                    .collect(),
+            };
+            approx::assert_abs_diff_eq!(decoded.as_slice(), samples.as_slice(), epsilon = 1e-4);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "read", feature = "write"))]
+    fn test_sanitize() {
+        use audio_blocks::AudioBlockInterleavedView;
+
+        use super::*;
+        use crate::reader::{AudioReadConfig, audio_read};
+
+        let samples = [
+            0.5f32,
+            f32::NAN,
+            f32::INFINITY,
+            -0.5,
+            f32::NEG_INFINITY,
+            0.25,
+        ];
+        let report = audio_write_with_report(
+            "tmp10.wav",
+            AudioBlockInterleavedView::from_slice(&samples, 2, 3),
+            48000,
+            AudioWriteConfig {
+                sample_format: WriteSampleFormat::Float32,
+                sanitize: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let data = audio_read::<_, f32>("tmp10.wav", AudioReadConfig::default()).unwrap();
+        let _ = std::fs::remove_file("tmp10.wav");
+
+        assert_eq!(
+            report,
+            WriteReport {
+                num_frames: 3,
+                sanitized_samples: 3,
+                clipped_samples: 0,
+                peak: 1.0,
+            }
+        );
+        assert_eq!(data.interleaved_samples, [0.5, 0.0, 1.0, -0.5, -1.0, 0.25]);
+    }
+
+    #[test]
+    fn test_builder() {
+        use super::*;
+
+        let config = AudioWriteConfig::builder()
+            .sample_format(WriteSampleFormat::Int16)
+            .dither(Dither::Tpdf)
+            .clamp_range(-0.5, 0.5)
+            .num_channels(2)
+            .extra_chunk(FourCC::new("iXML").unwrap(), b"<xml/>".to_vec())
+            .build()
+            .unwrap();
+        assert_eq!(config.dither, Dither::Tpdf);
+        assert_eq!(config.clamp_range, Some((-0.5, 0.5)));
+        assert_eq!(config.num_channels, Some(2));
+        assert_eq!(config.extra_chunks.len(), 1);
+
+        assert!(matches!(
+            AudioWriteConfig::builder().clamp_range(1.0, -1.0).build(),
+            Err(AudioWriteError::InvalidClampRange(..))
+        ));
+        assert!(matches!(
+            AudioWriteConfig::builder().num_channels(0).build(),
+            Err(AudioWriteError::InvalidNumChannels(0))
+        ));
+        assert!(matches!(
+            AudioWriteConfig::builder()
+                .preserve_source_format(true)
+                .channel_mask(0x3)
+                .build(),
+            Err(AudioWriteError::ConflictingOptions(
+                "preserve_source_format",
+                "channel_mask"
+            ))
+        ));
+        assert!(matches!(
+            AudioWriteConfig::builder()
+                .sample_format(WriteSampleFormat::Float32)
+                .dither(Dither::Tpdf)
+                .build(),
+            Err(AudioWriteError::ConflictingOptions(
+                "sample_format",
+                "dither"
+            ))
+        ));
+        // the sample format of the source decides if the dither is used
+        assert!(
+            AudioWriteConfig::builder()
+                .sample_format(WriteSampleFormat::Float32)
+                .dither(Dither::Tpdf)
+                .preserve_source_format(true)
+                .build()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_supported_write_formats() {
+        assert!(super::supported_write_formats().contains(&"wav"));
+    }
+
+    #[test]
+    #[cfg(all(feature = "read", feature = "resample"))]
+    fn test_target_sample_rate() {
+        use super::*;
+        use crate::reader::{AudioReadConfig, audio_read};
+
+        crate::test_util::write_test_tone("tmp12.wav", 48000, 2, 1000.0, 4800);
+        let source = audio_read::<_, f32>("tmp12.wav", AudioReadConfig::default()).unwrap();
+        let write = |target_sample_rate| {
+            let config = AudioWriteConfig {
+                sample_format: WriteSampleFormat::Float32,
+                target_sample_rate: Some(target_sample_rate),
+                ..Default::default()
+            };
+            audio_write_with_report("tmp12.wav", source.audio_block(), 48000, config).unwrap();
+            audio_read::<_, f32>("tmp12.wav", AudioReadConfig::default()).unwrap()
+        };
+
+        let resampled = write(44100);
+        assert_eq!(resampled.sample_rate, 44100);
+        assert_eq!(resampled.num_channels, 2);
+        assert_eq!(resampled.num_frames, 4410);
+
+        // the same rate writes the samples unchanged
+        let unchanged = write(48000);
+        std::fs::remove_file("tmp12.wav").unwrap();
+        assert_eq!(unchanged.sample_rate, 48000);
+        assert_eq!(unchanged.interleaved_samples, source.interleaved_samples);
+    }
+
+    #[test]
+    fn test_write_to_writer() {
+        use audio_blocks::AudioBlockInterleavedView;
+
+        use super::*;
+
+        let samples = [0.5f32, -0.25, 0.125, -1.0, 0.0, 0.75];
+        for byte_order in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let config = || AudioWriteConfig {
+                sample_format: WriteSampleFormat::Float32,
+                byte_order,
+                extra_chunks: vec![(FourCC::new("iXML").unwrap(), b"<x/>".to_vec())],
+                ..Default::default()
+            };
+            let block = || AudioBlockInterleavedView::from_slice(&samples, 2, 3);
+
+            let cursor =
+                audio_write_to_writer(std::io::Cursor::new(Vec::new()), block(), 48000, config())
+                    .unwrap();
+            audio_write("tmp23.wav", block(), 48000, config()).unwrap();
+            let expected = std::fs::read("tmp23.wav").unwrap();
+            let _ = std::fs::remove_file("tmp23.wav");
+            assert_eq!(cursor.into_inner(), expected);
+
+            // a reused buffer that is longer than the file keeps its bytes behind the file
+            let buffer = std::io::Cursor::new(vec![0xaa; 1000]);
+            let bytes = audio_write_to_writer(buffer, block(), 48000, config())
+                .unwrap()
+                .into_inner();
+            assert_eq!(bytes[..expected.len()], expected);
+            assert!(bytes[expected.len()..].iter().all(|byte| *byte == 0xaa));
+        }
+    }
+
+    #[test]
+    fn test_streaming_writer() {
+        use audio_blocks::AudioBlockInterleavedView;
+
+        use super::*;
+
+        let samples: Vec<f32> = (0..3000).map(|n| (n as f32 * 0.01).sin() * 0.3).collect();
+        let config = |sample_format| AudioWriteConfig {
+            sample_format,
+            dither: match sample_format {
+                WriteSampleFormat::Int16 => Dither::Tpdf,
+                WriteSampleFormat::Float32 => Dither::None,
+            },
+            extra_chunks: vec![(FourCC::new("iXML").unwrap(), b"<x/>".to_vec())],
+            ..Default::default()
+        };
+
+        // the same file as writing the whole audio at once
+        for sample_format in [WriteSampleFormat::Int16, WriteSampleFormat::Float32] {
+            let mut writer =
+                StreamingWavWriter::new("tmp24.wav", 48000, 2, config(sample_format)).unwrap();
+            for block in samples.chunks(700) {
+                writer
+                    .write_block(AudioBlockInterleavedView::from_slice(
+                        block,
+                        2,
+                        block.len() / 2,
+                    ))
+                    .unwrap();
+            }
+            assert_eq!(writer.num_frames(), 1500);
+            let report = writer.finalize().unwrap();
+            assert_eq!(report.num_frames, 1500);
+
+            let block = AudioBlockInterleavedView::from_slice(&samples, 2, 1500);
+            audio_write("tmp25.wav", block, 48000, config(sample_format)).unwrap();
+            assert_eq!(
+                std::fs::read("tmp24.wav").unwrap(),
+                std::fs::read("tmp25.wav").unwrap()
+            );
+        }
+        let _ = std::fs::remove_file("tmp24.wav");
+        let _ = std::fs::remove_file("tmp25.wav");
+
+        let mut writer =
+            StreamingWavWriter::<f32>::new("tmp24.wav", 48000, 2, AudioWriteConfig::default())
+                .unwrap();
+        assert!(matches!(
+            writer.write_block(AudioBlockInterleavedView::from_slice(&[0.0; 3], 3, 1)),
+            Err(AudioWriteError::ChannelCountMismatch(3, 2))
+        ));
+        drop(writer);
+
+        // the progress is reported after each block if the length is known
+        let captured = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let config = {
+            let captured = captured.clone();
+            AudioWriteConfig::builder()
+                .progress(move |fraction| captured.lock().unwrap().push(fraction))
+                .build()
+                .unwrap()
+        };
+        let mut writer = StreamingWavWriter::new("tmp24.wav", 48000, 2, config)
+            .unwrap()
+            .with_expected_frames(1500);
+        for block in samples.chunks(1000) {
+            writer
+                .write_block(AudioBlockInterleavedView::from_slice(
+                    block,
+                    2,
+                    block.len() / 2,
+                ))
+                .unwrap();
+        }
+        assert_eq!(*captured.lock().unwrap(), [1.0 / 3.0, 2.0 / 3.0]);
+        writer.finalize().unwrap();
+        assert_eq!(*captured.lock().unwrap(), [1.0 / 3.0, 2.0 / 3.0, 1.0]);
+
+        assert!(matches!(
+            StreamingWavWriter::<f32>::new("tmp24.wav", 48000, 70000, AudioWriteConfig::default()),
+            Err(AudioWriteError::InvalidNumChannels(70000))
+        ));
+        let _ = std::fs::remove_file("tmp24.wav");
+    }
+
+    #[test]
+    fn test_clipped_samples() {
+        use audio_blocks::AudioBlockInterleavedView;
+
+        use super::*;
+
+        let samples = [0.5, 1.5, -2.0, -1.0, 1.0, 0.25];
+        let write = |config: AudioWriteConfig| {
+            // f32 is converted in blocks, f64 sample by sample
+            let report = audio_write_with_report(
+                "tmp26.wav",
+                AudioBlockInterleavedView::from_slice(&samples.map(|s| s as f32), 2, 3),
+                48000,
+                AudioWriteConfig {
+                    sample_format: config.sample_format,
+                    clamp_range: config.clamp_range,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let report_f64 = audio_write_with_report(
+                "tmp26.wav",
+                AudioBlockInterleavedView::from_slice(&samples, 2, 3),
+                48000,
+                config,
+            )
+            .unwrap();
+            let _ = std::fs::remove_file("tmp26.wav");
+            assert_eq!(report, report_f64);
+            report
+        };
+
+        let report = write(AudioWriteConfig::default());
+        assert_eq!(report.clipped_samples, 2);
+        assert_eq!(report.peak, 2.0);
+
+        // float samples are only clipped with a clamp range
+        let report = write(AudioWriteConfig {
+            sample_format: WriteSampleFormat::Float32,
+            ..Default::default()
+        });
+        assert_eq!(report.clipped_samples, 0);
+        assert_eq!(report.peak, 2.0);
+        let report = write(AudioWriteConfig {
+            sample_format: WriteSampleFormat::Float32,
+            clamp_range: Some((-0.5, 0.5)),
+            ..Default::default()
+        });
+        assert_eq!(report.clipped_samples, 4);
     }
 }
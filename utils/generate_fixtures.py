@@ -0,0 +1,387 @@
+#!/usr/bin/env python3
+"""Generate the test fixtures that can't be written with hound.
+
+Only uses the python standard library, so the files can be regenerated without any encoder
+installed. FLAC streams only use verbatim subframes, which is valid but uncompressed.
+
+Run from the repository root:
+
+    python3 utils/generate_fixtures.py
+"""
+
+import struct
+import wave
+
+OUT_DIR = "test_data"
+
+
+def crc8(data):
+    crc = 0
+    for byte in data:
+        crc ^= byte
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x07) & 0xFF if crc & 0x80 else (crc << 1) & 0xFF
+    return crc
+
+
+def crc16(data):
+    crc = 0
+    for byte in data:
+        crc ^= byte << 8
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x8005) & 0xFFFF if crc & 0x8000 else (crc << 1) & 0xFFFF
+    return crc
+
+
+def crc32_ogg(data):
+    crc = 0
+    for byte in data:
+        crc ^= byte << 24
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x04C11DB7) & 0xFFFFFFFF if crc & 0x80000000 else (crc << 1) & 0xFFFFFFFF
+    return crc
+
+
+def utf8_number(n):
+    """FLAC uses the UTF-8 scheme to code frame numbers."""
+    if n < 0x80:
+        return bytes([n])
+    if n < 0x800:
+        return bytes([0xC0 | (n >> 6), 0x80 | (n & 0x3F)])
+    if n < 0x10000:
+        return bytes([0xE0 | (n >> 12), 0x80 | ((n >> 6) & 0x3F), 0x80 | (n & 0x3F)])
+    raise ValueError("frame number too large")
+
+
+def flac_streaminfo(sample_rate, channels, bits, total_frames, block_size):
+    info = struct.pack(">HH", block_size, block_size)
+    info += b"\x00\x00\x00\x00\x00\x00"  # unknown min/max frame size
+    packed = (sample_rate << 44) | ((channels - 1) << 41) | ((bits - 1) << 36) | total_frames
+    info += packed.to_bytes(8, "big")
+    info += b"\x00" * 16  # md5 not computed
+    return info
+
+
+def flac_vorbis_comment(vendor, tags):
+    data = struct.pack("<I", len(vendor)) + vendor.encode()
+    data += struct.pack("<I", len(tags))
+    for key, value in tags:
+        entry = f"{key}={value}".encode()
+        data += struct.pack("<I", len(entry)) + entry
+    return data
+
+
+def flac_metadata_block(block_type, data, last):
+    return bytes([(0x80 if last else 0) | block_type]) + len(data).to_bytes(3, "big") + data
+
+
+def flac_frames(channels_data, bits, block_size):
+    """Encode the samples (one list per channel) into FLAC frames with verbatim subframes."""
+    assert bits in (16, 24)
+    num_frames = len(channels_data[0])
+    frames = []
+    for number, start in enumerate(range(0, num_frames, block_size)):
+        size = min(block_size, num_frames - start)
+        if size == 4096:
+            size_code, size_extra = 12, b""
+        else:
+            size_code, size_extra = 7, struct.pack(">H", size - 1)
+        bits_code = {16: 4, 24: 6}[bits]
+        header = bytes([0xFF, 0xF8, (size_code << 4) | 0, ((len(channels_data) - 1) << 4) | (bits_code << 1)])
+        header += utf8_number(number) + size_extra
+        header += bytes([crc8(header)])
+        body = b""
+        for channel in channels_data:
+            body += b"\x02"  # verbatim subframe, no wasted bits
+            for sample in channel[start : start + size]:
+                body += (sample & ((1 << bits) - 1)).to_bytes(bits // 8, "big")
+        frame = header + body
+        frames.append(frame + struct.pack(">H", crc16(frame)))
+    return frames
+
+
+def id3v2_tag(frames):
+    """ID3v2.3 tag with ISO-8859-1 text frames."""
+    data = b""
+    for frame_id, text in frames:
+        body = b"\x00" + text.encode("latin-1")
+        data += frame_id.encode("ascii") + struct.pack(">IH", len(body), 0) + body
+    # the tag size is a syncsafe integer with 7 bits per byte
+    size = bytes((len(data) >> shift) & 0x7F for shift in (21, 14, 7, 0))
+    return b"ID3\x03\x00\x00" + size + data
+
+
+def write_flac(path, channels_data, sample_rate, bits, tags, block_size=4096, prefix=b""):
+    streaminfo = flac_streaminfo(sample_rate, len(channels_data), bits, len(channels_data[0]), block_size)
+    comment = flac_vorbis_comment("audio-io fixture generator", tags)
+    with open(path, "wb") as f:
+        f.write(prefix)
+        f.write(b"fLaC")
+        f.write(flac_metadata_block(0, streaminfo, False))
+        f.write(flac_metadata_block(4, comment, True))
+        for frame in flac_frames(channels_data, bits, block_size):
+            f.write(frame)
+
+
+def ogg_page(serial, sequence, granule, packets, bos=False, eos=False):
+    lacing = b""
+    for packet in packets:
+        lacing += b"\xff" * (len(packet) // 255) + bytes([len(packet) % 255])
+    assert len(lacing) <= 255
+    header_type = (0x02 if bos else 0) | (0x04 if eos else 0)
+    page = b"OggS" + bytes([0, header_type]) + struct.pack("<qII", granule, serial, sequence)
+    page += b"\x00\x00\x00\x00" + bytes([len(lacing)]) + lacing + b"".join(packets)
+    crc = crc32_ogg(page)
+    return page[:22] + struct.pack("<I", crc) + page[26:]
+
+
+def ogg_flac_pages(serial, channels_data, sample_rate, bits, block_size=4096):
+    """Pages of an Ogg FLAC logical stream, one FLAC frame per page."""
+    streaminfo = flac_streaminfo(sample_rate, len(channels_data), bits, len(channels_data[0]), block_size)
+    identification = b"\x7fFLAC\x01\x00\x00\x01fLaC" + flac_metadata_block(0, streaminfo, False)
+    comment = flac_metadata_block(4, flac_vorbis_comment("audio-io fixture generator", []), True)
+    pages = [
+        ogg_page(serial, 0, 0, [identification], bos=True),
+        ogg_page(serial, 1, 0, [comment]),
+    ]
+    frames = flac_frames(channels_data, bits, block_size)
+    granule = 0
+    for i, frame in enumerate(frames):
+        granule = min(granule + block_size, len(channels_data[0]))
+        pages.append(ogg_page(serial, i + 2, granule, [frame], eos=i == len(frames) - 1))
+    return pages
+
+
+def ogg_flac_stream(serial, channels_data, sample_rate, bits, block_size=4096):
+    """A complete Ogg FLAC logical stream."""
+    return b"".join(ogg_flac_pages(serial, channels_data, sample_rate, bits, block_size))
+
+
+def ogg_multiplex(streams):
+    """Group logical streams (lists of pages) into one physical stream.
+
+    The identification pages of all streams come first, followed by the comment pages, followed
+    by the interleaved audio pages.
+    """
+    pages = [stream[0] for stream in streams] + [stream[1] for stream in streams]
+    data = [stream[2:] for stream in streams]
+    for i in range(max(len(d) for d in data)):
+        pages += [d[i] for d in data if i < len(d)]
+    return b"".join(pages)
+
+
+def ogg_opus_pages(serial):
+    """Pages of a short Ogg Opus stream, which symphonia can demux but has no decoder for."""
+    head = b"OpusHead" + struct.pack("<BBHIhB", 1, 1, 312, 48000, 0, 0)
+    tags = b"OpusTags" + struct.pack("<I", 26) + b"audio-io fixture generator" + struct.pack("<I", 0)
+    silence = b"\xf8\xff\xfe"  # a single 20 ms CELT frame
+    return [
+        ogg_page(serial, 0, 0, [head], bos=True),
+        ogg_page(serial, 1, 0, [tags]),
+        ogg_page(serial, 2, 960, [silence], eos=True),
+    ]
+
+
+def ogg_opus_stream(serial):
+    return b"".join(ogg_opus_pages(serial))
+
+
+def caf_chunk(chunk_type, data):
+    return chunk_type + struct.pack(">q", len(data)) + data
+
+
+def caf_marker(marker_type, frame, marker_id):
+    # no SMPTE time, channel 0 (all channels)
+    return marker_type + struct.pack(">dI", frame, marker_id) + b"\x00" * 8 + struct.pack(">H", 0)
+
+
+def write_caf(path, samples, sample_rate, markers, loops):
+    """16-bit big endian mono CAF with named markers and loop regions.
+
+    `markers` is a list of (name, frame), `loops` a list of (start_frame, end_frame).
+    """
+    desc = struct.pack(">d4sIIIII", sample_rate, b"lpcm", 0, 2, 1, 1, 16)
+
+    names = b""
+    string_ids = b""
+    for i, (name, _) in enumerate(markers):
+        string_ids += struct.pack(">Iq", i + 1, len(names))
+        names += name.encode() + b"\x00"
+    strg = struct.pack(">I", len(markers)) + string_ids + names
+
+    mark = struct.pack(">II", 0, len(markers))
+    for i, (_, frame) in enumerate(markers):
+        mark += caf_marker(b"\x00\x00\x00\x00", frame, i + 1)
+
+    regn = struct.pack(">II", 0, len(loops))
+    for i, (start, end) in enumerate(loops):
+        # loop enabled and play forward
+        regn += struct.pack(">III", i + 1, 0x3, 2)
+        regn += caf_marker(b"rbeg", start, 0) + caf_marker(b"rend", end, 0)
+
+    data = struct.pack(">I", 0) + b"".join(struct.pack(">h", s) for s in samples)
+
+    with open(path, "wb") as f:
+        f.write(b"caff" + struct.pack(">HH", 1, 0))
+        f.write(caf_chunk(b"desc", desc))
+        f.write(caf_chunk(b"strg", strg))
+        f.write(caf_chunk(b"mark", mark))
+        f.write(caf_chunk(b"regn", regn))
+        f.write(caf_chunk(b"data", data))
+
+
+def write_wav_smpl(path, samples, sample_rate, unity_note, loops):
+    """16-bit mono WAV with a sampler chunk.
+
+    `loops` is a list of (start_frame, end_frame), the end is exclusive like in the reader.
+    """
+    fmt = struct.pack("<HHIIHH", 1, 1, sample_rate, sample_rate * 2, 2, 16)
+    # manufacturer, product, sample period, unity note, pitch fraction, SMPTE format and offset
+    smpl = struct.pack("<IIIIIII", 0, 0, 1_000_000_000 // sample_rate, unity_note, 0, 0, 0)
+    smpl += struct.pack("<II", len(loops), 0)
+    for i, (start, end) in enumerate(loops):
+        # the end in the file is the last frame of the loop
+        smpl += struct.pack("<IIIIII", i, 0, start, end - 1, 0, 0)
+    data = b"".join(struct.pack("<h", s) for s in samples)
+    chunks = b"fmt " + struct.pack("<I", len(fmt)) + fmt
+    chunks += b"smpl" + struct.pack("<I", len(smpl)) + smpl
+    chunks += b"data" + struct.pack("<I", len(data)) + data
+    with open(path, "wb") as f:
+        f.write(b"RIFF" + struct.pack("<I", 4 + len(chunks)) + b"WAVE" + chunks)
+
+
+W64_RIFF_GUID = bytes.fromhex("726966662e91cf11a5d628db04c10000")
+W64_WAVE_GUID = bytes.fromhex("77617665f3acd3118cd100c04f8edb8a")
+W64_FMT_GUID = bytes.fromhex("666d7420f3acd3118cd100c04f8edb8a")
+W64_DATA_GUID = bytes.fromhex("64617461f3acd3118cd100c04f8edb8a")
+
+
+def w64_chunk(guid, data):
+    """Wave64 chunk, the size includes the 24 byte header and chunks are aligned to 8 bytes."""
+    padding = b"\x00" * (-len(data) % 8)
+    return guid + struct.pack("<Q", 24 + len(data)) + data + padding
+
+
+def write_w64(path, channels_data, sample_rate):
+    """16-bit PCM Wave64 file."""
+    channels = len(channels_data)
+    fmt = struct.pack("<HHIIHH", 1, channels, sample_rate, sample_rate * 2 * channels, 2 * channels, 16)
+    data = b"".join(struct.pack("<h", s) for frame in zip(*channels_data) for s in frame)
+    chunks = w64_chunk(W64_FMT_GUID, fmt) + w64_chunk(W64_DATA_GUID, data)
+    with open(path, "wb") as f:
+        f.write(W64_RIFF_GUID + struct.pack("<Q", 40 + len(chunks)) + W64_WAVE_GUID + chunks)
+
+
+def write_w64_extensible(path, channels_data, sample_rate, channel_mask):
+    """Extensible PCM Wave64 file with 24 valid bits in 32-bit containers."""
+    channels = len(channels_data)
+    fmt = struct.pack("<HHIIHH", 0xFFFE, channels, sample_rate, sample_rate * 4 * channels, 4 * channels, 32)
+    # the PCM sub format GUID
+    fmt += struct.pack("<HHI", 22, 24, channel_mask) + bytes.fromhex("0100000000001000800000aa00389b71")
+    # the 24 valid bits are the most significant bits of the container
+    data = b"".join(struct.pack("<i", s << 16) for frame in zip(*channels_data) for s in frame)
+    chunks = w64_chunk(W64_FMT_GUID, fmt) + w64_chunk(W64_DATA_GUID, data)
+    with open(path, "wb") as f:
+        f.write(W64_RIFF_GUID + struct.pack("<Q", 40 + len(chunks)) + W64_WAVE_GUID + chunks)
+
+
+def read_wav_16bit(path):
+    with wave.open(path, "rb") as w:
+        assert w.getsampwidth() == 2
+        channels = w.getnchannels()
+        raw = w.readframes(w.getnframes())
+        samples = struct.unpack(f"<{len(raw) // 2}h", raw)
+        return [list(samples[c::channels]) for c in range(channels)], w.getframerate()
+
+
+def main():
+    # same audio as test_1ch.wav, with some vorbis comments
+    data, sample_rate = read_wav_16bit(f"{OUT_DIR}/test_1ch.wav")
+    write_flac(
+        f"{OUT_DIR}/test_1ch.flac",
+        data,
+        sample_rate,
+        16,
+        [
+            ("TITLE", "Test Signal"),
+            ("artist", "neodsp"),
+            ("Album", "audio-io fixtures"),
+            ("ENCODER", "audio-io fixture generator"),
+            ("DATE", "2025-10-27"),
+        ],
+    )
+
+    # two chained Ogg FLAC streams, which together contain a continuous ramp
+    ramp = [n - 8192 for n in range(16384)]
+    with open(f"{OUT_DIR}/test_chained.ogg", "wb") as f:
+        f.write(ogg_flac_stream(1, [ramp[:8192]], 48000, 16))
+        f.write(ogg_flac_stream(2, [ramp[8192:]], 48000, 16))
+
+    # two tracks in one file: a mono ramp and a stereo track with constant values
+    ramp = [n - 4096 for n in range(8192)]
+    with open(f"{OUT_DIR}/test_multitrack.ogg", "wb") as f:
+        f.write(
+            ogg_multiplex(
+                [
+                    ogg_flac_pages(1, [ramp], 48000, 16),
+                    ogg_flac_pages(2, [[1000] * 8192, [-1000] * 8192], 48000, 16),
+                ]
+            )
+        )
+
+    # two chained links with a mono ramp and a stereo track each, the stereo track has the
+    # values 1000 and -1000 in the first link and 2000 and -2000 in the second
+    with open(f"{OUT_DIR}/test_chained_multitrack.ogg", "wb") as f:
+        for link in range(2):
+            level = 1000 * (link + 1)
+            f.write(
+                ogg_multiplex(
+                    [
+                        ogg_flac_pages(2 * link + 1, [ramp[4096 * link :][:4096]], 48000, 16, 1024),
+                        ogg_flac_pages(2 * link + 2, [[level] * 4096, [-level] * 4096], 48000, 16, 1024),
+                    ]
+                )
+            )
+
+    with open(f"{OUT_DIR}/test_opus.ogg", "wb") as f:
+        f.write(ogg_opus_stream(1))
+
+    # a lossy Opus track in front of a lossless FLAC track with a ramp
+    with open(f"{OUT_DIR}/test_lossless.ogg", "wb") as f:
+        f.write(ogg_multiplex([ogg_opus_pages(1), ogg_flac_pages(2, [ramp], 48000, 16)]))
+
+    # the first 4800 frames of test_1ch.wav with two markers and a loop region
+    data, sample_rate = read_wav_16bit(f"{OUT_DIR}/test_1ch.wav")
+    write_caf(
+        f"{OUT_DIR}/test_loop.caf",
+        data[0][:4800],
+        sample_rate,
+        [("Intro", 1000), ("Drop", 3000)],
+        [(1200, 3600)],
+    )
+
+    # same audio as test_1ch.wav in a Wave64 file
+    data, sample_rate = read_wav_16bit(f"{OUT_DIR}/test_1ch.wav")
+    write_w64(f"{OUT_DIR}/test_1ch.w64", data, sample_rate)
+
+    # the first 4800 frames of test_1ch.wav and the inverted signal as side left and right,
+    # with 24 valid bits in 32-bit containers
+    inverted = [-s for s in data[0][:4800]]
+    write_w64_extensible(f"{OUT_DIR}/test_24in32.w64", [data[0][:4800], inverted], sample_rate, 0x600)
+
+    # the first 4800 frames of test_1ch.wav with a root note and a loop region
+    write_wav_smpl(f"{OUT_DIR}/test_smpl.wav", data[0][:4800], sample_rate, 60, [(1200, 3600)])
+
+    # the first 4800 frames of test_1ch.wav as FLAC with an ID3v2 tag in front
+    write_flac(
+        f"{OUT_DIR}/test_id3.flac",
+        [data[0][:4800]],
+        sample_rate,
+        16,
+        [],
+        prefix=id3v2_tag([("TIT2", "ID3 Title"), ("TPE1", "ID3 Artist"), ("TALB", "ID3 Album")]),
+    )
+
+
+if __name__ == "__main__":
+    main()